@@ -1,32 +1,179 @@
 mod modules;
 
-use modules::services::audio_folder_service::AudioFolderService;
+use modules::services::audio_folder_service::{AudioFolderService, ScanOptions};
 use modules::services::audio_loader::{AudioLoader, DynamicAudioLoader};
+use modules::services::bookmark_service::BookmarkService;
+use modules::services::history_service::HistoryService;
+use modules::services::library_service::LibraryService;
+use modules::services::log_service::{LogConfig, LogService};
+use modules::services::session_service::SessionService;
+use modules::services::smart_playlist_service::SmartPlaylistService;
+use modules::services::watch_service::WatchService;
 
-use modules::controllers::player_controller::PlayerController;
-use modules::models::audio_folder_model::AudioFolderModel;
-use modules::views::cli_view::CliView;
+use modules::controllers::async_player_controller::AsyncPlayerController;
+use modules::models::bookmark_model::BookmarkModel;
+use modules::models::session_model::SessionModel;
+use modules::views::cli_view::{CliContext, CliView};
 use std::env;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// How often the session, bookmarks and history renames are saved while the player runs, so
+/// a crash or Ctrl-C loses at most this much.
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
 
 fn main() {
     env::set_var("RUST_BACKTRACE", "1");
 
-    // Create an instance of AudioFolderService.
-    let audio_folder_service = AudioFolderService;
+    // Set up logging first, configured through SENSIT_LOG (filter) and SENSIT_LOG_FILE (target).
+    if let Err(err) = LogService::init(LogConfig::from_env()) {
+        eprintln!("⚠️  Logging disabled: {}", err);
+    }
+
+    // Create an instance of AudioFolderService, configured through the SENSIT_SCAN_* variables.
+    let audio_folder_service = AudioFolderService::new(ScanOptions::from_env());
+
+    // Load the music library (SENSIT_LIBRARY), so folders already scanned are not read again.
+    let library_service = LibraryService::from_env();
+    let mut library = library_service.load().unwrap_or_else(|err| {
+        eprintln!("⚠️  Starting with an empty library: {}", err);
+        Default::default()
+    });
+
+    // Load the bookmarks and resume points of long files (SENSIT_BOOKMARKS, SENSIT_RESUME_AFTER).
+    let bookmark_service = BookmarkService::from_env();
+    let bookmarks = bookmark_service.load().unwrap_or_else(|err| {
+        eprintln!("⚠️  Starting without bookmarks: {}", err);
+        BookmarkModel::new(bookmark_service.resume_after())
+    });
+
+    // Every playback is recorded in the play history (SENSIT_HISTORY).
+    let history_service = HistoryService::from_env();
+
+    // Offer to resume the last session (SENSIT_SESSION) if its folder or playlist still opens.
+    let session_service = SessionService::from_env();
+    let resumed = CliView::offer_session(&session_service).and_then(|session| {
+        let opened = CliView::open_source(
+            &audio_folder_service,
+            &library_service,
+            &mut library,
+            &session.source,
+        );
+        opened.map(|opened| (session, opened))
+    });
 
-    // Use the service to read a valid folder input from the user.
-    let audio_files = CliView::read_folder_input(&audio_folder_service);
+    // Step 3: Otherwise use the service to read a valid folder (or playlist) input from the user into an AudioFolderModel.
+    let (session, (audio_model, source)) = match resumed {
+        Some((session, opened)) => (Some(session), opened),
+        None => (
+            None,
+            CliView::read_folder_input(&audio_folder_service, &library_service, &mut library),
+        ),
+    };
 
-    // Step 3: Create an instance of AudioModel with the loaded files
-    let audio_model = AudioFolderModel::new(audio_files);
+    // Watch the chosen folder, so files added, deleted or renamed there show up in the track list.
+    let root = Some(source.clone()).filter(|source| source.is_dir());
+    let watcher = root.and_then(|root| match WatchService::start(vec![root]) {
+        Ok(watcher) => Some(watcher),
+        Err(err) => {
+            eprintln!("⚠️  Not watching the folder for changes: {}", err);
+            None
+        }
+    });
 
     // Step 4: Create an instance of DynamicAudioLoader wrapped in Box
     // I used a trait instead of a concrete type to make the code more expandable and to follow DI principles, reducing coupling between components and avoiding direct dependencies.
     let audio_loader: Box<dyn AudioLoader> = Box::new(DynamicAudioLoader);
 
-    // Step 5: Pass the audio loader and the audio model into the PlayerController
-    let mut player_controller = PlayerController::new(audio_model, audio_loader);
+    // Step 5: Pass the audio loader and the audio model into the PlayerController, which runs on its own thread.
+    // A resumed session is handed over right away, so only its track is decoded.
+    let player = AsyncPlayerController::spawn(
+        audio_model,
+        audio_loader,
+        bookmarks,
+        history_service.clone(),
+        session,
+    );
+
+    // Step 6: Start the CLI interface, saving the session, bookmarks and history now and then meanwhile.
+    let (quit, quitting) = mpsc::channel::<()>();
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            autosave(
+                quitting,
+                &player,
+                &source,
+                &session_service,
+                &bookmark_service,
+                &history_service,
+            )
+        });
+        CliView::start_cli(CliContext {
+            player: &player,
+            library: &mut library,
+            library_service: &library_service,
+            audio_folder_service: &audio_folder_service,
+            bookmark_service: &bookmark_service,
+            history_service: &history_service,
+            smart_playlist_service: &SmartPlaylistService::from_env(), // SENSIT_SMART_PLAYLISTS
+            watcher: watcher.as_ref(),
+        });
+        drop(quit);
+    });
+
+    // Keep where playback is, so the next start can pick up there.
+    save_state(
+        &player,
+        &source,
+        &session_service,
+        &bookmark_service,
+        &history_service,
+    );
+}
+
+/// Calls `save_state` every `AUTOSAVE_INTERVAL` until `quitting` is disconnected.
+fn autosave(
+    quitting: Receiver<()>,
+    player: &AsyncPlayerController,
+    source: &Path,
+    session_service: &SessionService,
+    bookmark_service: &BookmarkService,
+    history_service: &HistoryService,
+) {
+    while let Err(RecvTimeoutError::Timeout) = quitting.recv_timeout(AUTOSAVE_INTERVAL) {
+        save_state(
+            player,
+            source,
+            session_service,
+            bookmark_service,
+            history_service,
+        );
+    }
+}
 
-    // Step 6: Start the CLI interface
-    CliView::start_cli(&mut player_controller);
+/// Saves where playback is, the bookmarks and the renamed tracks of the history, so the
+/// next start can pick up there.
+fn save_state(
+    player: &AsyncPlayerController,
+    source: &Path,
+    session_service: &SessionService,
+    bookmark_service: &BookmarkService,
+    history_service: &HistoryService,
+) {
+    if let Err(err) = history_service.save() {
+        eprintln!("⚠️  Could not save the history: {}", err);
+    }
+    if let Some(status) = player.status() {
+        let session = SessionModel::from_status(source.to_path_buf(), &status);
+        if let Err(err) = session_service.save(&session) {
+            eprintln!("⚠️  Could not save the session: {}", err);
+        }
+    }
+    if let Some(bookmarks) = player.bookmarks() {
+        if let Err(err) = bookmark_service.save(&bookmarks) {
+            eprintln!("⚠️  Could not save the bookmarks: {}", err);
+        }
+    }
 }
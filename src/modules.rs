@@ -2,6 +2,7 @@
 
 // Declare the controllers module
 pub mod controllers {
+    pub mod async_player_controller; // Expose the threaded, command-driven player
     pub mod player_controller; // Expose player controller
 }
 
@@ -9,16 +10,58 @@ pub mod controllers {
 pub mod services {
     pub mod audio_folder_service;
     pub mod audio_loader; // Expose audio loader
+    pub mod bookmark_service; // Expose saving and loading of bookmarks and resume points
+    pub mod cue_service; // Expose CUE sheet parsing
+    pub mod event_bus; // Expose the publish/subscribe channel for player events
+    pub mod flac_loader; // Expose flac loader
+    pub mod history_service; // Expose the play history store
+    pub mod library_service; // Expose the persistent music library
+    pub mod log_service; // Expose the logging backend (levels, module filters, log file)
+    pub mod metadata_service; // Expose reading of tags and durations
     pub mod mp3_loader; // Expose mp3 loader
+    pub mod playback_handle; // Expose the handle shared between controller and audio callback
+    pub mod playlist_service; // Expose playlist import and export
+    pub mod preload_service; // Expose background decoding of upcoming tracks
+    pub mod session_service; // Expose saving and restoring of the playback session
+    pub mod smart_playlist_service; // Expose the smart playlist definition files
+    pub mod state_file; // Expose the paths, atomic writes and escaping shared by the state files
+    pub mod symphonia_loader; // Expose the symphonia decoding shared by the mp3 and flac loaders
+    pub mod tag_service; // Expose writing of tags back to audio files
+    #[cfg(test)]
+    pub mod temp_folder; // Expose self-removing temporary folders for tests
+    pub mod watch_service; // Expose folder watching for live track list updates
     pub mod wav_loader; // Expose wav loader
 }
 
 // Declare the models module
 pub mod models {
+    pub mod artwork_model; // Expose the cover art model
     pub mod audio_folder_model; // Expose the audio model, It can be renamed to album
+    pub mod bookmark_model; // Expose bookmarks and resume points of long files
+    pub mod browse_model; // Expose library browsing by artist, album, genre and year
+    pub mod crossfade_model; // Expose crossfade settings and fade curves
+    pub mod cue_model; // Expose CUE sheet tracks
+    pub mod folder_change_model; // Expose changes reported by the folder watcher
+    pub mod history_model; // Expose the play history and per-track play counts
+    pub mod library_model; // Expose the music library entries
+    pub mod player_event_model; // Expose events reported by the player
+    pub mod player_status_model; // Expose the player state snapshot for views
+    pub mod playlist_model; // Expose playlist entries
+    pub mod queue_model; // Expose the play queue
+    pub mod search_model; // Expose library search queries
+    pub mod session_model; // Expose the playback session kept across restarts
+    pub mod smart_playlist_model; // Expose rule-based playlists evaluated against the library
+    pub mod track_metadata_model; // Expose the metadata of a track
 }
 
 // Declare the views module
 pub mod views {
+    pub mod artwork_view; // Expose the terminal renderer for cover art
+    pub mod bookmark_view; // Expose the bookmark and resume commands of the CLI
+    pub mod browse_view; // Expose the library browsing commands of the CLI
     pub mod cli_view; // Expose CLI view logic
+    pub mod history_view; // Expose the play history commands of the CLI
+    pub mod rating_view; // Expose the rating and favourite commands of the CLI
+    pub mod search_view; // Expose the library search commands of the CLI
+    pub mod smart_view; // Expose the smart playlist commands of the CLI
 }
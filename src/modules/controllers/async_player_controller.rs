@@ -0,0 +1,424 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::modules::controllers::player_controller::PlayerController;
+use crate::modules::models::audio_folder_model::{AudioFolderModel, SortKey};
+use crate::modules::models::bookmark_model::{Bookmark, BookmarkModel};
+use crate::modules::models::crossfade_model::CrossfadeModel;
+use crate::modules::models::player_event_model::Event;
+use crate::modules::models::player_status_model::PlayerStatus;
+use crate::modules::models::session_model::SessionModel;
+use crate::modules::models::track_metadata_model::TrackMetadata;
+use crate::modules::services::audio_loader::AudioLoader;
+use crate::modules::services::event_bus::EventBus;
+use crate::modules::services::history_service::HistoryService;
+
+/// How long the player thread waits for a command before doing its background work.
+const TICK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// `Command` is one request sent to the player thread.
+#[derive(Clone, Debug)]
+pub enum Command {
+    Play,
+    Pause,
+    TogglePlay,
+    Stop,
+    Next,
+    Prev,
+    /// Jump to an absolute position in the current track.
+    Seek(Duration),
+    /// Jump by a number of seconds relative to the current position (negative = backwards).
+    SeekBy(f64),
+    ToggleShuffle,
+    CycleRepeat,
+    /// Reorder the folder tracks.
+    Sort(SortKey),
+    /// Replace the metadata of a file after its tags were edited.
+    UpdateMetadata(PathBuf, TrackMetadata),
+    /// Add a file (a search result) to the folder tracks.
+    AddTrack(PathBuf, TrackMetadata),
+    /// Add a file to the folder tracks if needed and play it.
+    PlayTrack(PathBuf, TrackMetadata),
+    /// Replace the folder tracks (with an album, ...) and play its current track.
+    PlayTracks(AudioFolderModel),
+    /// Remove the tracks of a deleted file or folder.
+    RemoveTracks(PathBuf),
+    /// Follow a renamed file or folder.
+    RenameTracks(PathBuf, PathBuf),
+    SetCrossfade(CrossfadeModel),
+    SetFade(Duration),
+    /// Set the volume, from `0.0` to `1.0`.
+    SetVolume(f32),
+    /// Bookmark the current position, with a name or `Bookmark <n>`.
+    AddBookmark(Option<String>),
+    /// Remove a bookmark of a file.
+    RemoveBookmark(PathBuf, Bookmark),
+    /// Play a file from a bookmarked position.
+    PlayBookmark(PathBuf, TrackMetadata, Duration),
+    /// Set the length from which files resume where they were left (`None` = never).
+    SetResumeAfter(Option<Duration>),
+    /// Add a track to the end of the play queue.
+    Enqueue(PathBuf),
+    /// Add a track to the front of the play queue.
+    PlayNext(PathBuf),
+    /// Remove the queue entry at a 0-based position.
+    RemoveFromQueue(usize),
+    /// Move a queue entry from one 0-based position to another.
+    MoveInQueue(usize, usize),
+    ClearQueue,
+    /// Ask for a `PlayerStatus` snapshot, sent back on the given channel.
+    GetStatus(Sender<PlayerStatus>),
+    /// Ask for the folder tracks, in folder order.
+    GetTracks(Sender<Vec<PathBuf>>),
+    /// Ask for the folder tracks with their metadata, in folder order.
+    GetMetadata(Sender<Vec<(PathBuf, TrackMetadata)>>),
+    /// Ask for the bookmarks and resume points.
+    GetBookmarks(Sender<BookmarkModel>),
+    /// Stop the player thread.
+    Quit,
+}
+
+/// **AsyncPlayerController Struct**
+///
+/// Runs a `PlayerController` on its own thread. Callers send `Command`s and never block on
+/// decoding or stream creation; the player reports back through `Event`s, which any
+/// number of listeners can `subscribe` to.
+/// The `PlayerController` is created on the player thread because `cpal` streams cannot
+/// move between threads. A panic while running a command or the background work is caught
+/// and published as an `Event::Error`, the player thread keeps going. Only a panic while
+/// creating the controller ends the thread, after publishing its error.
+pub struct AsyncPlayerController {
+    commands: Sender<Command>,
+    event_bus: Arc<EventBus>,
+    /// Subscribed before the player thread started, handed to the first `subscribe` call so
+    /// it also gets the events of the first load.
+    first_subscriber: Mutex<Option<Receiver<Event>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AsyncPlayerController {
+    /// **Spawn the Player Thread**
+    ///
+    /// # Parameters:
+    /// - `audio_model`: The model holding the list of audio files and the current track index.
+    /// - `audio_loader`: A boxed trait object that loads audio streams dynamically.
+    /// - `bookmarks`: The saved bookmarks and resume points.
+    /// - `history`: The store every playback is recorded in.
+    /// - `session`: The session to go back to, `None` to start at the current track of
+    ///   `audio_model`.
+    ///
+    /// # Returns:
+    /// - A handle to send commands to the player and receive its events.
+    pub fn spawn(
+        audio_model: AudioFolderModel,
+        audio_loader: Box<dyn AudioLoader>,
+        bookmarks: BookmarkModel,
+        history: HistoryService,
+        session: Option<SessionModel>,
+    ) -> Self {
+        let (commands, command_receiver) = mpsc::channel::<Command>();
+        let event_bus = Arc::new(EventBus::new());
+        let first_subscriber = Mutex::new(Some(event_bus.subscribe()));
+
+        let thread_event_bus = Arc::clone(&event_bus);
+        let thread = thread::spawn(move || {
+            let events = Arc::clone(&thread_event_bus);
+            let created = panic::catch_unwind(AssertUnwindSafe(|| {
+                PlayerController::new(
+                    audio_model,
+                    audio_loader,
+                    bookmarks,
+                    history,
+                    session,
+                    thread_event_bus,
+                )
+            }));
+            let mut player_controller = match created {
+                Ok(player_controller) => player_controller,
+                Err(panic) => {
+                    Self::report_panic(&events, panic); // Without a controller there is nothing to run.
+                    return;
+                }
+            };
+
+            let mut tick_failed = false; // Report a tick that keeps failing only once.
+            loop {
+                match command_receiver.recv_timeout(TICK_INTERVAL) {
+                    Ok(Command::Quit) | Err(RecvTimeoutError::Disconnected) => break,
+                    Ok(command) => {
+                        let executed = panic::catch_unwind(AssertUnwindSafe(|| {
+                            Self::execute(&mut player_controller, command)
+                        }));
+                        if let Err(panic) = executed {
+                            Self::report_panic(&events, panic);
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                }
+                match panic::catch_unwind(AssertUnwindSafe(|| player_controller.tick())) {
+                    Ok(()) => tick_failed = false,
+                    Err(panic) => {
+                        if !tick_failed {
+                            Self::report_panic(&events, panic);
+                        }
+                        tick_failed = true;
+                    }
+                }
+            }
+        });
+
+        AsyncPlayerController {
+            commands,
+            event_bus,
+            first_subscriber,
+            thread: Some(thread),
+        }
+    }
+
+    /// **Subscribe to Player Events**
+    ///
+    /// Adds another listener (notifications, scrobbling, logging, ...). Can be called from
+    /// any thread, any number of times.
+    ///
+    /// # Returns:
+    /// - A `Receiver` that gets every event published from now on. The first one gets every
+    ///   event since the player thread started, including the `TrackChanged` of the first
+    ///   track, which may be published before anyone had a chance to subscribe.
+    pub fn subscribe(&self) -> Receiver<Event> {
+        self.first_subscriber
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap_or_else(|| self.event_bus.subscribe())
+    }
+
+    /// Sends a command to the player thread. Returns immediately.
+    pub fn send(&self, command: Command) {
+        let _ = self.commands.send(command); // Only fails once the player thread has exited.
+    }
+
+    /// **Get the Player Status**
+    ///
+    /// Asks the player thread for a snapshot of its state and waits for the answer.
+    ///
+    /// # Returns:
+    /// - `Some(PlayerStatus)`, or `None` if the player thread has exited.
+    pub fn status(&self) -> Option<PlayerStatus> {
+        let (sender, receiver) = mpsc::channel();
+        self.send(Command::GetStatus(sender));
+        receiver.recv().ok()
+    }
+
+    /// **Get the Tracks**
+    ///
+    /// # Returns:
+    /// - The folder tracks in folder order, or `None` if the player thread has exited.
+    pub fn tracks(&self) -> Option<Vec<PathBuf>> {
+        let (sender, receiver) = mpsc::channel();
+        self.send(Command::GetTracks(sender));
+        receiver.recv().ok()
+    }
+
+    /// **Get the Track Metadata**
+    ///
+    /// # Returns:
+    /// - The folder tracks with their metadata in folder order, or `None` if the player
+    ///   thread has exited.
+    pub fn metadata(&self) -> Option<Vec<(PathBuf, TrackMetadata)>> {
+        let (sender, receiver) = mpsc::channel();
+        self.send(Command::GetMetadata(sender));
+        receiver.recv().ok()
+    }
+
+    /// **Get the Bookmarks**
+    ///
+    /// # Returns:
+    /// - The bookmarks and resume points as the player holds them, or `None` if the player
+    ///   thread has exited.
+    pub fn bookmarks(&self) -> Option<BookmarkModel> {
+        let (sender, receiver) = mpsc::channel();
+        self.send(Command::GetBookmarks(sender));
+        receiver.recv().ok()
+    }
+
+    /// Publishes a panic caught on the player thread as an `Event::Error`.
+    fn report_panic(events: &EventBus, panic: Box<dyn Any + Send>) {
+        let message = match panic.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => match panic.downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => "unknown error".to_string(),
+            },
+        };
+        events.publish(Event::Error(format!("The player failed: {}", message)));
+    }
+
+    /// Runs one command against the controller on the player thread.
+    fn execute(player_controller: &mut PlayerController, command: Command) {
+        match command {
+            Command::Play => player_controller.play(),
+            Command::Pause => player_controller.pause(),
+            Command::TogglePlay => player_controller.toggle_play(),
+            Command::Stop => player_controller.stop(),
+            Command::Next => player_controller.next(),
+            Command::Prev => player_controller.prev(),
+            Command::Seek(position) => player_controller.seek(position),
+            Command::SeekBy(seconds) => player_controller.seek_by(seconds),
+            Command::ToggleShuffle => player_controller.toggle_shuffle(),
+            Command::CycleRepeat => player_controller.cycle_repeat(),
+            Command::Sort(key) => player_controller.sort(key),
+            Command::UpdateMetadata(path, metadata) => {
+                player_controller.update_metadata(&path, metadata)
+            }
+            Command::AddTrack(track, metadata) => {
+                player_controller.add_track(track, metadata);
+            }
+            Command::PlayTrack(track, metadata) => player_controller.play_track(track, metadata),
+            Command::PlayTracks(audio_model) => player_controller.play_tracks(audio_model),
+            Command::RemoveTracks(path) => player_controller.remove_tracks(&path),
+            Command::RenameTracks(from, to) => player_controller.rename_tracks(&from, &to),
+            Command::SetCrossfade(crossfade) => player_controller.set_crossfade(crossfade),
+            Command::SetFade(fade) => player_controller.set_fade(fade),
+            Command::SetVolume(volume) => player_controller.set_volume(volume),
+            Command::AddBookmark(name) => player_controller.add_bookmark(name),
+            Command::RemoveBookmark(path, bookmark) => {
+                player_controller.remove_bookmark(&path, &bookmark)
+            }
+            Command::PlayBookmark(track, metadata, position) => {
+                player_controller.play_bookmark(track, metadata, position)
+            }
+            Command::SetResumeAfter(resume_after) => {
+                player_controller.set_resume_after(resume_after)
+            }
+            Command::Enqueue(track) => player_controller.enqueue(track),
+            Command::PlayNext(track) => player_controller.play_next(track),
+            Command::RemoveFromQueue(position) => player_controller.remove_from_queue(position),
+            Command::MoveInQueue(from, to) => player_controller.move_in_queue(from, to),
+            Command::ClearQueue => player_controller.clear_queue(),
+            Command::GetStatus(reply) => {
+                let _ = reply.send(player_controller.status()); // The caller may have given up.
+            }
+            Command::GetTracks(reply) => {
+                let _ = reply.send(player_controller.tracks());
+            }
+            Command::GetMetadata(reply) => {
+                let _ = reply.send(player_controller.metadata());
+            }
+            Command::GetBookmarks(reply) => {
+                let _ = reply.send(player_controller.bookmarks());
+            }
+            Command::Quit => {}
+        }
+    }
+}
+
+impl Drop for AsyncPlayerController {
+    /// Stops the player thread and waits for it, so the audio streams are dropped cleanly.
+    fn drop(&mut self) {
+        self.send(Command::Quit);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::services::audio_loader::DecodedAudio;
+    use crate::modules::services::playback_handle::PlaybackHandle;
+    use crate::modules::services::temp_folder::TempFolder;
+    use cpal::Stream;
+    use std::error::Error;
+    use std::path::Path;
+
+    /// A loader that panics on files named `panic.wav` and fails to decode any other file.
+    struct PanickingLoader;
+
+    impl AudioLoader for PanickingLoader {
+        fn decode(&self, file_path: &Path) -> Result<DecodedAudio, Box<dyn Error>> {
+            if file_path.ends_with("panic.wav") {
+                panic!("decoder bug");
+            }
+            Err("not decodable".into())
+        }
+
+        fn create_audio_stream(
+            &self,
+            _audio: Arc<DecodedAudio>,
+            _handle: Arc<PlaybackHandle>,
+        ) -> Result<Stream, Box<dyn Error>> {
+            Err("no output device".into())
+        }
+    }
+
+    #[test]
+    fn panics_are_reported_and_the_player_keeps_running() {
+        let folder = TempFolder::new("async-panic");
+        let player = AsyncPlayerController::spawn(
+            AudioFolderModel::new(vec![
+                folder.path().join("a.wav"),
+                folder.path().join("panic.wav"),
+            ]),
+            Box::new(PanickingLoader),
+            BookmarkModel::new(None),
+            HistoryService::new(folder.path().join("history.tsv")),
+            None,
+        );
+        let events = player.subscribe();
+        player.send(Command::Next);
+
+        let status = player.status();
+        assert!(status.is_some(), "the player thread is still running");
+        let errors: Vec<String> = events
+            .try_iter()
+            .filter_map(|event| match event {
+                Event::Error(message) => Some(message),
+                _ => None,
+            })
+            .collect();
+        assert!(
+            errors
+                .iter()
+                .any(|message| message == "The player failed: decoder bug"),
+            "{:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn tracks_and_metadata_are_queried_apart_from_the_status() {
+        let folder = TempFolder::new("async-queries");
+        let tracks = vec![folder.path().join("a.wav"), folder.path().join("b.wav")];
+        let mut audio_model = AudioFolderModel::new(tracks.clone());
+        audio_model.update_metadata(
+            &tracks[1],
+            TrackMetadata {
+                title: Some("Bee".to_string()),
+                ..TrackMetadata::default()
+            },
+        );
+        let player = AsyncPlayerController::spawn(
+            audio_model,
+            Box::new(PanickingLoader),
+            BookmarkModel::new(None),
+            HistoryService::new(folder.path().join("history.tsv")),
+            None,
+        );
+
+        assert_eq!(player.status().unwrap().current_index, 0);
+        assert_eq!(player.tracks(), Some(tracks.clone()));
+        let metadata = player.metadata().unwrap();
+        assert_eq!(
+            metadata.iter().map(|(track, _)| track).collect::<Vec<_>>(),
+            tracks.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(metadata[1].1.title.as_deref(), Some("Bee"));
+        assert_eq!(player.bookmarks().unwrap().all().count(), 0);
+    }
+}
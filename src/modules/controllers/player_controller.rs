@@ -1,22 +1,84 @@
 extern crate cpal;
 
-use crate::modules::models::audio_folder_model::AudioFolderModel;
+use crate::modules::models::audio_folder_model::{AudioFolderModel, RepeatMode, SortKey};
+use crate::modules::models::bookmark_model::{Bookmark, BookmarkModel};
+use crate::modules::models::crossfade_model::{CrossfadeModel, FadeCurve};
+use crate::modules::models::cue_model::CueTrack;
+use crate::modules::models::folder_change_model::renamed_path;
+use crate::modules::models::history_model::{is_play, PlayRecord};
+use crate::modules::models::player_event_model::Event;
+use crate::modules::models::player_status_model::PlayerStatus;
+use crate::modules::models::queue_model::QueueModel;
+use crate::modules::models::session_model::SessionModel;
+use crate::modules::models::track_metadata_model::TrackMetadata;
 use crate::modules::services::audio_loader::AudioLoader;
+use crate::modules::services::event_bus::EventBus;
+use crate::modules::services::history_service::HistoryService;
+use crate::modules::services::metadata_service::MetadataService;
+use crate::modules::services::playback_handle::PlaybackHandle;
+use crate::modules::services::preload_service::{PreloadService, DEFAULT_CACHE_BUDGET};
 use cpal::traits::StreamTrait;
 use cpal::Stream;
-use std::path::PathBuf;
-use std::time::Instant;
+use log::{error, info, warn};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Default length of the short ramps applied on play, pause, stop and seek.
+pub const DEFAULT_FADE: Duration = Duration::from_millis(20);
+
+/// The longest click-suppression ramp accepted by `set_fade`.
+pub const MAX_FADE: Duration = Duration::from_secs(2);
+
+/// How often `Event::PositionUpdated` is published while playing.
+const POSITION_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A `cpal` stream together with the handle its callback renders through.
+struct ActiveStream {
+    stream: Stream,
+    handle: Arc<PlaybackHandle>,
+    file: Option<PathBuf>, // The whole file played, `None` for a CUE track (no resume point).
+}
+
+/// The playback of the current track, recorded in the history when the track is left.
+struct Playback {
+    path: PathBuf,
+    started: SystemTime,      // When the track started playing.
+    length: Option<Duration>, // The length of the track, if known.
+    listened: Duration,       // Playing time up to the last pause.
+    resumed: Option<Instant>, // When playback last started or resumed, `None` while paused.
+}
+
+/// What to do with the stream once its fade-out has reached silence.
+#[derive(Clone, Copy, PartialEq)]
+enum Halt {
+    Pause, // Pause the stream and keep the position.
+    Stop,  // Pause the stream and rewind to the beginning.
+}
 
 /// **PlayerController Struct**
 ///
 /// This struct manages the playback of audio files using the `AudioFolderModel`
 /// and `AudioLoader`. It holds the current stream state, manages play/pause operations,
-/// and allows navigation between tracks.
+/// and allows navigation between tracks, optionally crossfading from one track into the next.
 pub struct PlayerController {
     audio_model: AudioFolderModel, // Manages the collection and current index of audio files.
-    audio_loader: Box<dyn AudioLoader>, // Uses dynamic dispatch to load audio streams based on the file format.
-    stream: Option<Stream>,             // Holds the currently playing audio stream, if any.
-    is_playing: bool,                   // Tracks whether audio playback is currently active.
+    queue: QueueModel,             // Tracks lined up to play before the folder order continues.
+    queued_track: Option<PathBuf>, // The queued track being played, `None` while on a folder track.
+    audio_loader: Arc<dyn AudioLoader>, // Uses dynamic dispatch to load audio streams based on the file format.
+    preloader: PreloadService, // Decodes the upcoming track in the background and caches decoded audio.
+    stream: Option<ActiveStream>, // Holds the currently playing audio stream, if any.
+    fading_out: Vec<ActiveStream>, // Outgoing streams of running crossfades, dropped once silent.
+    crossfade: CrossfadeModel, // Crossfade duration and curve, disabled by default.
+    fade: Duration,            // Length of the click-suppression ramps.
+    volume: f32,               // The user's volume, applied to every stream.
+    bookmarks: BookmarkModel,  // Bookmarks, and where long files were left.
+    history: HistoryService,   // Records every playback.
+    playback: Option<Playback>, // The playback of the current track, `None` until it plays.
+    pending_halt: Option<Halt>, // Pause/stop waiting for the fade-out to finish.
+    events: Arc<EventBus>,     // Publishes playback events to every subscriber.
+    last_position_event: Instant, // When the last position tick was published.
+    is_playing: bool,          // Tracks whether audio playback is currently active.
 }
 
 impl PlayerController {
@@ -28,18 +90,49 @@ impl PlayerController {
     /// # Parameters:
     /// - `audio_model`: The model holding the list of audio files and the current track index.
     /// - `audio_loader`: A boxed trait object that loads audio streams dynamically.
+    /// - `bookmarks`: The saved bookmarks and resume points, so the first track already
+    ///   resumes where it was left.
+    /// - `history`: The store every playback is recorded in.
+    /// - `session`: The session to go back to (see `restore_session`), `None` to load the
+    ///   current track of `audio_model`. Only the track that plays first is decoded.
+    /// - `events`: The bus the controller publishes its events on. Listeners subscribe on the
+    ///   bus, so subscriptions made before the controller exists also see the first track load.
     ///
     /// # Returns:
     /// - A new instance of `PlayerController`.
-    pub fn new(audio_model: AudioFolderModel, audio_loader: Box<dyn AudioLoader>) -> Self {
+    pub fn new(
+        audio_model: AudioFolderModel,
+        audio_loader: Box<dyn AudioLoader>,
+        bookmarks: BookmarkModel,
+        history: HistoryService,
+        session: Option<SessionModel>,
+        events: Arc<EventBus>,
+    ) -> Self {
+        let audio_loader: Arc<dyn AudioLoader> = Arc::from(audio_loader);
         let mut instance = PlayerController {
             audio_model,
+            queue: QueueModel::new(),
+            queued_track: None,
+            preloader: PreloadService::new(Arc::clone(&audio_loader), DEFAULT_CACHE_BUDGET),
             audio_loader,
             stream: None,
+            fading_out: Vec::new(),
+            crossfade: CrossfadeModel::default(),
+            fade: DEFAULT_FADE,
+            volume: 1.0,
+            bookmarks,
+            history,
+            playback: None,
+            pending_halt: None,
+            events,
+            last_position_event: Instant::now(),
             is_playing: false,
         };
 
-        instance.load_current();
+        match session {
+            Some(session) => instance.restore_session(session),
+            None => instance.load_current(),
+        }
         // Maybe call play here if we want to play immediately after first load
         // instance.play() ;
         instance
@@ -47,42 +140,612 @@ impl PlayerController {
 
     /// **Load the Current Audio Track**
     ///
-    /// Loads the current audio file from the `AudioFolderModel` and creates an audio stream.
+    /// Loads the current audio file (the queued track, or the current folder track) and
+    /// creates its audio stream, taken from the preload cache when it was decoded ahead.
+    /// The previous stream fades out if it was playing, and the track after the new one is
+    /// preloaded.
     ///
-    /// # Returns:
-    /// - `Ok(())` if the stream is successfully loaded.
-    /// - `Err(Box<dyn Error>)` if loading fails.
+    /// # Behavior
+    /// - If the track cannot be loaded there is no stream afterwards, the failure is logged
+    ///   and published as an `Event::Error`.
     pub fn load_current(&mut self) {
-        let current_file = self.get_current_file(); // Get the current audio file path.
-        println!("[SENSIT_LOG] Loading file: {:?}", current_file.display());
+        if let Some(previous) = self.stream.take() {
+            Self::leave(&mut self.bookmarks, &previous);
+            self.end_playback(previous.handle.is_finished());
+            if self.is_playing {
+                // Let the old track fade out instead of cutting it mid-waveform.
+                previous.handle.ramp_to(0.0, self.fade, FadeCurve::Linear);
+                self.fading_out.push(previous);
+            }
+        }
+        self.pending_halt = None;
+        self.stream = self.open_current(); // Replace the stream, or reset it if loading fails.
+        self.preload_upcoming();
+    }
+
+    /// Remembers where the file of a stream that stops playing was left (see
+    /// `BookmarkModel::leave`).
+    fn leave(bookmarks: &mut BookmarkModel, active: &ActiveStream) {
+        if let Some(ref file) = active.file {
+            let position = active.handle.position();
+            bookmarks.leave(file, position, position + active.handle.remaining());
+        }
+    }
+
+    /// Starts the playback record of the current track, or resumes it after a pause.
+    fn start_playback(&mut self) {
+        match self.playback {
+            Some(ref mut playback) => {
+                playback.resumed.get_or_insert_with(Instant::now);
+            }
+            None => {
+                let length = match self.current_cue_track() {
+                    Some(track) => track.end.map(|end| end.saturating_sub(track.start)),
+                    None => (self.stream.as_ref())
+                        .map(|active| active.handle.position() + active.handle.remaining()),
+                };
+                self.playback = self.get_current_file().map(|path| Playback {
+                    path,
+                    started: SystemTime::now(),
+                    length,
+                    listened: Duration::ZERO,
+                    resumed: Some(Instant::now()),
+                });
+            }
+        }
+    }
+
+    /// Stops counting the listened time of the current track.
+    fn pause_playback(&mut self) {
+        if let Some(ref mut playback) = self.playback {
+            if let Some(resumed) = playback.resumed.take() {
+                playback.listened += resumed.elapsed();
+            }
+        }
+    }
+
+    /// **Record the Playback of the Track Being Left**
+    ///
+    /// Appends the playback of the current track to the history, if it played at all.
+    ///
+    /// # Parameters:
+    /// - `finished`: `true` if the track played to its end. Otherwise it counts as played if
+    ///   enough of it was listened to (see `is_play`), or else as skipped.
+    fn end_playback(&mut self, finished: bool) {
+        self.pause_playback();
+        let Some(playback) = self.playback.take() else {
+            return; // Loaded but never played.
+        };
+        if playback.listened.is_zero() {
+            return;
+        }
+        let record = PlayRecord {
+            path: playback.path,
+            started: playback.started,
+            listened: playback.listened,
+            skipped: !(finished || is_play(playback.listened, playback.length)),
+        };
+        if let Err(err) = self.history.append(&record) {
+            warn!(
+                "Could not record the playback of {:?}: {}",
+                record.path.display(),
+                err
+            );
+        }
+    }
+
+    /// Publishes an event to all subscribers of the event bus.
+    fn emit(&self, event: Event) {
+        self.events.publish(event);
+    }
+
+    /// **Open a Stream for the Current Track**
+    ///
+    /// Creates a stream for the current audio file without storing it. The stream starts
+    /// silent, `play` fades it in. Decoded audio comes from the preload cache when the
+    /// track was decoded ahead of time. A long file starts where it was left.
+    ///
+    /// # Returns:
+    /// - `Some(ActiveStream)` if the stream is successfully created, `None` otherwise
+    ///   (also when there is no track, which publishes `Event::NothingToPlay`).
+    fn open_current(&self) -> Option<ActiveStream> {
+        let Some(current_file) = self.get_current_file() else {
+            info!("No tracks to load.");
+            self.emit(Event::NothingToPlay);
+            return None;
+        };
+        info!("Loading file: {:?}", current_file.display());
 
         let start_time = Instant::now(); // Start measuring the time taken to load the stream.
+        let handle = Arc::new(PlaybackHandle::new(0.0));
+        handle.set_volume(self.volume);
 
-        // Use the audio loader to create an audio stream for the current file.
-        match self.audio_loader.create_audio_stream(&current_file) {
+        // Decode (or take the preloaded audio) and create an audio stream for the current file.
+        let stream = self.preloader.load(&current_file).and_then(|audio| {
+            self.audio_loader
+                .create_audio_stream(audio, Arc::clone(&handle))
+        });
+        match stream {
             Ok(stream) => {
-                self.stream = Some(stream); // Store the created stream.
-                println!(
-                    "[SENSIT_LOG] Stream created successfully for file {:?} in {:?}.",
+                info!(
+                    "Stream created successfully for file {:?} in {:?}.",
                     current_file.display(),
                     start_time.elapsed() // Log the time taken to create the stream.
                 );
+                let file = match self.current_cue_track() {
+                    Some(cue_track) => {
+                        // A CUE track only plays its section of the file.
+                        handle.seek(cue_track.start, Duration::ZERO);
+                        handle.set_end(cue_track.end);
+                        None
+                    }
+                    None => Some(current_file),
+                };
+                let resume_point = (file.as_ref())
+                    .and_then(|file| self.bookmarks.resume_point(file, handle.remaining()));
+                if let Some(position) = resume_point {
+                    info!("Resuming at {:?}.", position);
+                    handle.seek(position, Duration::ZERO);
+                }
+                self.emit_track_changed();
+                Some(ActiveStream {
+                    stream,
+                    handle,
+                    file,
+                })
             }
             Err(err) => {
-                eprintln!("[ERROR] Failed to create stream: {:?}", err); // Log the error.
-                self.stream = None; // Reset the stream if loading fails.
+                error!("Failed to create stream: {}", err); // Log the error.
+                self.emit(Event::Error(format!(
+                    "Failed to load {}: {}",
+                    current_file.display(),
+                    err
+                )));
+                None
+            }
+        }
+    }
+
+    /// Publishes `TrackChanged` for the current track with its metadata and cover art.
+    /// Queued tracks are not part of the folder, their tags are read when they start.
+    fn emit_track_changed(&self) {
+        let Some(path) = self.get_current_file() else {
+            return;
+        };
+        let mut metadata = match self.queued_track {
+            Some(ref path) => MetadataService.read(path),
+            None => self.audio_model.current_metadata(),
+        };
+        metadata.artwork = MetadataService.read_artwork(&path).map(Arc::new);
+        self.emit(Event::TrackChanged {
+            index: self.current_folder_index(),
+            path,
+            metadata,
+        });
+    }
+
+    /// Returns the metadata of `path`: from the folder model if it is one of its tracks,
+    /// otherwise read from the file's tags.
+    fn metadata_of(&self, path: &Path) -> TrackMetadata {
+        match self.audio_model.files.iter().position(|file| file == path) {
+            Some(index) => self.audio_model.track_metadata(index),
+            None => MetadataService.read(path),
+        }
+    }
+
+    /// **Get the CUE Track Being Played**
+    ///
+    /// # Returns:
+    /// - `Some(&CueTrack)` while a CUE track plays, `None` for a whole file (or a queued track).
+    fn current_cue_track(&self) -> Option<&CueTrack> {
+        match self.queued_track {
+            Some(_) => None,
+            None => self.audio_model.current_cue_track(),
+        }
+    }
+
+    /// **Get the Contiguous CUE Track**
+    ///
+    /// # Returns:
+    /// - `Some(&CueTrack)` if the upcoming track is the CUE track that follows the current
+    ///   one in the same file, so playback can run on without reopening the stream.
+    fn contiguous_cue_track(&self) -> Option<&CueTrack> {
+        if self.audio_model.repeat == RepeatMode::One || !self.queue.tracks().is_empty() {
+            return None;
+        }
+        let current_end = self.current_cue_track()?.end?;
+        let next = self.audio_model.peek_next()?;
+        let next_track = self.audio_model.cue_track(next)?;
+        (Some(&self.audio_model.files[next]) == self.get_current_file().as_ref()
+            && next_track.start == current_end)
+            .then_some(next_track)
+    }
+
+    /// **Preload the Upcoming Track**
+    ///
+    /// Asks the preloader to decode the track that follows the current one (per queue,
+    /// folder or shuffle order and repeat mode). Any earlier preload request is cancelled.
+    /// With repeat one the current track is already decoded, so the track a `next` would
+    /// move to is preloaded instead.
+    fn preload_upcoming(&self) {
+        let upcoming = match self.audio_model.repeat {
+            RepeatMode::One => match self.queue.peek() {
+                Some(track) => Some(track.clone()),
+                None => self
+                    .audio_model
+                    .peek_following()
+                    .map(|index| self.audio_model.files[index].clone()),
+            },
+            _ => self.upcoming_track(),
+        };
+        match upcoming {
+            Some(track) => self.preloader.request(&track),
+            None => self.preloader.cancel(), // End of the play order, nothing comes next.
+        }
+    }
+
+    /// **Get the Upcoming Track**
+    ///
+    /// Returns the track that plays when the current one finishes on its own: the current
+    /// track again with repeat one, otherwise the front of the queue, otherwise the next
+    /// track of the folder order.
+    ///
+    /// # Returns:
+    /// - `Some(PathBuf)`: The upcoming track, or `None` at the end of the play order (or
+    ///   without tracks).
+    fn upcoming_track(&self) -> Option<PathBuf> {
+        if self.audio_model.repeat == RepeatMode::One {
+            return self.get_current_file();
+        }
+        if let Some(track) = self.queue.peek() {
+            return Some(track.clone());
+        }
+        self.audio_model
+            .peek_next()
+            .map(|index| self.audio_model.files[index].clone())
+    }
+
+    /// **Advance Automatically**
+    ///
+    /// Moves to the track returned by `upcoming_track`, taking it off the queue if it
+    /// was queued.
+    ///
+    /// # Returns:
+    /// - `true` if there is a track to move to, `false` at the end of the play order.
+    fn advance_automatically(&mut self) -> bool {
+        if self.audio_model.repeat == RepeatMode::One {
+            return true; // Play the current track again.
+        }
+        if let Some(track) = self.queue.pop() {
+            self.queued_track = Some(track);
+            self.emit(Event::QueueChanged);
+            return true;
+        }
+        if self.audio_model.advance() {
+            self.queued_track = None; // Back to the folder order.
+            true
+        } else {
+            false
+        }
+    }
+
+    /// **Get the Folder Index of the Current Track**
+    ///
+    /// # Returns:
+    /// - `Some(usize)`: The current index in the `AudioFolderModel`, or `None` while a
+    ///   queued track plays.
+    fn current_folder_index(&self) -> Option<usize> {
+        match self.queued_track {
+            Some(_) => None,
+            None => Some(self.audio_model.current_index),
+        }
+    }
+
+    /// **Enqueue a Track**
+    ///
+    /// Adds a track to the end of the play queue.
+    pub fn enqueue(&mut self, track: PathBuf) {
+        info!("Enqueued {:?}.", track.display());
+        self.queue.enqueue(track);
+        self.queue_changed();
+    }
+
+    /// **Play a Track Next**
+    ///
+    /// Puts a track at the front of the play queue, right after the current track.
+    pub fn play_next(&mut self, track: PathBuf) {
+        info!("Playing {:?} next.", track.display());
+        self.queue.play_next(track);
+        self.queue_changed();
+    }
+
+    /// **Remove a Track from the Queue**
+    ///
+    /// # Parameters:
+    /// - `position`: The 0-based position in the queue.
+    pub fn remove_from_queue(&mut self, position: usize) {
+        match self.queue.remove(position) {
+            Some(track) => {
+                info!("Removed {:?} from the queue.", track.display());
+                self.queue_changed();
+            }
+            None => self.report_queue_position(position),
+        }
+    }
+
+    /// **Move a Track in the Queue**
+    ///
+    /// # Parameters:
+    /// - `from`: The current 0-based position of the track.
+    /// - `to`: The 0-based position the track moves to.
+    pub fn move_in_queue(&mut self, from: usize, to: usize) {
+        if self.queue.move_track(from, to) {
+            info!("Moved queue entry {} to {}.", from, to);
+            self.queue_changed();
+        } else {
+            self.report_queue_position(from.max(to));
+        }
+    }
+
+    /// **Clear the Queue**
+    pub fn clear_queue(&mut self) {
+        self.queue.clear();
+        info!("Queue cleared.");
+        self.queue_changed();
+    }
+
+    /// Re-targets the preloader and tells listeners after a queue edit.
+    fn queue_changed(&mut self) {
+        self.preload_upcoming();
+        self.emit(Event::QueueChanged);
+    }
+
+    /// Logs and reports a queue position that does not exist.
+    fn report_queue_position(&self, position: usize) {
+        warn!("No queue entry at position {}.", position);
+        self.emit(Event::Error(format!(
+            "No queue entry at position {}.",
+            position + 1
+        )));
+    }
+
+    /// **Get the Player Status**
+    ///
+    /// # Returns:
+    /// - A `PlayerStatus` snapshot for views running on another thread.
+    pub fn status(&self) -> PlayerStatus {
+        PlayerStatus {
+            current_index: self.audio_model.current_index,
+            current_track: self.get_current_file(),
+            queue: self.queue.tracks(),
+            position: self.position(),
+            is_playing: self.is_playing,
+            shuffle: self.audio_model.is_shuffled(),
+            repeat: self.audio_model.repeat,
+            volume: self.volume,
+            crossfade: self.crossfade,
+        }
+    }
+
+    /// **Get the Tracks**
+    ///
+    /// # Returns:
+    /// - All tracks of the `AudioFolderModel`, in folder order.
+    pub fn tracks(&self) -> Vec<PathBuf> {
+        self.audio_model.files.clone()
+    }
+
+    /// **Get the Track Metadata**
+    ///
+    /// # Returns:
+    /// - Every folder track with its metadata (of its CUE track, if it is one), in folder order.
+    pub fn metadata(&self) -> Vec<(PathBuf, TrackMetadata)> {
+        (self.audio_model.files.iter().enumerate())
+            .map(|(index, track)| (track.clone(), self.audio_model.track_metadata(index)))
+            .collect()
+    }
+
+    /// **Get the Bookmarks**
+    ///
+    /// # Returns:
+    /// - The bookmarks and resume points, with the resume point of the current track taken
+    ///   at the current position.
+    pub fn bookmarks(&self) -> BookmarkModel {
+        let mut bookmarks = self.bookmarks.clone();
+        if let Some(ref active) = self.stream {
+            Self::leave(&mut bookmarks, active);
+        }
+        bookmarks
+    }
+
+    /// **Toggle Shuffle**
+    ///
+    /// Switches between folder order and a shuffled order starting at the current track.
+    pub fn toggle_shuffle(&mut self) {
+        let shuffle = !self.audio_model.is_shuffled();
+        self.audio_model.set_shuffle(shuffle);
+        info!("Shuffle {}.", if shuffle { "on" } else { "off" });
+        self.preload_upcoming(); // The upcoming track changed.
+        self.emit(Event::QueueChanged);
+    }
+
+    /// **Cycle the Repeat Mode**
+    ///
+    /// Switches to the next repeat mode (off -> all -> one -> off).
+    pub fn cycle_repeat(&mut self) {
+        self.audio_model.repeat = self.audio_model.repeat.cycle();
+        info!("Repeat mode: {:?}.", self.audio_model.repeat);
+        self.preload_upcoming(); // The upcoming track may have changed.
+        self.emit(Event::QueueChanged);
+    }
+
+    /// **Sort the Tracks**
+    ///
+    /// Reorders the folder tracks by `key`. The current track keeps playing and stays
+    /// current.
+    pub fn sort(&mut self, key: SortKey) {
+        self.audio_model.sort(key);
+        info!(
+            "Sorted {} tracks by {:?}.",
+            self.audio_model.files.len(),
+            key
+        );
+        self.preload_upcoming(); // The upcoming track may have changed.
+        self.emit(Event::QueueChanged);
+    }
+
+    /// **Update the Metadata of a File**
+    ///
+    /// Replaces the metadata of every folder track that plays `path`, after its tags
+    /// were written.
+    pub fn update_metadata(&mut self, path: &Path, metadata: TrackMetadata) {
+        self.audio_model.update_metadata(path, metadata);
+        self.emit(Event::QueueChanged); // Listings show the new tags.
+    }
+
+    /// **Add a Track to the Folder Tracks**
+    ///
+    /// Adds a file (a new file in the folder, a library search result) to the folder tracks
+    /// at its place in natural path order, unless it already is one of them.
+    ///
+    /// # Parameters:
+    /// - `track`: The audio file.
+    /// - `metadata`: Its tags, shown by listings.
+    ///
+    /// # Returns:
+    /// - The index of the track in the folder tracks.
+    pub fn add_track(&mut self, track: PathBuf, metadata: TrackMetadata) -> usize {
+        let count = self.audio_model.files.len();
+        let index = self.audio_model.add_track(track, metadata);
+        if self.audio_model.files.len() > count {
+            info!(
+                "Added {:?} to the tracks.",
+                self.audio_model.files[index].display()
+            );
+        }
+        if count == 0 && self.queued_track.is_none() {
+            self.load_current(); // The first track becomes the current one.
+        } else {
+            self.preload_upcoming(); // The upcoming track may have changed.
+        }
+        self.emit(Event::QueueChanged);
+        index
+    }
+
+    /// **Play a Track**
+    ///
+    /// Adds a file to the folder tracks (see `add_track`) and switches to it. Folder order
+    /// continues after it.
+    pub fn play_track(&mut self, track: PathBuf, metadata: TrackMetadata) {
+        let from = self.get_current_file();
+        let index = self.add_track(track, metadata);
+        self.queued_track = None;
+        self.audio_model.current_index = index;
+        self.change_track(from.as_deref());
+    }
+
+    /// **Remove Deleted Tracks**
+    ///
+    /// Removes the folder tracks and queued tracks of a deleted file or folder. The current
+    /// track stays current if it was not deleted; if it was, the next remaining track is
+    /// loaded (and played if playback was running). Once every track is gone, playback
+    /// stops and `Event::NothingToPlay` is published.
+    ///
+    /// # Parameters:
+    /// - `path`: The deleted file or folder.
+    pub fn remove_tracks(&mut self, path: &Path) {
+        let current = self.get_current_file();
+        let queued = self.queue.remove_tracks(path);
+        let removed = self.audio_model.remove_tracks(path);
+        if queued + removed == 0 {
+            return;
+        }
+        info!(
+            "Removed {} tracks and {} queued tracks of {:?}.",
+            removed,
+            queued,
+            path.display()
+        );
+        let deleted = current
+            .as_ref()
+            .is_some_and(|current| current.starts_with(path));
+        if deleted && (removed > 0 || self.queued_track.is_some()) {
+            self.queued_track = None;
+            if self.is_playing {
+                self.change_track(current.as_deref());
+            } else {
+                self.load_current();
+            }
+        } else {
+            self.preload_upcoming(); // The upcoming track may be gone.
+        }
+        self.emit(Event::QueueChanged);
+    }
+
+    /// **Follow a Renamed File or Folder**
+    ///
+    /// Points the folder tracks, the queue, the current track, the bookmarks, the resume
+    /// points and the play history to the new paths, so playback goes on where it is and
+    /// the files keep what was recorded about them.
+    pub fn rename_tracks(&mut self, from: &Path, to: &Path) {
+        let renamed = self.audio_model.rename_tracks(from, to);
+        self.queue.rename_tracks(from, to);
+        let rename = |path: &mut PathBuf| {
+            if let Some(renamed) = renamed_path(path, from, to) {
+                *path = renamed;
             }
+        };
+        self.queued_track.iter_mut().for_each(rename);
+        (self.stream.iter_mut().chain(&mut self.fading_out))
+            .filter_map(|active| active.file.as_mut())
+            .for_each(rename);
+        self.playback
+            .iter_mut()
+            .for_each(|playback| rename(&mut playback.path));
+        self.bookmarks.rename(from, to);
+        self.history.rename(from, to); // Written to the file with the next save.
+        info!(
+            "Renamed {} tracks from {:?} to {:?}.",
+            renamed,
+            from.display(),
+            to.display()
+        );
+        self.preload_upcoming();
+        self.emit(Event::QueueChanged);
+    }
+
+    /// **Play a List of Tracks**
+    ///
+    /// Replaces the folder tracks with `audio_model` (an album, an artist, ... from the
+    /// library) and plays its current track. Shuffle and repeat stay as they are, the
+    /// queue is kept.
+    pub fn play_tracks(&mut self, mut audio_model: AudioFolderModel) {
+        if audio_model.files.is_empty() {
+            return;
         }
+        let from = self.get_current_file();
+        audio_model.repeat = self.audio_model.repeat;
+        audio_model.set_shuffle(self.audio_model.is_shuffled());
+        info!("Playing {} tracks.", audio_model.files.len());
+        self.audio_model = audio_model;
+        self.queued_track = None;
+        self.emit(Event::QueueChanged);
+        self.change_track(from.as_deref());
     }
 
     /// **Get the Current Audio File**
     ///
-    /// Returns the path of the current audio file from the `AudioFolderModel`.
+    /// Returns the path of the queued track being played, or else of the current audio
+    /// file from the `AudioFolderModel`.
     ///
     /// # Returns:
-    /// - `PathBuf`: The path to the current audio file.
-    fn get_current_file(&self) -> PathBuf {
-        self.audio_model.get_current_file().to_path_buf()
+    /// - `Some(PathBuf)`: The path to the current audio file.
+    /// - `None`: If nothing is queued and there are no folder tracks.
+    fn get_current_file(&self) -> Option<PathBuf> {
+        match self.queued_track {
+            Some(ref track) => Some(track.clone()),
+            None => self.audio_model.get_current_file().cloned(),
+        }
     }
 
     /// **Toggle Playback State**
@@ -98,53 +761,666 @@ impl PlayerController {
 
     /// **Play the Current Audio Stream**
     ///
-    /// Starts playback of the current audio stream. If no stream is available,
-    /// it attempts to load the stream and plays it if loading is successful.
-    fn play(&mut self) {
-        if let Some(ref stream) = self.stream {
+    /// Starts playback of the current audio stream with a short fade-in. Without a stream
+    /// (the track failed to load, or there are no tracks) nothing plays: playback that was
+    /// running stops with `Event::PlaybackStopped`, after the `Event::Error` of the failed
+    /// load. If the output device refuses to start, an `Event::Error` is published.
+    pub fn play(&mut self) {
+        if let Some(ref active) = self.stream {
             // If a stream is already available, start playback.
-            stream.play().expect("Failed to play the stream");
+            self.pending_halt = None; // Resuming cancels a pause that is still fading out.
+            if let Err(err) = active.stream.play() {
+                error!("Failed to play the stream: {}", err);
+                self.emit(Event::Error(format!("Could not start playback: {}", err)));
+                return;
+            }
+            active.handle.ramp_to(1.0, self.fade, FadeCurve::Linear);
             self.is_playing = true;
-            println!("[SENSIT_LOG] Playback started.");
+            self.start_playback();
+            info!("Playback started.");
+            self.emit(Event::PlaybackStarted);
+        } else if self.get_current_file().is_none() {
+            self.is_playing = false;
+            self.emit(Event::NothingToPlay);
         } else {
-            // No stream available, attempt to load the current track.
-            println!("[SENSIT_LOG] No stream available.");
+            // No stream available, the current track could not be loaded. Without a stream
+            // `tick` cannot advance, so playback must not keep claiming to run.
+            warn!("No stream available.");
+            if self.is_playing {
+                self.is_playing = false;
+                self.emit(Event::PlaybackStopped);
+            }
         }
     }
 
     /// **Pause the Current Audio Stream**
     ///
-    /// Pauses the playback of the current audio stream. If no stream is available,
-    /// it logs an error.
-    fn pause(&mut self) {
-        if let Some(ref stream) = self.stream {
-            // If a stream is available, pause it.
-            stream.pause().expect("Failed to pause the stream");
-            self.is_playing = false; // Update the playback state.
-            println!("[SENSIT_LOG] Playback paused.");
+    /// Pauses the playback of the current audio stream. The audio fades out first, the
+    /// stream itself is paused by `tick` once the fade has completed. If no stream is
+    /// available, it logs an error.
+    pub fn pause(&mut self) {
+        if self.fade_out_and_halt(Halt::Pause) {
+            info!("Playback paused.");
+            self.emit(Event::PlaybackPaused);
         } else {
             // Log an error if no stream is available.
-            println!("[ERROR] No stream available to pause.");
+            warn!("No stream available to pause.");
+            self.emit(Event::Error("No stream available to pause.".to_string()));
+        }
+    }
+
+    /// **Stop Playback**
+    ///
+    /// Fades the current track out, then pauses the stream and rewinds it to the beginning.
+    pub fn stop(&mut self) {
+        if self.fade_out_and_halt(Halt::Stop) {
+            info!("Playback stopped.");
+            self.emit(Event::PlaybackStopped);
+        } else {
+            warn!("No stream available to stop.");
+            self.emit(Event::Error("No stream available to stop.".to_string()));
+        }
+    }
+
+    /// **Seek in the Current Track**
+    ///
+    /// Jumps to `position` in the current track. While playing, the jump is wrapped in a
+    /// short fade-out and fade-in so it does not click.
+    ///
+    /// # Parameters:
+    /// - `position`: The position from the start of the track, clamped to its length.
+    ///   For a CUE track, the start of the track (not of the file).
+    pub fn seek(&mut self, position: Duration) {
+        let offset = self
+            .current_cue_track()
+            .map_or(Duration::ZERO, |track| track.start);
+        if let Some(ref active) = self.stream {
+            let fade = if self.is_playing {
+                self.fade
+            } else {
+                Duration::ZERO // A paused stream renders nothing, so it can jump right away.
+            };
+            active.handle.seek(offset + position, fade);
+            info!("Seeking to {:?}.", position);
+        } else {
+            warn!("No stream available to seek.");
+            self.emit(Event::Error("No stream available to seek.".to_string()));
+        }
+    }
+
+    /// **Seek Relative to the Current Position**
+    ///
+    /// # Parameters:
+    /// - `seconds`: Offset from the current position, negative values seek backwards.
+    pub fn seek_by(&mut self, seconds: f64) {
+        let current = self.position().unwrap_or_default().as_secs_f64();
+        match Duration::try_from_secs_f64((current + seconds).max(0.0)) {
+            Ok(position) => self.seek(position),
+            Err(_) => error!("Invalid seek offset: {}", seconds),
+        }
+    }
+
+    /// **Get the Playback Position**
+    ///
+    /// # Returns:
+    /// - `Some(Duration)`: The position in the current track (from the start of the CUE
+    ///   track for a CUE track).
+    /// - `None`: If no stream is loaded.
+    pub fn position(&self) -> Option<Duration> {
+        let offset = self
+            .current_cue_track()
+            .map_or(Duration::ZERO, |track| track.start);
+        self.stream
+            .as_ref()
+            .map(|active| active.handle.position().saturating_sub(offset))
+    }
+
+    /// **Set the Click-Suppression Fade**
+    ///
+    /// # Parameters:
+    /// - `fade`: Length of the ramps applied on play, pause, stop and seek, clamped to `MAX_FADE`.
+    ///   A zero duration switches the ramps off.
+    pub fn set_fade(&mut self, fade: Duration) {
+        self.fade = fade.min(MAX_FADE);
+        info!("Fade set to {:?}.", self.fade);
+    }
+
+    /// **Set the Volume**
+    ///
+    /// # Parameters:
+    /// - `volume`: From `0.0` (muted) to `1.0` (full), clamped. Applies to the playing
+    ///   stream, streams still fading out and every track loaded later.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+        for active in self.stream.iter().chain(&self.fading_out) {
+            active.handle.set_volume(self.volume);
+        }
+        info!("Volume set to {:.0}%.", self.volume * 100.0);
+    }
+
+    /// **Add a Bookmark**
+    ///
+    /// Bookmarks the position of the current track and publishes `Event::BookmarkAdded`.
+    ///
+    /// # Parameters:
+    /// - `name`: The name of the bookmark, `Bookmark <n>` if `None`.
+    pub fn add_bookmark(&mut self, name: Option<String>) {
+        let Some(file) = self.stream.as_ref().and_then(|active| active.file.clone()) else {
+            self.emit(Event::Error(
+                "Only a loaded track that is a whole file (not a CUE track) can be bookmarked."
+                    .to_string(),
+            ));
+            return;
+        };
+        let bookmark = (self.bookmarks).add(&file, name, self.position().unwrap_or_default());
+        info!(
+            "Bookmarked {:?} at {:?} as {:?}.",
+            file.display(),
+            bookmark.position,
+            bookmark.name
+        );
+        self.emit(Event::BookmarkAdded {
+            path: file,
+            bookmark,
+        });
+    }
+
+    /// **Remove a Bookmark**
+    pub fn remove_bookmark(&mut self, path: &Path, bookmark: &Bookmark) {
+        if self.bookmarks.remove(path, bookmark) {
+            info!(
+                "Removed bookmark {:?} of {:?}.",
+                bookmark.name,
+                path.display()
+            );
+        } else {
+            self.emit(Event::Error(format!("No bookmark {:?}.", bookmark.name)));
+        }
+    }
+
+    /// **Jump to a Bookmark**
+    ///
+    /// Plays `track` from `position`. A track other than the current one is added to the
+    /// folder tracks (see `add_track`) and loaded first.
+    ///
+    /// # Parameters:
+    /// - `track`: The bookmarked audio file.
+    /// - `metadata`: Its tags, shown by listings.
+    /// - `position`: The bookmarked position.
+    pub fn play_bookmark(&mut self, track: PathBuf, metadata: TrackMetadata, position: Duration) {
+        let playing_track = self.stream.as_ref().and_then(|active| active.file.as_ref());
+        if playing_track != Some(&track) {
+            let index = self.add_track(track, metadata);
+            self.queued_track = None;
+            self.audio_model.current_index = index;
+            self.load_current();
+        }
+        if self.stream.is_some() {
+            self.seek(position); // A new stream is silent, so it jumps right away.
+            if !self.is_playing {
+                self.play();
+            }
+        }
+    }
+
+    /// **Set the Resume Length**
+    ///
+    /// # Parameters:
+    /// - `resume_after`: Files at least this long start where they were left, `None`
+    ///   turns resuming off.
+    pub fn set_resume_after(&mut self, resume_after: Option<Duration>) {
+        self.bookmarks.set_resume_after(resume_after);
+        info!("Resuming files from {:?} on.", resume_after);
+    }
+
+    /// **Restore a Saved Session**
+    ///
+    /// Goes back to the track and position of `session` and takes over its volume, crossfade, shuffle
+    /// and repeat modes and queue. The folder tracks must already be the ones of the
+    /// session's source. Playback starts if it was running when the session was saved.
+    ///
+    /// # Parameters:
+    /// - `session`: The session saved when the player last quit. Queued tracks whose file
+    ///   is gone are dropped; a current track that is gone leaves the first track current.
+    fn restore_session(&mut self, session: SessionModel) {
+        self.set_volume(session.volume);
+        self.set_crossfade(session.crossfade);
+        self.audio_model.repeat = session.repeat;
+        self.queue.clear();
+        for track in session.queue.into_iter().filter(|track| track.is_file()) {
+            self.queue.enqueue(track);
+        }
+
+        // The saved index tells apart the CUE tracks of one file, unless the tracks moved.
+        let files = &self.audio_model.files;
+        self.queued_track = None;
+        match session.track {
+            Some(track) if files.get(session.index) == Some(&track) => {
+                self.audio_model.current_index = session.index;
+            }
+            Some(track) => match files.iter().position(|file| *file == track) {
+                Some(index) => self.audio_model.current_index = index,
+                None if track.is_file() => {
+                    // A queued track was playing, the folder order continues after it.
+                    if session.index < files.len() {
+                        self.audio_model.current_index = session.index;
+                    }
+                    self.queued_track = Some(track);
+                }
+                None => warn!("{:?} is gone, starting over.", track.display()),
+            },
+            None => {}
+        }
+        self.audio_model.set_shuffle(session.shuffle); // The shuffled order starts here.
+        info!("Restored the session of {:?}.", session.source.display());
+
+        self.load_current();
+        if self.stream.is_some() && !session.position.is_zero() {
+            self.seek(session.position); // Paused, so it jumps right away.
+        }
+        if session.playing {
+            self.play();
+        }
+        self.emit(Event::QueueChanged);
+    }
+
+    /// **Fade Out Before Halting**
+    ///
+    /// Starts the fade-out of the current stream (and of any crossfade still running) and
+    /// records what `tick` should do once it is silent.
+    ///
+    /// # Returns:
+    /// - `true` if a stream was available, `false` otherwise.
+    fn fade_out_and_halt(&mut self, halt: Halt) -> bool {
+        let active = match self.stream {
+            Some(ref active) => active,
+            None => return false,
+        };
+        active.handle.ramp_to(0.0, self.fade, FadeCurve::Linear);
+        for outgoing in &self.fading_out {
+            outgoing.handle.ramp_to(0.0, self.fade, FadeCurve::Linear);
         }
+        self.pending_halt = Some(halt);
+        self.is_playing = false; // Update the playback state.
+        self.pause_playback();
+        true
     }
 
     /// **Play the Next Track**
     ///
-    /// Advances to the next track in the `AudioFolderModel` and starts playback.
+    /// Plays the front of the queue if anything is queued, otherwise advances to the next
+    /// track in the `AudioFolderModel`, and starts playback.
     pub fn next(&mut self) {
-        println!("[SENSIT_LOG] Playing next track...");
-        self.audio_model.next_track(); // Move to the next track.
-        self.load_current(); // Load and play the next track.
-        self.play();
+        info!("Playing next track...");
+        let from = self.get_current_file();
+        if let Some(track) = self.queue.pop() {
+            self.queued_track = Some(track); // Queued tracks come before the folder order.
+            self.emit(Event::QueueChanged);
+        } else {
+            self.queued_track = None;
+            self.audio_model.next_track(); // Move to the next track.
+        }
+        self.change_track(from.as_deref()); // Load and play the next track.
     }
 
     /// **Play the Previous Track**
     ///
-    /// Moves to the previous track in the `AudioFolderModel` and starts playback.
+    /// Moves to the previous track in the `AudioFolderModel` and starts playback. While a
+    /// queued track plays, this returns to the folder track that played before it.
     pub fn prev(&mut self) {
-        println!("[SENSIT_LOG] Playing previous track...");
-        self.audio_model.prev_track(); // Move to the previous track.
-        self.load_current(); // Load and play the previous track.
+        info!("Playing previous track...");
+        let from = self.get_current_file();
+        if self.queued_track.take().is_none() {
+            self.audio_model.prev_track(); // Move to the previous track.
+        }
+        self.change_track(from.as_deref()); // Load and play the previous track.
+    }
+
+    /// **Set the Crossfade**
+    ///
+    /// Replaces the crossfade settings used for the following track changes.
+    ///
+    /// # Parameters:
+    /// - `crossfade`: The new duration and curve. A zero duration disables crossfading.
+    pub fn set_crossfade(&mut self, crossfade: CrossfadeModel) {
+        if crossfade.is_enabled() {
+            info!(
+                "Crossfade set to {:?} ({:?}).",
+                crossfade.duration(),
+                crossfade.curve()
+            );
+        } else {
+            info!("Crossfade disabled.");
+        }
+        self.crossfade = crossfade;
+    }
+
+    /// **Advance Background Work**
+    ///
+    /// Must be called periodically (the `AsyncPlayerController` thread does it). Drops the
+    /// streams of finished crossfades, pauses the stream once a pause/stop fade-out is
+    /// silent, publishes position ticks, and moves on to the upcoming track automatically
+    /// when the current one ends, starting the crossfade early when it applies.
+    pub fn tick(&mut self) {
+        self.fading_out
+            .retain(|active| !active.handle.is_silent() && !active.handle.is_finished());
+
+        if let (Some(halt), Some(ref active)) = (self.pending_halt, &self.stream) {
+            if active.handle.is_silent() {
+                // The fade-out already muted it, a stream that cannot pause stays silent.
+                if let Err(err) = active.stream.pause() {
+                    error!("Failed to pause the stream: {}", err);
+                    self.emit(Event::Error(format!("Could not pause the output: {}", err)));
+                }
+                if halt == Halt::Stop {
+                    let start = self.current_cue_track().map_or(Duration::ZERO, |t| t.start);
+                    active.handle.seek(start, Duration::ZERO);
+                }
+                self.pending_halt = None;
+            }
+        }
+
+        if !self.is_playing {
+            return;
+        }
+        if self.last_position_event.elapsed() >= POSITION_INTERVAL {
+            if let Some(position) = self.position() {
+                self.emit(Event::PositionUpdated(position));
+            }
+            self.last_position_event = Instant::now();
+        }
+
+        let Some(ref active) = self.stream else {
+            return;
+        };
+        // CUE tracks that follow each other in one file play on gaplessly: the stream keeps
+        // running past the end of the current track, which only moves the model along.
+        if let Some(next) = self.contiguous_cue_track() {
+            active.handle.set_end(next.end);
+            if active.handle.position() >= next.start {
+                if let Some(path) = self.get_current_file() {
+                    self.emit(Event::TrackFinished {
+                        index: self.current_folder_index(),
+                        path,
+                    });
+                }
+                self.end_playback(true);
+                self.audio_model.advance();
+                self.start_playback();
+                info!("Continuing with the next CUE track...");
+                self.emit_track_changed();
+                self.preload_upcoming();
+            }
+            return;
+        }
+        if let Some(cue_track) = self.current_cue_track() {
+            active.handle.set_end(cue_track.end); // Undo an extension the play order no longer allows.
+        }
+        let (remaining, finished) = (active.handle.remaining(), active.handle.is_finished());
+
+        let Some(from) = self.get_current_file() else {
+            return;
+        };
+        let upcoming = self.upcoming_track();
+        let crossfade_due = match upcoming {
+            Some(ref upcoming) => {
+                remaining <= self.crossfade.duration()
+                    && self.crossfade.applies_between(
+                        &from,
+                        &self.metadata_of(&from),
+                        upcoming,
+                        &self.metadata_of(upcoming),
+                    )
+            }
+            None => false,
+        };
+        self.end_track(from, crossfade_due, finished);
+    }
+
+    /// **End the Current Track**
+    ///
+    /// Moves on to the next track once `from` is finished, or crossfades into it when
+    /// `crossfade_due`. Without a next track, the track plays to its end and playback
+    /// stops there, as without a crossfade.
+    fn end_track(&mut self, from: PathBuf, crossfade_due: bool, finished: bool) {
+        let index = self.current_folder_index();
+        let crossfade = crossfade_due && self.advance_automatically();
+        if !crossfade && !finished {
+            return;
+        }
+        self.emit(Event::TrackFinished { index, path: from });
+
+        if crossfade {
+            info!("Track ending, crossfading into the next track...");
+            self.crossfade_to_current();
+        } else if self.advance_automatically() {
+            info!("Track finished, playing next track...");
+            self.load_current();
+            self.play();
+        } else {
+            info!("Reached the end of the play order.");
+            self.stop();
+        }
+    }
+
+    /// **Switch to the Current Track**
+    ///
+    /// Called after the model moved away from `from` (`None` if there was no track).
+    /// Crossfades when playback is running and the crossfade applies to the pair of tracks,
+    /// otherwise cuts to the new track.
+    fn change_track(&mut self, from: Option<&Path>) {
+        match (from, self.get_current_file()) {
+            (_, None) => {
+                self.load_current(); // Fades out what played and reports that nothing is left.
+                self.is_playing = false;
+            }
+            (Some(from), Some(to))
+                if self.is_playing
+                    && self.crossfade.applies_between(
+                        from,
+                        &self.metadata_of(from),
+                        &to,
+                        &self.metadata_of(&to),
+                    ) =>
+            {
+                self.crossfade_to_current();
+            }
+            _ => {
+                self.load_current();
+                self.play();
+            }
+        }
+    }
+
+    /// **Crossfade into the Current Track**
+    ///
+    /// Fades the playing stream out while a new stream for the current track fades in.
+    /// The overlap is shortened if the outgoing track has less time left than configured.
+    fn crossfade_to_current(&mut self) {
+        let curve = self.crossfade.curve();
+        let mut duration = self.crossfade.duration();
+
+        let mut fading = None;
+        if let Some(outgoing) = self.stream.take() {
+            Self::leave(&mut self.bookmarks, &outgoing);
+            // Crossfading at the end of the track (not after a skip) finishes it.
+            self.end_playback(outgoing.handle.remaining() <= duration);
+            duration = duration.min(outgoing.handle.remaining());
+            outgoing.handle.ramp_to(0.0, duration, curve);
+            fading = Some(Arc::clone(&outgoing.handle));
+            self.fading_out.push(outgoing); // Keep it alive until the fade-out completes.
+        }
+
+        info!("Crossfading over {:?}.", duration);
+        self.stream = self.open_current();
+        if let (None, Some(handle)) = (&self.stream, fading) {
+            // Nothing to fade into, playback stops: fade out as quickly as a failed load does.
+            handle.ramp_to(0.0, self.fade, FadeCurve::Linear);
+        }
         self.play();
+        if let Some(ref incoming) = self.stream {
+            incoming.handle.ramp_to(1.0, duration, curve);
+        }
+        self.preload_upcoming();
+    }
+}
+
+impl Drop for PlayerController {
+    /// Records the playback of the track that plays when the player quits.
+    fn drop(&mut self) {
+        let finished = (self.stream.as_ref()).is_some_and(|active| active.handle.is_finished());
+        self.end_playback(finished);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::services::audio_loader::DecodedAudio;
+    use crate::modules::services::temp_folder::TempFolder;
+    use std::error::Error;
+    use std::sync::mpsc::Receiver;
+
+    /// A loader that fails to decode every file.
+    struct FailingLoader;
+
+    impl AudioLoader for FailingLoader {
+        fn decode(&self, _file_path: &Path) -> Result<DecodedAudio, Box<dyn Error>> {
+            Err("not decodable".into())
+        }
+
+        fn create_audio_stream(
+            &self,
+            _audio: Arc<DecodedAudio>,
+            _handle: Arc<PlaybackHandle>,
+        ) -> Result<Stream, Box<dyn Error>> {
+            Err("no output device".into())
+        }
+    }
+
+    /// Creates a controller over two tracks in different folders (so a crossfade applies
+    /// between them) that claims to be playing, as it would before its next track fails.
+    fn playing_controller(folder: &TempFolder) -> (PlayerController, Receiver<Event>) {
+        let events = Arc::new(EventBus::new());
+        let receiver = events.subscribe();
+        let mut controller = PlayerController::new(
+            AudioFolderModel::new(vec![
+                folder.path().join("a").join("one.wav"),
+                folder.path().join("b").join("two.wav"),
+            ]),
+            Box::new(FailingLoader),
+            BookmarkModel::new(None),
+            HistoryService::new(folder.path().join("history.tsv")),
+            None,
+            events,
+        );
+        controller.is_playing = true;
+        (controller, receiver)
+    }
+
+    /// Asserts that the failed load was reported and playback no longer claims to run.
+    fn assert_stopped(controller: &PlayerController, events: &Receiver<Event>) {
+        let events: Vec<Event> = events.try_iter().collect();
+        assert!(
+            events
+                .iter()
+                .any(|event| matches!(event, Event::Error(message) if message.contains("two.wav"))),
+            "{:?}",
+            events
+        );
+        assert_eq!(events.last(), Some(&Event::PlaybackStopped));
+        assert!(!controller.status().is_playing);
+    }
+
+    #[test]
+    fn sessions_with_missing_tracks_start_over() {
+        let folder = TempFolder::new("controller-session");
+        let one = folder.write("one.wav", b"not decodable");
+        let queued = folder.write("queued.wav", b"not decodable");
+        let crossfade = CrossfadeModel::new(Duration::from_secs(3), FadeCurve::Linear);
+        let session = SessionModel {
+            source: folder.path().to_path_buf(),
+            track: Some(folder.path().join("deleted.wav")),
+            index: 1,
+            position: Duration::from_secs(42),
+            playing: false,
+            volume: 0.5,
+            shuffle: false,
+            repeat: RepeatMode::All,
+            crossfade,
+            queue: vec![folder.path().join("gone.wav"), queued.clone()],
+        };
+        let controller = PlayerController::new(
+            AudioFolderModel::new(vec![one.clone(), folder.path().join("two.wav")]),
+            Box::new(FailingLoader),
+            BookmarkModel::new(None),
+            HistoryService::new(folder.path().join("history.tsv")),
+            Some(session),
+            Arc::new(EventBus::new()),
+        );
+
+        let status = controller.status();
+        assert_eq!(status.current_index, 0, "the saved track is gone");
+        assert_eq!(status.current_track, Some(one));
+        assert_eq!(status.queue, vec![queued], "gone queued tracks are dropped");
+        assert_eq!(status.volume, 0.5);
+        assert_eq!(status.repeat, RepeatMode::All);
+        assert_eq!(status.crossfade, crossfade);
+        assert!(!status.is_playing);
+    }
+
+    #[test]
+    fn failed_load_on_next_stops_playback() {
+        let folder = TempFolder::new("controller-next");
+        let (mut controller, events) = playing_controller(&folder);
+        controller.next();
+        assert_stopped(&controller, &events);
+    }
+
+    #[test]
+    fn crossfades_wait_for_a_next_track() {
+        let folder = TempFolder::new("controller-last");
+        let (mut controller, events) = playing_controller(&folder);
+        controller.audio_model.current_index = 1;
+        controller.audio_model.repeat = RepeatMode::Off;
+        let last = controller.get_current_file().unwrap();
+        events.try_iter().for_each(drop); // The failed first load.
+        controller.end_track(last.clone(), true, false);
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![],
+            "the last track plays on"
+        );
+        assert_eq!(controller.get_current_file(), Some(last));
+        assert!(controller.is_playing);
+    }
+
+    #[test]
+    fn failed_load_at_the_end_of_a_crossfade_stops_playback() {
+        let folder = TempFolder::new("controller-crossfade-end");
+        let (mut controller, events) = playing_controller(&folder);
+        let first = controller.get_current_file().unwrap();
+        events.try_iter().for_each(drop); // The failed first load.
+        controller.end_track(first.clone(), true, false);
+        assert_eq!(
+            events.try_recv().ok(),
+            Some(Event::TrackFinished {
+                index: Some(0),
+                path: first
+            })
+        );
+        assert_stopped(&controller, &events);
+    }
+
+    #[test]
+    fn failed_load_while_crossfading_stops_playback() {
+        let folder = TempFolder::new("controller-crossfade");
+        let (mut controller, events) = playing_controller(&folder);
+        controller.set_crossfade(CrossfadeModel::new(
+            Duration::from_secs(2),
+            FadeCurve::EqualPower,
+        ));
+        controller.next();
+        assert_stopped(&controller, &events);
     }
 }
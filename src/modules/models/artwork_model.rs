@@ -0,0 +1,21 @@
+use std::path::PathBuf;
+
+/// `ArtworkSource` tells where the artwork of a track was found.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArtworkSource {
+    /// A picture stored in the audio file (ID3v2 `APIC`, FLAC `PICTURE`, MP4 `covr`).
+    Embedded,
+    /// An image file in the track's folder (`cover.jpg`, `folder.png`, ...).
+    Folder(PathBuf),
+}
+
+/// `Artwork` is the cover image of a track, still encoded (JPEG, PNG, ...).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Artwork {
+    /// MIME type of `data`, e.g. `image/jpeg`.
+    pub media_type: String,
+    /// The encoded image.
+    pub data: Vec<u8>,
+    /// Where the image was found.
+    pub source: ArtworkSource,
+}
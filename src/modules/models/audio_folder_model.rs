@@ -1,12 +1,163 @@
-use std::path::PathBuf;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::cue_model::{CueFile, CueTrack};
+use super::folder_change_model::renamed_path;
+use super::library_model::LibraryEntry;
+use super::playlist_model::PlaylistModel;
+use super::track_metadata_model::TrackMetadata;
+
+/// `RepeatMode` controls what happens when a track finishes on its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RepeatMode {
+    /// Stop after the last track of the play order.
+    Off,
+    /// Wrap around to the first track of the play order.
+    All,
+    /// Play the current track again.
+    One,
+}
+
+impl RepeatMode {
+    /// Returns the mode that follows this one when cycling (Off -> All -> One -> Off).
+    pub fn cycle(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::All,
+            RepeatMode::All => RepeatMode::One,
+            RepeatMode::One => RepeatMode::Off,
+        }
+    }
+
+    /// Parses a mode name (`off`, `all`, `one`), as written by its `Debug` form.
+    ///
+    /// # Returns
+    /// * `Some(RepeatMode)` if the name is known, otherwise `None`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "off" => Some(RepeatMode::Off),
+            "all" => Some(RepeatMode::All),
+            "one" => Some(RepeatMode::One),
+            _ => None,
+        }
+    }
+}
+
+/// `SortKey` selects the order of the tracks in an `AudioFolderModel`.
+/// Ties (and tracks missing the value) fall back to the natural path order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    /// Natural order of the paths, so `2 - x` comes before `10 - x` (the scan order).
+    Name,
+    /// Oldest modification time first.
+    Modified,
+    /// Album by album (folder), then by disc and track number from the tags.
+    TrackNumber,
+    /// Shortest first.
+    Duration,
+}
+
+impl SortKey {
+    /// Parses a sort key name as typed in the CLI (`name`, `mtime`, `track`, `duration`).
+    ///
+    /// # Returns
+    /// * `Some(SortKey)` if the name is known, otherwise `None`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "name" => Some(SortKey::Name),
+            "mtime" | "modified" | "date" => Some(SortKey::Modified),
+            "track" | "tracknumber" => Some(SortKey::TrackNumber),
+            "duration" | "length" => Some(SortKey::Duration),
+            _ => None,
+        }
+    }
+
+    /// Compares two tracks by this key, `None` values sorting after known ones.
+    fn compare(
+        &self,
+        (a, a_meta): (&Path, &TrackMetadata),
+        (b, b_meta): (&Path, &TrackMetadata),
+    ) -> Ordering {
+        fn known_first<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+            match (a, b) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        }
+        let by_key = match self {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Modified => known_first(a_meta.modified, b_meta.modified),
+            SortKey::TrackNumber => natural_cmp(
+                &a.parent().unwrap_or(a).to_string_lossy(),
+                &b.parent().unwrap_or(b).to_string_lossy(),
+            )
+            .then(known_first(a_meta.disc_number, b_meta.disc_number))
+            .then(known_first(a_meta.track_number, b_meta.track_number)),
+            SortKey::Duration => known_first(a_meta.duration, b_meta.duration),
+        };
+        by_key.then_with(|| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()))
+    }
+}
+
+/// Compares two names in natural order: runs of digits compare by their numeric value,
+/// everything else compares case-insensitively, so `Track 2` < `track 10`.
+/// Names that only differ in case or leading zeros fall back to plain string order,
+/// so the result is a total, stable order.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chars, mut b_chars) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                        digits.push(c);
+                    }
+                    digits.trim_start_matches('0').to_string()
+                };
+                let (x, y) = (take_number(&mut a_chars), take_number(&mut b_chars));
+                // Without leading zeros, a longer number is a bigger one.
+                let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
 
 /// `AudioFolderModel` represents a collection of audio files with the capability to
-/// track the current playing file's index and navigate between tracks.
+/// track the current playing file's index and navigate between tracks, either in
+/// folder order or in a shuffled order.
+#[derive(Clone, Debug)]
 pub struct AudioFolderModel {
     /// A vector containing the paths to all audio files.
     pub files: Vec<PathBuf>,
     /// The index of the currently playing track.
     pub current_index: usize,
+    /// What happens when a track finishes on its own.
+    pub repeat: RepeatMode,
+    /// Play order as indices into `files` while shuffle is on, `None` for folder order.
+    shuffle_order: Option<Vec<usize>>,
+    /// The CUE track each entry of `files` plays, `None` for a whole file.
+    /// A file split by a CUE sheet appears once per track in `files`.
+    cue_tracks: Vec<Option<CueTrack>>,
+    /// The tags of the file each entry of `files` plays, empty until `attach_metadata`.
+    metadata: Vec<TrackMetadata>,
 }
 
 impl AudioFolderModel {
@@ -19,51 +170,613 @@ impl AudioFolderModel {
     /// * `AudioFolderModel` - A new instance initialized with the provided files.
     pub fn new(files: Vec<PathBuf>) -> Self {
         Self {
+            cue_tracks: vec![None; files.len()],
+            metadata: vec![TrackMetadata::default(); files.len()],
             files,
-            current_index: 0, // Start with the first track by default.
+            current_index: 0,        // Start with the first track by default.
+            repeat: RepeatMode::All, // Wrap around at the end, like the player always did.
+            shuffle_order: None,
         }
     }
 
+    /// Creates a new `AudioFolderModel` from the entries of a playlist, in playlist order.
+    /// The titles and durations the playlist states are the metadata until tags are read
+    /// (and stay where the tags have none, see `attach_metadata`).
+    ///
+    /// # Arguments
+    /// * `playlist` - A loaded playlist (M3U, ...). Missing entries are not included.
+    pub fn from_playlist(playlist: &PlaylistModel) -> Self {
+        let mut model = Self::new(
+            playlist
+                .entries
+                .iter()
+                .map(|entry| entry.path.clone())
+                .collect(),
+        );
+        model.metadata = playlist
+            .entries
+            .iter()
+            .map(|entry| TrackMetadata {
+                title: entry.title.clone(),
+                duration: entry.duration,
+                ..TrackMetadata::default()
+            })
+            .collect();
+        model
+    }
+
+    /// Creates a new `AudioFolderModel` from library entries, in the given order. The
+    /// metadata stored in the library is used, the files are not read again.
+    ///
+    /// # Arguments
+    /// * `entries` - The result of a library query (see `LibraryModel::query`).
+    pub fn from_library(entries: &[&LibraryEntry]) -> Self {
+        let mut model = Self::new(entries.iter().map(|entry| entry.path.clone()).collect());
+        model.metadata = entries.iter().map(|entry| entry.metadata.clone()).collect();
+        model
+    }
+
+    /// Adds a whole file at its place in natural path order (where a scan of the folder
+    /// puts it), unless it is already one of them. The current track stays current, a
+    /// shuffled play order gets the new track at its end.
+    ///
+    /// # Arguments
+    /// * `file` - The audio file.
+    /// * `metadata` - Its tags (from the library).
+    ///
+    /// # Returns
+    /// * The index of the track in `files`.
+    pub fn add_track(&mut self, file: PathBuf, metadata: TrackMetadata) -> usize {
+        if let Some(index) = (0..self.files.len())
+            .find(|&index| self.files[index] == file && self.cue_tracks[index].is_none())
+        {
+            return index;
+        }
+        let name = file.to_string_lossy().into_owned();
+        let index = (self.files.iter())
+            .position(|other| natural_cmp(&other.to_string_lossy(), &name) == Ordering::Greater)
+            .unwrap_or(self.files.len());
+        self.files.insert(index, file);
+        self.cue_tracks.insert(index, None);
+        self.metadata.insert(index, metadata);
+        if self.files.len() > 1 && self.current_index >= index {
+            self.current_index += 1;
+        }
+        if let Some(ref mut order) = self.shuffle_order {
+            for old in order.iter_mut().filter(|old| **old >= index) {
+                *old += 1;
+            }
+            order.push(index);
+        }
+        index
+    }
+
+    /// Removes the tracks of a deleted file or folder. The current track stays current if it
+    /// is kept, otherwise the next kept track in folder order takes its place. Removing
+    /// every track leaves the model empty.
+    ///
+    /// # Arguments
+    /// * `path` - The deleted file, or a folder whose tracks are all removed.
+    ///
+    /// # Returns
+    /// * The number of tracks removed.
+    pub fn remove_tracks(&mut self, path: &Path) -> usize {
+        let keep: Vec<bool> = self
+            .files
+            .iter()
+            .map(|file| !file.starts_with(path))
+            .collect();
+        self.retain_tracks(&keep)
+    }
+
+    /// Keeps the tracks whose `keep` entry is set. The current track stays current if it
+    /// is kept, otherwise the next kept track in folder order takes its place.
+    ///
+    /// # Returns
+    /// * The number of tracks removed.
+    fn retain_tracks(&mut self, keep: &[bool]) -> usize {
+        let count = self.files.len();
+        // new_index[old] is where a kept track ends up.
+        let mut new_index = vec![None; count];
+        let mut kept = 0;
+        for old in (0..count).filter(|&old| keep[old]) {
+            new_index[old] = Some(kept);
+            kept += 1;
+        }
+        if kept == count {
+            return 0;
+        }
+
+        let current = self.current_index;
+        self.current_index = (current..count)
+            .chain(0..current)
+            .find_map(|old| new_index[old])
+            .unwrap_or(0);
+        let keep = |old: &usize| new_index[*old].is_some();
+        let indices: Vec<usize> = (0..count).filter(keep).collect();
+        self.files = indices.iter().map(|&old| self.files[old].clone()).collect();
+        self.cue_tracks = indices
+            .iter()
+            .map(|&old| self.cue_tracks[old].clone())
+            .collect();
+        self.metadata = indices
+            .iter()
+            .map(|&old| self.metadata[old].clone())
+            .collect();
+        if let Some(ref mut order) = self.shuffle_order {
+            *order = order.iter().filter_map(|&old| new_index[old]).collect();
+        }
+        count - kept
+    }
+
+    /// Points the tracks of a renamed file or folder to their new paths. A file moved onto
+    /// a track replaces it: the tracks of the replaced file are removed, and if one of them
+    /// was current the moved track takes its place.
+    ///
+    /// # Returns
+    /// * The number of tracks renamed.
+    pub fn rename_tracks(&mut self, from: &Path, to: &Path) -> usize {
+        let moved: Vec<bool> = self
+            .files
+            .iter()
+            .map(|file| file.starts_with(from))
+            .collect();
+        for file in self.files.iter_mut() {
+            if let Some(path) = renamed_path(file, from, to) {
+                *file = path;
+            }
+        }
+        // The moved track a file now is, if it was moved at all.
+        let moved_to =
+            |file: &PathBuf| (0..self.files.len()).find(|&i| moved[i] && &self.files[i] == file);
+        let keep: Vec<bool> = (0..self.files.len())
+            .map(|index| moved[index] || moved_to(&self.files[index]).is_none())
+            .collect();
+        if keep.get(self.current_index) == Some(&false) {
+            self.current_index =
+                moved_to(&self.files[self.current_index]).unwrap_or(self.current_index);
+        }
+        self.retain_tracks(&keep);
+        moved.iter().filter(|&&moved| moved).count()
+    }
+
     /// Returns a reference to the `PathBuf` of the currently playing audio file.
     ///
-    /// # Panics
-    /// If the `files` vector is empty, this will panic due to an out-of-bounds access.
+    /// # Returns
+    /// * `Some(&PathBuf)` - The current file.
+    /// * `None` - If there are no tracks (an empty folder, or every track was deleted).
     ///
     /// # Example
     /// ```
-    /// let current_file = audio_model.get_current_file();
-    /// println!("Now playing: {:?}", current_file);
+    /// if let Some(current_file) = audio_model.get_current_file() {
+    ///     println!("Now playing: {:?}", current_file);
+    /// }
     /// ```
-    pub fn get_current_file(&self) -> &PathBuf {
-        &self.files[self.current_index]
+    pub fn get_current_file(&self) -> Option<&PathBuf> {
+        self.files.get(self.current_index)
+    }
+
+    /// Returns `true` if there are no tracks.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Returns the CUE track the current entry plays, `None` for a whole file.
+    pub fn current_cue_track(&self) -> Option<&CueTrack> {
+        self.cue_track(self.current_index)
+    }
+
+    /// Returns the CUE track the entry at `index` plays, `None` for a whole file.
+    pub fn cue_track(&self, index: usize) -> Option<&CueTrack> {
+        self.cue_tracks.get(index).and_then(Option::as_ref)
+    }
+
+    /// Reads the metadata of every track with `read`, once per file. A title or duration
+    /// the tags do not have keeps the one the track already had (from a playlist).
+    ///
+    /// # Arguments
+    /// * `read` - Reads the tags of one audio file (see `MetadataService::read`).
+    pub fn attach_metadata(&mut self, read: impl Fn(&Path) -> TrackMetadata) {
+        let mut by_file: HashMap<&PathBuf, TrackMetadata> = HashMap::new();
+        self.metadata = self
+            .files
+            .iter()
+            .zip(&self.metadata)
+            .map(|(file, known)| {
+                let mut metadata = by_file.entry(file).or_insert_with(|| read(file)).clone();
+                metadata.title = metadata.title.or_else(|| known.title.clone());
+                metadata.duration = metadata.duration.or(known.duration);
+                metadata
+            })
+            .collect();
     }
 
-    /// Advances to the next track in the list.
+    /// Replaces the metadata of every entry that plays `path`.
+    pub fn update_metadata(&mut self, path: &Path, metadata: TrackMetadata) {
+        for (file, entry) in self.files.iter().zip(&mut self.metadata) {
+            if file == path {
+                *entry = metadata.clone();
+            }
+        }
+    }
+
+    /// Returns the metadata of the current entry, see `track_metadata`.
+    pub fn current_metadata(&self) -> TrackMetadata {
+        self.track_metadata(self.current_index)
+    }
+
+    /// Returns the metadata of the entry at `index`. CUE tracks take their title,
+    /// performer, number and length from the sheet, the tags describe the whole file.
+    pub fn track_metadata(&self, index: usize) -> TrackMetadata {
+        let mut metadata = self.metadata.get(index).cloned().unwrap_or_default();
+        if let Some(cue_track) = self.cue_track(index) {
+            metadata.title = cue_track.title.clone().or(metadata.title);
+            metadata.artist = cue_track.performer.clone().or(metadata.artist);
+            metadata.track_number = Some(cue_track.number);
+            metadata.duration = cue_track.end.map(|end| end.saturating_sub(cue_track.start));
+        }
+        metadata
+    }
+
+    /// Replaces every file that a CUE sheet splits into tracks by one entry per track,
+    /// so next/previous move between the tracks of the sheet. The current entry moves to
+    /// the first track of its file.
+    ///
+    /// # Arguments
+    /// * `sheets` - The files of the CUE sheets found for the tracks.
+    pub fn split_cue_sheets(&mut self, sheets: &[CueFile]) {
+        let mut files = Vec::with_capacity(self.files.len());
+        let mut cue_tracks = Vec::with_capacity(self.files.len());
+        let mut metadata = Vec::with_capacity(self.files.len());
+        let mut current_index = self.current_index;
+        let entries = self.files.iter().zip(&self.cue_tracks).zip(&self.metadata);
+        for (index, ((file, cue_track), tags)) in entries.enumerate() {
+            if index == self.current_index {
+                current_index = files.len();
+            }
+            let sheet = sheets.iter().find(|sheet| &sheet.path == file);
+            match (sheet, cue_track) {
+                (Some(sheet), None) => {
+                    for track in &sheet.tracks {
+                        files.push(file.clone());
+                        cue_tracks.push(Some(track.clone()));
+                        metadata.push(tags.clone());
+                    }
+                }
+                _ => {
+                    files.push(file.clone());
+                    cue_tracks.push(cue_track.clone());
+                    metadata.push(tags.clone());
+                }
+            }
+        }
+        self.files = files;
+        self.cue_tracks = cue_tracks;
+        self.metadata = metadata;
+        self.current_index = current_index;
+        self.set_shuffle(self.is_shuffled()); // Indices changed, shuffle the new entries.
+    }
+
+    /// Returns the index of the track that plays when the current one finishes on its own,
+    /// following the shuffle order and the repeat mode.
+    ///
+    /// # Returns
+    /// * `Some(index)` - The index into `files` of the upcoming track.
+    /// * `None` - If the end of the play order is reached and repeat is off, or there are
+    ///   no tracks.
+    pub fn peek_next(&self) -> Option<usize> {
+        if self.is_empty() {
+            return None;
+        }
+        match self.repeat {
+            RepeatMode::One => Some(self.current_index),
+            RepeatMode::All => Some(self.step(1)),
+            RepeatMode::Off => {
+                if self.order_position() + 1 < self.files.len() {
+                    Some(self.step(1))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Returns the index `next_track` would move to, whatever the repeat mode.
+    ///
+    /// # Returns
+    /// * `Some(index)` - The index into `files` of the next track of the play order.
+    /// * `None` - If there are no tracks.
+    pub fn peek_following(&self) -> Option<usize> {
+        (!self.is_empty()).then(|| self.step(1))
+    }
+
+    /// Moves to the track returned by `peek_next`, used when a track finishes on its own.
+    ///
+    /// # Returns
+    /// * `true` if there is a track to move to, `false` at the end of the play order.
+    pub fn advance(&mut self) -> bool {
+        match self.peek_next() {
+            Some(index) => {
+                self.current_index = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Advances to the next track in the play order.
     /// If the end of the list is reached, it wraps around to the first track.
+    /// Without tracks, nothing happens.
     ///
     /// # Example
     /// ```
     /// audio_model.next_track();
     /// ```
     pub fn next_track(&mut self) {
-        // Use modulo to wrap around to the first track if at the end of the list.
-        self.current_index = (self.current_index + 1) % self.files.len();
+        if !self.is_empty() {
+            self.current_index = self.step(1);
+        }
     }
 
-    /// Moves to the previous track in the list.
+    /// Moves to the previous track in the play order.
     /// If the current track is the first one, it wraps around to the last track.
+    /// Without tracks, nothing happens.
     ///
     /// # Example
     /// ```
     /// audio_model.prev_track();
     /// ```
     pub fn prev_track(&mut self) {
-        if self.current_index == 0 {
-            // Wrap around to the last track if at the first track.
-            self.current_index = self.files.len() - 1;
+        if !self.is_empty() {
+            self.current_index = self.step(self.files.len() - 1);
+        }
+    }
+
+    /// Reorders the tracks by `key`. The current track stays current and a shuffled play
+    /// order stays the same, only the positions in `files` change.
+    ///
+    /// Keys other than the name use the metadata from `attach_metadata`.
+    ///
+    /// # Arguments
+    /// * `key` - The sort key.
+    pub fn sort(&mut self, key: SortKey) {
+        let metadata: Vec<TrackMetadata> = (0..self.files.len())
+            .map(|index| self.track_metadata(index))
+            .collect();
+        let cue_start = |index: usize| self.cue_track(index).map(|track| track.start);
+
+        let mut order: Vec<usize> = (0..self.files.len()).collect();
+        order.sort_by(|&a, &b| {
+            key.compare(
+                (&self.files[a], &metadata[a]),
+                (&self.files[b], &metadata[b]),
+            )
+            .then_with(|| cue_start(a).cmp(&cue_start(b))) // Tracks of one file in sheet order.
+        });
+
+        // new_index[old] is where the track at `old` ends up.
+        let mut new_index = vec![0; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            new_index[old] = new;
+        }
+        self.files = order.iter().map(|&old| self.files[old].clone()).collect();
+        self.cue_tracks = order
+            .iter()
+            .map(|&old| self.cue_tracks[old].clone())
+            .collect();
+        self.metadata = order
+            .iter()
+            .map(|&old| self.metadata[old].clone())
+            .collect();
+        if let Some(current) = new_index.get(self.current_index) {
+            self.current_index = *current;
+        }
+        if let Some(ref mut shuffle_order) = self.shuffle_order {
+            for index in shuffle_order.iter_mut() {
+                *index = new_index[*index];
+            }
+        }
+    }
+
+    /// Returns `true` while the tracks play in a shuffled order.
+    pub fn is_shuffled(&self) -> bool {
+        self.shuffle_order.is_some()
+    }
+
+    /// Switches shuffle on or off. A new shuffle starts with the current track, so
+    /// playback continues where it is and the rest of the tracks follow in random order.
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        self.shuffle_order = if shuffle {
+            Some(Self::shuffled_order(self.files.len(), self.current_index))
         } else {
-            // Otherwise, move to the previous track.
-            self.current_index -= 1;
+            None
+        };
+    }
+
+    /// Returns the index `offset` steps further along the play order, wrapping around.
+    /// Only called with tracks, `files.len()` is never zero here.
+    fn step(&self, offset: usize) -> usize {
+        let position = (self.order_position() + offset) % self.files.len();
+        match self.shuffle_order {
+            Some(ref order) => order[position],
+            None => position,
         }
     }
+
+    /// Returns the position of the current track within the play order.
+    fn order_position(&self) -> usize {
+        match self.shuffle_order {
+            Some(ref order) => order
+                .iter()
+                .position(|&index| index == self.current_index)
+                .unwrap_or(0),
+            None => self.current_index,
+        }
+    }
+
+    /// Builds a random permutation of `0..len` that starts with `first`
+    /// (Fisher-Yates with a small xorshift generator seeded from the clock).
+    fn shuffled_order(len: usize, first: usize) -> Vec<usize> {
+        let mut seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or(0x2545_f491_4f6c_dd1d)
+            | 1;
+        let mut random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        let mut order: Vec<usize> = (0..len).filter(|&index| index != first).collect();
+        for i in (1..order.len()).rev() {
+            order.swap(i, (random() % (i as u64 + 1)) as usize);
+        }
+        if first < len {
+            order.insert(0, first);
+        }
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(files: &[&str]) -> AudioFolderModel {
+        AudioFolderModel::new(files.iter().map(PathBuf::from).collect())
+    }
+
+    fn names(model: &AudioFolderModel) -> Vec<&str> {
+        model
+            .files
+            .iter()
+            .map(|file| file.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn numbers_compare_by_value() {
+        assert_eq!(natural_cmp("2", "10"), Ordering::Less);
+        assert_eq!(natural_cmp("Track 10", "track 2"), Ordering::Greater);
+        assert_eq!(natural_cmp("007", "10"), Ordering::Less);
+        assert_eq!(
+            natural_cmp("99999999999999999999", "100000000000000000000"),
+            Ordering::Less,
+            "numbers longer than any integer type"
+        );
+    }
+
+    #[test]
+    fn ties_fall_back_to_string_order() {
+        assert_eq!(natural_cmp("01", "1"), Ordering::Less, "leading zeros");
+        assert_eq!(natural_cmp("1", "01"), Ordering::Greater);
+        assert_eq!(natural_cmp("Track", "track"), Ordering::Less, "case only");
+        assert_eq!(natural_cmp("track", "Track"), Ordering::Greater);
+        assert_eq!(natural_cmp("Track 1", "Track 1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn digit_and_text_runs_compare_in_turn() {
+        assert_eq!(natural_cmp("a2b9", "a2b10"), Ordering::Less);
+        assert_eq!(natural_cmp("a2b", "a10"), Ordering::Less);
+        assert_eq!(natural_cmp("cd2 track1", "CD10 track1"), Ordering::Less);
+        assert_eq!(
+            natural_cmp("1 intro", "intro"),
+            Ordering::Less,
+            "digits before letters"
+        );
+        assert_eq!(
+            natural_cmp("disc 1", "disc 1 bonus"),
+            Ordering::Less,
+            "prefix first"
+        );
+    }
+
+    #[test]
+    fn added_tracks_take_their_natural_place() {
+        let mut tracks = model(&["/m/2 b.mp3", "/m/10 d.mp3"]);
+        tracks.current_index = 1;
+        assert_eq!(
+            tracks.add_track("/m/3 c.mp3".into(), TrackMetadata::default()),
+            1
+        );
+        assert_eq!(
+            tracks.add_track("/m/1 a.mp3".into(), TrackMetadata::default()),
+            0
+        );
+        assert_eq!(
+            tracks.add_track("/m/20 e.mp3".into(), TrackMetadata::default()),
+            4
+        );
+        assert_eq!(
+            tracks.add_track("/m/3 c.mp3".into(), TrackMetadata::default()),
+            2
+        );
+        assert_eq!(
+            names(&tracks),
+            vec![
+                "/m/1 a.mp3",
+                "/m/2 b.mp3",
+                "/m/3 c.mp3",
+                "/m/10 d.mp3",
+                "/m/20 e.mp3"
+            ]
+        );
+        assert_eq!(
+            tracks.get_current_file(),
+            Some(&PathBuf::from("/m/10 d.mp3"))
+        );
+
+        let mut empty = model(&[]);
+        assert_eq!(
+            empty.add_track("/m/a.mp3".into(), TrackMetadata::default()),
+            0
+        );
+        assert_eq!(empty.current_index, 0);
+    }
+
+    #[test]
+    fn added_tracks_join_the_shuffle_order() {
+        let mut tracks = model(&["/m/a.mp3", "/m/c.mp3", "/m/d.mp3"]);
+        tracks.set_shuffle(true);
+        let before: Vec<PathBuf> = (tracks.shuffle_order.as_ref().unwrap().iter())
+            .map(|&index| tracks.files[index].clone())
+            .collect();
+        let index = tracks.add_track("/m/b.mp3".into(), TrackMetadata::default());
+        let after: Vec<PathBuf> = (tracks.shuffle_order.as_ref().unwrap().iter())
+            .map(|&index| tracks.files[index].clone())
+            .collect();
+        assert_eq!(index, 1);
+        assert_eq!(after[..3], before[..]);
+        assert_eq!(after[3], PathBuf::from("/m/b.mp3"));
+    }
+
+    #[test]
+    fn renames_follow_files_and_folders() {
+        let mut tracks = model(&["/m/old/1.mp3", "/m/old/2.mp3", "/m/x.mp3"]);
+        tracks.current_index = 1;
+        assert_eq!(
+            tracks.rename_tracks(Path::new("/m/old"), Path::new("/m/new")),
+            2
+        );
+        assert_eq!(
+            names(&tracks),
+            vec!["/m/new/1.mp3", "/m/new/2.mp3", "/m/x.mp3"]
+        );
+        assert_eq!(tracks.current_index, 1);
+    }
+
+    #[test]
+    fn a_file_moved_onto_a_track_replaces_it() {
+        let mut tracks = model(&["/m/a.mp3", "/m/b.mp3", "/m/c.mp3"]);
+        tracks.current_index = 2;
+        assert_eq!(
+            tracks.rename_tracks(Path::new("/m/a.mp3"), Path::new("/m/c.mp3")),
+            1
+        );
+        assert_eq!(names(&tracks), vec!["/m/c.mp3", "/m/b.mp3"]);
+        assert_eq!(tracks.current_index, 0);
+    }
 }
@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::folder_change_model::renamed_path;
+
+/// Leaving a long file this close to its end counts as having finished it, its resume
+/// point is dropped.
+const FINISHED_MARGIN: Duration = Duration::from_secs(30);
+
+/// Leaving a long file this close to its start keeps no resume point.
+const STARTED_MARGIN: Duration = Duration::from_secs(10);
+
+/// `Bookmark` is a named position in an audio file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bookmark {
+    pub name: String,
+    /// Position from the start of the file.
+    pub position: Duration,
+}
+
+/// `BookmarkModel` holds the bookmarks of every file and the resume points of long files
+/// (lectures, podcasts, audiobooks): where playback was when the file was left, so it
+/// starts there the next time instead of at the beginning.
+#[derive(Clone, Debug, Default)]
+pub struct BookmarkModel {
+    /// Bookmarks by file, each list in position order.
+    bookmarks: BTreeMap<PathBuf, Vec<Bookmark>>,
+    /// Where each long file was left.
+    resume_points: BTreeMap<PathBuf, Duration>,
+    /// Files at least this long resume, `None` turns resuming off.
+    resume_after: Option<Duration>,
+}
+
+impl BookmarkModel {
+    /// Creates a model without bookmarks.
+    ///
+    /// # Arguments
+    /// * `resume_after` - Files at least this long resume from where they were left,
+    ///   `None` turns resuming off.
+    pub fn new(resume_after: Option<Duration>) -> Self {
+        BookmarkModel {
+            resume_after,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the length from which files resume, `None` if resuming is off.
+    pub fn resume_after(&self) -> Option<Duration> {
+        self.resume_after
+    }
+
+    /// Sets the length from which files resume, `None` turns resuming off. The resume
+    /// points are kept either way.
+    pub fn set_resume_after(&mut self, resume_after: Option<Duration>) {
+        self.resume_after = resume_after;
+    }
+
+    /// Adds a bookmark to `path`.
+    ///
+    /// # Arguments
+    /// * `path` - The audio file.
+    /// * `name` - The name shown in listings. Without one it is `Bookmark <n>`, numbered
+    ///   after the file's bookmarks and never a name the file already uses.
+    /// * `position` - The position from the start of the file.
+    ///
+    /// # Returns
+    /// * The added bookmark.
+    pub fn add(&mut self, path: &Path, name: Option<String>, position: Duration) -> Bookmark {
+        let bookmarks = self.bookmarks.entry(path.to_path_buf()).or_default();
+        let name = name.unwrap_or_else(|| {
+            // After a removal the count can be taken, names must stay unique.
+            (bookmarks.len() + 1..)
+                .map(|number| format!("Bookmark {}", number))
+                .find(|name| bookmarks.iter().all(|other| &other.name != name))
+                .unwrap_or_default()
+        });
+        let bookmark = Bookmark { name, position };
+        let index = bookmarks.partition_point(|other| other.position <= position);
+        bookmarks.insert(index, bookmark.clone());
+        bookmark
+    }
+
+    /// Removes a bookmark of `path`.
+    ///
+    /// # Returns
+    /// * `true` if the bookmark was found and removed.
+    pub fn remove(&mut self, path: &Path, bookmark: &Bookmark) -> bool {
+        let Some(bookmarks) = self.bookmarks.get_mut(path) else {
+            return false;
+        };
+        let Some(index) = bookmarks.iter().position(|other| other == bookmark) else {
+            return false;
+        };
+        bookmarks.remove(index);
+        if bookmarks.is_empty() {
+            self.bookmarks.remove(path);
+        }
+        true
+    }
+
+    /// Returns the bookmarks of `path` in position order.
+    pub fn bookmarks(&self, path: &Path) -> &[Bookmark] {
+        self.bookmarks.get(path).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns every bookmark with its file, by file and position.
+    pub fn all(&self) -> impl Iterator<Item = (&Path, &Bookmark)> {
+        self.bookmarks.iter().flat_map(|(path, bookmarks)| {
+            bookmarks
+                .iter()
+                .map(move |bookmark| (path.as_path(), bookmark))
+        })
+    }
+
+    /// Returns every resume point with its file.
+    pub fn resume_points(&self) -> impl Iterator<Item = (&Path, Duration)> {
+        (self.resume_points.iter()).map(|(path, position)| (path.as_path(), *position))
+    }
+
+    /// Sets where `path` resumes, for resume points read back from a file.
+    pub fn set_resume_point(&mut self, path: PathBuf, position: Duration) {
+        self.resume_points.insert(path, position);
+    }
+
+    /// Moves the bookmarks and resume points of a renamed file or folder to the new paths.
+    /// Those of a file that was replaced by the rename are dropped.
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        fn rename_keys<T>(map: &mut BTreeMap<PathBuf, T>, from: &Path, to: &Path) {
+            let moved: Vec<PathBuf> = (map.keys())
+                .filter(|path| path.starts_with(from))
+                .cloned()
+                .collect();
+            let renamed: Vec<(PathBuf, T)> = moved
+                .iter()
+                .filter_map(|path| Some((renamed_path(path, from, to)?, map.remove(path)?)))
+                .collect();
+            map.extend(renamed);
+        }
+        rename_keys(&mut self.bookmarks, from, to);
+        rename_keys(&mut self.resume_points, from, to);
+    }
+
+    /// **Resume Point of a File**
+    ///
+    /// # Arguments
+    /// * `path` - The audio file being loaded.
+    /// * `length` - Its length.
+    ///
+    /// # Returns
+    /// * `Some(Duration)` - Where to start, if the file is long enough to resume and was
+    ///   left before its end.
+    /// * `None` - Start at the beginning.
+    pub fn resume_point(&self, path: &Path, length: Duration) -> Option<Duration> {
+        let resume_after = self.resume_after?;
+        if length < resume_after {
+            return None;
+        }
+        self.resume_points.get(path).copied()
+    }
+
+    /// **Leave a File**
+    ///
+    /// Remembers where a long file was left. Files shorter than the resume length are not
+    /// remembered; leaving near the start or the end drops the resume point.
+    ///
+    /// # Arguments
+    /// * `path` - The audio file that stops playing.
+    /// * `position` - Where it stops.
+    /// * `length` - Its length.
+    pub fn leave(&mut self, path: &Path, position: Duration, length: Duration) {
+        match self.resume_after {
+            Some(resume_after) if length >= resume_after => {}
+            _ => return, // Not a long file, or resuming is off.
+        }
+        if position < STARTED_MARGIN || position + FINISHED_MARGIN >= length {
+            self.resume_points.remove(path);
+        } else {
+            self.resume_points.insert(path.to_path_buf(), position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn second(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn default_names_stay_unique_after_a_removal() {
+        let mut bookmarks = BookmarkModel::default();
+        let file = Path::new("/m/talk.mp3");
+        let first = bookmarks.add(file, None, second(10));
+        let second_mark = bookmarks.add(file, None, second(20));
+        bookmarks.add(file, None, second(30));
+        assert_eq!(second_mark.name, "Bookmark 2");
+        assert!(bookmarks.remove(file, &first));
+
+        let added = bookmarks.add(file, None, second(40));
+        assert_eq!(added.name, "Bookmark 4", "Bookmark 3 is still there");
+        let names: Vec<&str> = (bookmarks.bookmarks(file).iter())
+            .map(|mark| mark.name.as_str())
+            .collect();
+        assert_eq!(names, ["Bookmark 2", "Bookmark 3", "Bookmark 4"]);
+        assert_eq!(
+            bookmarks
+                .add(Path::new("/m/other.mp3"), None, second(1))
+                .name,
+            "Bookmark 1"
+        );
+    }
+
+    #[test]
+    fn only_long_files_are_left_with_a_resume_point() {
+        let mut bookmarks = BookmarkModel::new(Some(20 * MINUTE));
+        let file = Path::new("/m/book.mp3");
+        bookmarks.leave(file, 5 * MINUTE, 20 * MINUTE - second(1));
+        assert_eq!(bookmarks.resume_points().count(), 0, "just too short");
+        bookmarks.leave(file, 5 * MINUTE, 20 * MINUTE);
+        assert_eq!(bookmarks.resume_point(file, 20 * MINUTE), Some(5 * MINUTE));
+        assert_eq!(bookmarks.resume_point(file, 20 * MINUTE - second(1)), None);
+
+        bookmarks.set_resume_after(None);
+        assert_eq!(
+            bookmarks.resume_point(file, 20 * MINUTE),
+            None,
+            "resuming is off"
+        );
+        bookmarks.leave(file, 6 * MINUTE, 20 * MINUTE);
+        bookmarks.set_resume_after(Some(20 * MINUTE));
+        assert_eq!(
+            bookmarks.resume_point(file, 20 * MINUTE),
+            Some(5 * MINUTE),
+            "kept while resuming was off"
+        );
+    }
+
+    #[test]
+    fn leaving_near_the_start_or_the_end_drops_the_resume_point() {
+        let mut bookmarks = BookmarkModel::new(Some(20 * MINUTE));
+        let file = Path::new("/m/book.mp3");
+        let length = 60 * MINUTE;
+        let left_at = |bookmarks: &mut BookmarkModel, position| {
+            bookmarks.set_resume_point(file.to_path_buf(), 30 * MINUTE);
+            bookmarks.leave(file, position, length);
+            bookmarks.resume_point(file, length)
+        };
+        assert_eq!(left_at(&mut bookmarks, second(9)), None);
+        assert_eq!(left_at(&mut bookmarks, second(10)), Some(second(10)));
+        assert_eq!(
+            left_at(&mut bookmarks, length - second(31)),
+            Some(length - second(31))
+        );
+        assert_eq!(left_at(&mut bookmarks, length - second(30)), None);
+        assert_eq!(left_at(&mut bookmarks, length), None);
+    }
+
+    #[test]
+    fn renames_move_bookmarks_and_resume_points() {
+        let mut bookmarks = BookmarkModel::new(Some(Duration::from_secs(600)));
+        bookmarks.add(
+            Path::new("/m/old/a.mp3"),
+            Some("Intro".to_string()),
+            second(60),
+        );
+        bookmarks.add(Path::new("/m/b.mp3"), None, second(5));
+        bookmarks.set_resume_point("/m/old/a.mp3".into(), second(120));
+        bookmarks.set_resume_point("/m/b.mp3".into(), second(300));
+
+        bookmarks.rename(Path::new("/m/old"), Path::new("/m/new"));
+        assert!(bookmarks.bookmarks(Path::new("/m/old/a.mp3")).is_empty());
+        assert_eq!(
+            bookmarks.bookmarks(Path::new("/m/new/a.mp3"))[0].name,
+            "Intro"
+        );
+        let length = second(3600);
+        assert_eq!(
+            bookmarks.resume_point(Path::new("/m/new/a.mp3"), length),
+            Some(second(120))
+        );
+
+        // Moved onto another file, whose bookmarks and resume point go.
+        bookmarks.rename(Path::new("/m/new/a.mp3"), Path::new("/m/b.mp3"));
+        let moved: Vec<&str> = bookmarks
+            .all()
+            .map(|(_, mark)| mark.name.as_str())
+            .collect();
+        assert_eq!(moved, vec!["Intro"]);
+        assert_eq!(
+            bookmarks.resume_point(Path::new("/m/b.mp3"), length),
+            Some(second(120))
+        );
+        assert_eq!(bookmarks.resume_points().count(), 1);
+    }
+}
@@ -0,0 +1,450 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use super::audio_folder_model::natural_cmp;
+use super::library_model::{LibraryEntry, LibraryModel};
+use super::search_model::fold;
+
+/// Album artist of albums whose tracks have different artists and no album artist tag.
+pub const VARIOUS_ARTISTS: &str = "Various Artists";
+
+/// Names used for tracks without an artist, album or genre tag.
+const UNKNOWN_ARTIST: &str = "Unknown Artist";
+const UNKNOWN_ALBUM: &str = "Unknown Album";
+const UNKNOWN_GENRE: &str = "Unknown Genre";
+
+/// `BrowseNode` is one place in the library tree: a list at the top (artists, albums,
+/// genres, years), a group below it or a single track.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BrowseNode {
+    /// All album artists.
+    Artists,
+    /// All albums.
+    Albums,
+    /// All genres.
+    Genres,
+    /// All release years.
+    Years,
+    /// The albums of an album artist.
+    Artist(String),
+    /// The album artists of a genre.
+    Genre(String),
+    /// The albums released in a year.
+    Year(u32),
+    /// The tracks of an album, in disc and track order. Albums of the same name by the
+    /// same artist in different folders are different albums.
+    Album {
+        artist: String,
+        album: String,
+        folder: PathBuf,
+    },
+    /// One track.
+    Track(PathBuf),
+}
+
+impl BrowseNode {
+    /// Parses the name of a top-level list as typed in the CLI (`artists`, `albums`,
+    /// `genres`, `years`).
+    ///
+    /// # Returns
+    /// * `Some(BrowseNode)` if the name is known, otherwise `None`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "artists" | "artist" => Some(BrowseNode::Artists),
+            "albums" | "album" => Some(BrowseNode::Albums),
+            "genres" | "genre" => Some(BrowseNode::Genres),
+            "years" | "year" => Some(BrowseNode::Years),
+            _ => None,
+        }
+    }
+
+    /// Returns the name shown for the node (`Artist - Album` for albums).
+    pub fn name(&self) -> String {
+        match self {
+            BrowseNode::Artists => "Artists".to_string(),
+            BrowseNode::Albums => "Albums".to_string(),
+            BrowseNode::Genres => "Genres".to_string(),
+            BrowseNode::Years => "Years".to_string(),
+            BrowseNode::Artist(name) | BrowseNode::Genre(name) => name.clone(),
+            BrowseNode::Year(year) => year.to_string(),
+            BrowseNode::Album { artist, album, .. } => format!("{} - {}", artist, album),
+            BrowseNode::Track(path) => path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+        }
+    }
+}
+
+/// A library entry with the names it is grouped by.
+struct BrowseTrack<'a> {
+    entry: &'a LibraryEntry,
+    /// The album artist tag, `Various Artists` for compilations, or else the artist.
+    artist: String,
+    album: String,
+    genre: String,
+    /// The folder of the album, see `album_folder`.
+    folder: PathBuf,
+    /// The names folded (see `fold`), compared when grouping and sorting.
+    keys: [String; 3],
+}
+
+/// What the children of a node are grouped by: folded names and the album folder.
+type GroupKey<'t> = (String, String, &'t Path);
+
+/// `BrowseModel` groups the library by its tags for browsing: artists -> albums ->
+/// tracks, genres -> artists, years -> albums.
+///
+/// Albums belong to their album artist. Albums without one whose tracks in a folder have
+/// different artists are compilations and belong to `Various Artists`. Names are grouped
+/// ignoring case and accents.
+pub struct BrowseModel<'a> {
+    tracks: Vec<BrowseTrack<'a>>,
+}
+
+impl<'a> BrowseModel<'a> {
+    /// Groups the entries of `library`.
+    ///
+    /// # Arguments
+    /// * `library` - The music library, borrowed while browsing.
+    pub fn new(library: &'a LibraryModel) -> Self {
+        // Track artists of the albums without an album artist, by album and folder.
+        let album_key = |entry: &LibraryEntry| {
+            let album = entry.metadata.album.as_deref().map(fold);
+            album.map(|album| (album, album_folder(&entry.path)))
+        };
+        let mut album_artists: HashMap<_, HashSet<String>> = HashMap::new();
+        for entry in library.entries() {
+            if entry.metadata.album_artist.is_none() {
+                if let (Some(key), Some(artist)) = (album_key(entry), &entry.metadata.artist) {
+                    album_artists.entry(key).or_default().insert(fold(artist));
+                }
+            }
+        }
+
+        let tracks = library
+            .entries()
+            .map(|entry| {
+                let metadata = &entry.metadata;
+                let compilation = || {
+                    album_key(entry)
+                        .and_then(|key| album_artists.get(&key))
+                        .is_some_and(|artists| artists.len() > 1)
+                };
+                let artist = match (&metadata.album_artist, &metadata.artist) {
+                    (Some(album_artist), _) => album_artist.clone(),
+                    (None, _) if compilation() => VARIOUS_ARTISTS.to_string(),
+                    (None, Some(artist)) => artist.clone(),
+                    (None, None) => UNKNOWN_ARTIST.to_string(),
+                };
+                let album = (metadata.album.clone()).unwrap_or_else(|| UNKNOWN_ALBUM.to_string());
+                let genre = (metadata.genre.clone()).unwrap_or_else(|| UNKNOWN_GENRE.to_string());
+                BrowseTrack {
+                    entry,
+                    folder: album_folder(&entry.path),
+                    keys: [fold(&artist), fold(&album), fold(&genre)],
+                    artist,
+                    album,
+                    genre,
+                }
+            })
+            .collect();
+        BrowseModel { tracks }
+    }
+
+    /// **List the Children of a Node**
+    ///
+    /// # Arguments
+    /// * `node` - The node to open.
+    ///
+    /// # Returns
+    /// * The nodes one level below, each with its number of tracks: artists by name,
+    ///   albums by artist, year and title, years in order, tracks in play order.
+    pub fn children(&self, node: &BrowseNode) -> Vec<(BrowseNode, usize)> {
+        let tracks = self.tracks_of(node);
+        match node {
+            BrowseNode::Track(_) => return Vec::new(),
+            BrowseNode::Album { .. } => {
+                return tracks
+                    .iter()
+                    .map(|track| (BrowseNode::Track(track.entry.path.clone()), 1))
+                    .collect()
+            }
+            _ => {}
+        }
+
+        // The first spelling of a name is shown, the folded one groups.
+        let mut groups: BTreeMap<GroupKey, (BrowseNode, Option<u32>, usize)> = BTreeMap::new();
+        let none = Path::new("");
+        for track in tracks {
+            let (key, child) = match node {
+                BrowseNode::Artists | BrowseNode::Genre(_) => (
+                    (track.keys[0].clone(), String::new(), none),
+                    BrowseNode::Artist(track.artist.clone()),
+                ),
+                BrowseNode::Genres => (
+                    (track.keys[2].clone(), String::new(), none),
+                    BrowseNode::Genre(track.genre.clone()),
+                ),
+                BrowseNode::Years => match track.entry.metadata.year {
+                    Some(year) => (
+                        (format!("{:010}", year), String::new(), none),
+                        BrowseNode::Year(year),
+                    ),
+                    None => continue,
+                },
+                _ => (
+                    (
+                        track.keys[0].clone(),
+                        track.keys[1].clone(),
+                        track.folder.as_path(),
+                    ),
+                    BrowseNode::Album {
+                        artist: track.artist.clone(),
+                        album: track.album.clone(),
+                        folder: track.folder.clone(),
+                    },
+                ),
+            };
+            let group = groups.entry(key).or_insert((child, None, 0));
+            group.1 = match (group.1, track.entry.metadata.year) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            group.2 += 1;
+        }
+
+        let mut children: Vec<(BrowseNode, Option<u32>, usize)> = groups.into_values().collect();
+        children.sort_by(|(a, a_year, _), (b, b_year, _)| match (a, b) {
+            (
+                BrowseNode::Album {
+                    artist: a_artist,
+                    album: a_album,
+                    folder: a_folder,
+                },
+                BrowseNode::Album {
+                    artist: b_artist,
+                    album: b_album,
+                    folder: b_folder,
+                },
+            ) => natural_cmp(a_artist, b_artist)
+                .then(Self::known_first(*a_year, *b_year))
+                .then_with(|| natural_cmp(a_album, b_album))
+                .then_with(|| {
+                    natural_cmp(&a_folder.to_string_lossy(), &b_folder.to_string_lossy())
+                }),
+            (BrowseNode::Year(a), BrowseNode::Year(b)) => a.cmp(b),
+            _ => natural_cmp(&a.name(), &b.name()),
+        });
+        children
+            .into_iter()
+            .map(|(child, _, count)| (child, count))
+            .collect()
+    }
+
+    /// **Tracks of a Node**
+    ///
+    /// # Returns
+    /// * The library entries below `node`, ordered by album artist, album (by the year of
+    ///   its oldest track, then by title), disc (number or folder), track number and path,
+    ///   the order an album or a discography is played in.
+    pub fn tracks(&self, node: &BrowseNode) -> Vec<&'a LibraryEntry> {
+        self.tracks_of(node)
+            .into_iter()
+            .map(|track| track.entry)
+            .collect()
+    }
+
+    /// Returns the tracks below `node` in play order.
+    fn tracks_of(&self, node: &BrowseNode) -> Vec<&BrowseTrack<'a>> {
+        let same = |key: &str, name: &str| key == fold(name);
+        let mut tracks: Vec<&BrowseTrack<'a>> = self
+            .tracks
+            .iter()
+            .filter(|track| match node {
+                BrowseNode::Artists | BrowseNode::Albums | BrowseNode::Genres => true,
+                BrowseNode::Years => track.entry.metadata.year.is_some(),
+                BrowseNode::Artist(artist) => same(&track.keys[0], artist),
+                BrowseNode::Genre(genre) => same(&track.keys[2], genre),
+                BrowseNode::Year(year) => track.entry.metadata.year == Some(*year),
+                BrowseNode::Album {
+                    artist,
+                    album,
+                    folder,
+                } => {
+                    same(&track.keys[0], artist)
+                        && same(&track.keys[1], album)
+                        && &track.folder == folder
+                }
+                BrowseNode::Track(path) => &track.entry.path == path,
+            })
+            .collect();
+
+        // Albums are ordered by their oldest track, so a track with another year (a bonus
+        // track, a remaster) stays in its album.
+        let mut years: HashMap<(&str, &str, &Path), u32> = HashMap::new();
+        for track in &tracks {
+            if let Some(year) = track.entry.metadata.year {
+                let key = (
+                    track.keys[0].as_str(),
+                    track.keys[1].as_str(),
+                    track.folder.as_path(),
+                );
+                years
+                    .entry(key)
+                    .and_modify(|oldest| *oldest = (*oldest).min(year))
+                    .or_insert(year);
+            }
+        }
+        let year_of = |track: &BrowseTrack| {
+            let key = (
+                track.keys[0].as_str(),
+                track.keys[1].as_str(),
+                track.folder.as_path(),
+            );
+            years.get(&key).copied()
+        };
+        tracks.sort_by(|a, b| {
+            let (a_meta, b_meta) = (&a.entry.metadata, &b.entry.metadata);
+            natural_cmp(&a.keys[0], &b.keys[0])
+                .then(Self::known_first(year_of(a), year_of(b)))
+                .then_with(|| natural_cmp(&a.keys[1], &b.keys[1]))
+                .then_with(|| natural_cmp(&a.folder.to_string_lossy(), &b.folder.to_string_lossy()))
+                .then(Self::known_first(a_meta.disc_number, b_meta.disc_number))
+                // Disc folders without disc numbers.
+                .then_with(|| {
+                    let (a_folder, b_folder) = (a.entry.path.parent(), b.entry.path.parent());
+                    natural_cmp(
+                        &a_folder.unwrap_or(Path::new("")).to_string_lossy(),
+                        &b_folder.unwrap_or(Path::new("")).to_string_lossy(),
+                    )
+                })
+                .then(Self::known_first(a_meta.track_number, b_meta.track_number))
+                .then_with(|| {
+                    natural_cmp(
+                        &a.entry.path.to_string_lossy(),
+                        &b.entry.path.to_string_lossy(),
+                    )
+                })
+        });
+        tracks
+    }
+
+    /// Compares two optional numbers, `None` sorting after known ones.
+    fn known_first(a: Option<u32>, b: Option<u32>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+/// Returns the folder of the album of the track at `path`: the folder of the file, or the
+/// one above it when the file is in a disc folder (`CD1`, `Disc 2`, ...) so the discs of
+/// an album stay together.
+fn album_folder(path: &Path) -> PathBuf {
+    let folder = path.parent().unwrap_or(Path::new(""));
+    let name = folder
+        .file_name()
+        .map(|name| fold(&name.to_string_lossy()))
+        .unwrap_or_default();
+    let disc = ["cd", "disc", "disk"].iter().any(|prefix| {
+        name.strip_prefix(prefix).is_some_and(|number| {
+            let number = number.trim_start_matches([' ', '_', '-', '.']);
+            !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+        })
+    });
+    match folder.parent() {
+        Some(parent) if disc => parent.to_path_buf(),
+        _ => folder.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::models::track_metadata_model::TrackMetadata;
+
+    fn library(tracks: &[(&str, &str, Option<u32>, u32)]) -> LibraryModel {
+        let mut library = LibraryModel::default();
+        for &(path, album, year, track_number) in tracks {
+            library.insert(LibraryEntry {
+                path: PathBuf::from(path),
+                size: 0,
+                format: "mp3".to_string(),
+                metadata: TrackMetadata {
+                    artist: Some("Artist".to_string()),
+                    album: Some(album.to_string()),
+                    year,
+                    track_number: Some(track_number),
+                    ..TrackMetadata::default()
+                },
+                favourite: false,
+            });
+        }
+        library
+    }
+
+    fn paths(entries: Vec<&LibraryEntry>) -> Vec<&str> {
+        entries
+            .iter()
+            .map(|entry| entry.path.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn tracks_with_another_year_stay_in_their_album() {
+        let library = library(&[
+            ("/m/b/1.mp3", "Beta", Some(2000), 1),
+            ("/m/a/1.mp3", "Alpha", Some(1990), 1),
+            ("/m/a/2.mp3", "Alpha", Some(2005), 2),
+            ("/m/a/3.mp3", "Alpha", Some(1990), 3),
+        ]);
+        let browse = BrowseModel::new(&library);
+        let artist = BrowseNode::Artist("Artist".to_string());
+        assert_eq!(
+            paths(browse.tracks(&artist)),
+            vec!["/m/a/1.mp3", "/m/a/2.mp3", "/m/a/3.mp3", "/m/b/1.mp3"]
+        );
+        let albums: Vec<String> = (browse.children(&artist).iter())
+            .map(|(node, _)| node.name())
+            .collect();
+        assert_eq!(albums, vec!["Artist - Alpha", "Artist - Beta"]);
+    }
+
+    #[test]
+    fn albums_are_told_apart_by_folder() {
+        let library = library(&[
+            ("/m/live/1.mp3", "Hits", Some(1999), 1),
+            ("/m/studio/1.mp3", "Hits", Some(1995), 1),
+            ("/m/box/CD2/1.mp3", "Box", None, 1),
+            ("/m/box/CD1/1.mp3", "Box", None, 1),
+            ("/m/box/CD1/2.mp3", "Box", None, 2),
+        ]);
+        let browse = BrowseModel::new(&library);
+        let albums = browse.children(&BrowseNode::Artist("artist".to_string()));
+        let folders: Vec<(&Path, usize)> = albums
+            .iter()
+            .map(|(node, count)| match node {
+                BrowseNode::Album { folder, .. } => (folder.as_path(), *count),
+                node => panic!("Not an album: {:?}", node),
+            })
+            .collect();
+        assert_eq!(
+            folders,
+            vec![
+                (Path::new("/m/studio"), 1),
+                (Path::new("/m/live"), 1),
+                (Path::new("/m/box"), 3),
+            ]
+        );
+        assert_eq!(
+            paths(browse.tracks(&albums[2].0)),
+            vec!["/m/box/CD1/1.mp3", "/m/box/CD1/2.mp3", "/m/box/CD2/1.mp3"]
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use std::f32::consts::FRAC_PI_2;
+use std::path::Path;
+use std::time::Duration;
+
+use super::track_metadata_model::TrackMetadata;
+
+/// The longest crossfade the player accepts. Longer values are clamped to this.
+pub const MAX_CROSSFADE: Duration = Duration::from_secs(12);
+
+/// `FadeCurve` describes the shape of a volume ramp between two tracks
+/// (or between silence and a track).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FadeCurve {
+    /// Gain changes at a constant rate.
+    Linear,
+    /// Sine/cosine pair, keeps the perceived loudness constant while two tracks overlap.
+    EqualPower,
+    /// Gain follows a decibel scale (60 dB range), which sounds even to the ear.
+    Logarithmic,
+}
+
+impl FadeCurve {
+    /// Returns the gain of a fade-in that is `progress` (0.0 - 1.0) of the way through.
+    ///
+    /// A fade-out uses the same curve mirrored in time, so `gain(1.0 - progress)`.
+    pub fn gain(&self, progress: f32) -> f32 {
+        let progress = progress.clamp(0.0, 1.0);
+        match self {
+            FadeCurve::Linear => progress,
+            FadeCurve::EqualPower => (progress * FRAC_PI_2).sin(),
+            FadeCurve::Logarithmic => {
+                if progress <= 0.0 {
+                    0.0 // -60 dB is close enough to silence, but snap to a real zero at the start.
+                } else {
+                    10f32.powf(3.0 * (progress - 1.0))
+                }
+            }
+        }
+    }
+
+    /// Returns the name of the curve, as accepted by `from_name`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FadeCurve::Linear => "linear",
+            FadeCurve::EqualPower => "equal",
+            FadeCurve::Logarithmic => "log",
+        }
+    }
+
+    /// Parses a curve name as typed in the CLI (`linear`, `equal`, `log`, ...).
+    ///
+    /// # Returns
+    /// * `Some(FadeCurve)` if the name is known, otherwise `None`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "linear" | "lin" => Some(FadeCurve::Linear),
+            "equal" | "equal-power" | "equalpower" | "power" => Some(FadeCurve::EqualPower),
+            "log" | "logarithmic" => Some(FadeCurve::Logarithmic),
+            _ => None,
+        }
+    }
+}
+
+/// `CrossfadeModel` holds the crossfade settings used by the `PlayerController`
+/// when switching from one track to the next.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CrossfadeModel {
+    /// How long the outgoing and incoming tracks overlap. Zero disables crossfading.
+    duration: Duration,
+    /// The shape of the volume ramps.
+    curve: FadeCurve,
+}
+
+impl CrossfadeModel {
+    /// Creates a new `CrossfadeModel`.
+    ///
+    /// # Arguments
+    /// * `duration` - The overlap between tracks, clamped to `MAX_CROSSFADE`.
+    /// * `curve` - The shape of the volume ramps.
+    pub fn new(duration: Duration, curve: FadeCurve) -> Self {
+        Self {
+            duration: duration.min(MAX_CROSSFADE),
+            curve,
+        }
+    }
+
+    /// Creates a model with crossfading switched off (tracks cut as before).
+    pub fn disabled() -> Self {
+        Self::new(Duration::ZERO, FadeCurve::EqualPower)
+    }
+
+    /// Returns `true` if a non-zero crossfade duration is configured.
+    pub fn is_enabled(&self) -> bool {
+        !self.duration.is_zero()
+    }
+
+    /// Returns the configured overlap between tracks.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Returns the configured fade curve.
+    pub fn curve(&self) -> FadeCurve {
+        self.curve
+    }
+
+    /// Decides whether the change from `from` to `to` should be crossfaded.
+    ///
+    /// Consecutive tracks of the same album are meant to flow into each other (live
+    /// albums, gapless mixes), so the crossfade is skipped for them, see `same_album`.
+    ///
+    /// # Arguments
+    /// * `from`, `from_tags` - The outgoing track and its metadata.
+    /// * `to`, `to_tags` - The incoming track and its metadata.
+    pub fn applies_between(
+        &self,
+        from: &Path,
+        from_tags: &TrackMetadata,
+        to: &Path,
+        to_tags: &TrackMetadata,
+    ) -> bool {
+        self.is_enabled() && from != to && !Self::same_album(from, from_tags, to, to_tags)
+    }
+
+    /// Returns `true` if two tracks belong to the same album: equal album titles and album
+    /// artists in the tags. Only when either track has no album tag are tracks in the same
+    /// folder treated as one album.
+    fn same_album(
+        from: &Path,
+        from_tags: &TrackMetadata,
+        to: &Path,
+        to_tags: &TrackMetadata,
+    ) -> bool {
+        let album_artist = |tags: &TrackMetadata| {
+            tags.album_artist
+                .as_ref()
+                .map(|artist| artist.to_lowercase())
+        };
+        match (&from_tags.album, &to_tags.album) {
+            (Some(from_album), Some(to_album)) => {
+                from_album.to_lowercase() == to_album.to_lowercase()
+                    && album_artist(from_tags) == album_artist(to_tags)
+            }
+            _ => from.parent() == to.parent(),
+        }
+    }
+}
+
+impl Default for CrossfadeModel {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [FadeCurve; 3] = [
+        FadeCurve::Linear,
+        FadeCurve::EqualPower,
+        FadeCurve::Logarithmic,
+    ];
+
+    fn tags(album: Option<&str>, album_artist: Option<&str>) -> TrackMetadata {
+        TrackMetadata {
+            album: album.map(String::from),
+            album_artist: album_artist.map(String::from),
+            ..TrackMetadata::default()
+        }
+    }
+
+    fn crossfades(
+        from: &str,
+        from_tags: &TrackMetadata,
+        to: &str,
+        to_tags: &TrackMetadata,
+    ) -> bool {
+        CrossfadeModel::new(Duration::from_secs(2), FadeCurve::EqualPower).applies_between(
+            Path::new(from),
+            from_tags,
+            Path::new(to),
+            to_tags,
+        )
+    }
+
+    #[test]
+    fn curves_run_from_silence_to_full_gain() {
+        for curve in CURVES {
+            assert_eq!(curve.gain(0.0), 0.0, "{:?} starts silent", curve);
+            assert!(
+                (curve.gain(1.0) - 1.0).abs() < 1e-6,
+                "{:?} ends at full gain",
+                curve
+            );
+            assert_eq!(curve.gain(-0.5), 0.0, "{:?} clamps below the start", curve);
+            assert!(
+                (curve.gain(1.5) - 1.0).abs() < 1e-6,
+                "{:?} clamps past the end",
+                curve
+            );
+        }
+        assert!(
+            (FadeCurve::Logarithmic.gain(2.0 / 3.0) - 0.1).abs() < 1e-6,
+            "-20 dB"
+        );
+    }
+
+    #[test]
+    fn equal_power_keeps_the_summed_power_constant() {
+        for step in 0..=10 {
+            let progress = step as f32 / 10.0;
+            let fade_in = FadeCurve::EqualPower.gain(progress);
+            let fade_out = FadeCurve::EqualPower.gain(1.0 - progress);
+            assert!((fade_in.powi(2) + fade_out.powi(2) - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn curves_are_parsed_by_name() {
+        assert_eq!(FadeCurve::from_name("LIN"), Some(FadeCurve::Linear));
+        assert_eq!(
+            FadeCurve::from_name("equal-power"),
+            Some(FadeCurve::EqualPower)
+        );
+        assert_eq!(FadeCurve::from_name("power"), Some(FadeCurve::EqualPower));
+        assert_eq!(
+            FadeCurve::from_name("Logarithmic"),
+            Some(FadeCurve::Logarithmic)
+        );
+        assert_eq!(FadeCurve::from_name("cubic"), None);
+        for curve in CURVES {
+            assert_eq!(FadeCurve::from_name(curve.name()), Some(curve));
+        }
+        assert_eq!(FadeCurve::from_name(""), None);
+    }
+
+    #[test]
+    fn duration_is_clamped_and_zero_disables() {
+        let model = CrossfadeModel::new(Duration::from_secs(60), FadeCurve::Linear);
+        assert_eq!(model.duration(), MAX_CROSSFADE);
+        assert!(!CrossfadeModel::disabled().is_enabled());
+        let untagged = TrackMetadata::default();
+        assert!(!CrossfadeModel::disabled().applies_between(
+            Path::new("a/one.wav"),
+            &untagged,
+            Path::new("b/two.wav"),
+            &untagged,
+        ));
+    }
+
+    #[test]
+    fn same_album_tags_skip_the_crossfade() {
+        let live = tags(Some("Live"), Some("Band"));
+        assert!(!crossfades(
+            "a/one.wav",
+            &live,
+            "b/two.wav",
+            &tags(Some("LIVE"), Some("band"))
+        ));
+        assert!(crossfades(
+            "a/one.wav",
+            &live,
+            "a/two.wav",
+            &tags(Some("Studio"), Some("Band"))
+        ));
+        assert!(crossfades(
+            "a/one.wav",
+            &live,
+            "a/two.wav",
+            &tags(Some("Live"), Some("Other"))
+        ));
+        assert!(crossfades(
+            "a/one.wav",
+            &live,
+            "a/two.wav",
+            &tags(Some("Live"), None)
+        ));
+        assert!(
+            !crossfades("a/one.wav", &live, "a/one.wav", &live),
+            "not into itself"
+        );
+    }
+
+    #[test]
+    fn untagged_tracks_fall_back_to_the_folder() {
+        let untagged = TrackMetadata::default();
+        let tagged = tags(Some("Live"), None);
+        assert!(!crossfades("a/one.wav", &untagged, "a/two.wav", &untagged));
+        assert!(crossfades("a/one.wav", &untagged, "b/two.wav", &untagged));
+        assert!(!crossfades("a/one.wav", &tagged, "a/two.wav", &untagged));
+        assert!(crossfades("a/one.wav", &tagged, "b/two.wav", &untagged));
+    }
+}
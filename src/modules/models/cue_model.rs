@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// `CueTrack` is one track of a CUE sheet: a section of a larger audio file that is
+/// played as a track of its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CueTrack {
+    /// Track number (`TRACK 03`).
+    pub number: u32,
+    /// Track title (`TITLE`).
+    pub title: Option<String>,
+    /// Track performer (`PERFORMER`), falling back to the performer of the whole sheet.
+    pub performer: Option<String>,
+    /// Where the track starts in the audio file (`INDEX 01`).
+    pub start: Duration,
+    /// Where the next track of the same file starts, `None` for the last track (end of file).
+    pub end: Option<Duration>,
+}
+
+/// `CueFile` is one `FILE` of a CUE sheet with the tracks it is split into.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CueFile {
+    /// The audio file, resolved against the folder of the CUE sheet.
+    pub path: PathBuf,
+    /// The tracks, in the order of the sheet.
+    pub tracks: Vec<CueTrack>,
+}
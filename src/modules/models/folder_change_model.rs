@@ -0,0 +1,37 @@
+use std::path::{Path, PathBuf};
+
+/// `FolderChange` is a change below a watched folder, reported by `WatchService` once the
+/// path has been quiet for a moment (so files still being copied are not read half-way).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FolderChange {
+    /// A file or folder was created or written.
+    Changed(PathBuf),
+    /// A file or folder was deleted or moved out of the watched folders.
+    Removed(PathBuf),
+    /// A file or folder was renamed or moved within the watched folders.
+    Renamed {
+        /// The old path.
+        from: PathBuf,
+        /// The new path.
+        to: PathBuf,
+    },
+}
+
+/// **Path after a Rename**
+///
+/// # Arguments
+/// * `path` - A track path.
+/// * `from` - The renamed file or folder.
+/// * `to` - Its new path.
+///
+/// # Returns
+/// * `Some(PathBuf)` - The new path of `path` if it is `from` or lies below it.
+/// * `None` - If the rename does not affect `path`.
+pub fn renamed_path(path: &Path, from: &Path, to: &Path) -> Option<PathBuf> {
+    let rest = path.strip_prefix(from).ok()?;
+    if rest.as_os_str().is_empty() {
+        Some(to.to_path_buf())
+    } else {
+        Some(to.join(rest))
+    }
+}
@@ -0,0 +1,160 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use super::folder_change_model::renamed_path;
+
+/// Listening to this much of a track counts as playing it, also when it is left early.
+const PLAY_FRACTION: f64 = 0.5;
+
+/// Listening this long counts as playing a track whatever its length.
+const PLAY_MINIMUM: Duration = Duration::from_secs(4 * 60);
+
+/// `PlayRecord` is one playback of a track, from the moment it started playing until it
+/// was left.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlayRecord {
+    /// The audio file.
+    pub path: PathBuf,
+    /// When playback of the track started.
+    pub started: SystemTime,
+    /// How long the track was actually playing (pauses not counted).
+    pub listened: Duration,
+    /// `true` if the track was left before it counted as played, see `is_play`.
+    pub skipped: bool,
+}
+
+/// **Does a Playback Count as a Play**
+///
+/// # Arguments
+/// * `listened` - How long the track was actually playing.
+/// * `length` - The length of the track, `None` if it is not known.
+///
+/// # Returns
+/// * `true` if at least half of the track or 4 minutes were listened to, so leaving a
+///   track shortly before its end still counts as playing it.
+pub fn is_play(listened: Duration, length: Option<Duration>) -> bool {
+    listened >= PLAY_MINIMUM
+        || length.is_some_and(|length| listened >= length.mul_f64(PLAY_FRACTION))
+}
+
+/// `TrackStats` sums up the playbacks of one track.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TrackStats {
+    /// Playbacks that counted as playing the track.
+    pub plays: usize,
+    /// Playbacks left before they counted as a play.
+    pub skips: usize,
+    /// When the track last started a playback that counted as a play, skips are not
+    /// counted.
+    pub last_played: Option<SystemTime>,
+    /// Time spent listening to the track, over all playbacks.
+    pub listened: Duration,
+}
+
+/// `HistoryModel` is the play history, oldest playback first, with play counts, skip
+/// counts and last played times per track.
+#[derive(Clone, Debug, Default)]
+pub struct HistoryModel {
+    records: Vec<PlayRecord>,
+}
+
+impl HistoryModel {
+    /// Adds a playback. Records are kept in the order they are added.
+    pub fn record(&mut self, record: PlayRecord) {
+        self.records.push(record);
+    }
+
+    /// Returns the number of playbacks.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns `true` if nothing was played yet.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Points the playbacks of a renamed file or folder to the new paths.
+    ///
+    /// # Returns
+    /// * The number of playbacks changed.
+    pub fn rename(&mut self, from: &Path, to: &Path) -> usize {
+        let mut renamed = 0;
+        for record in self.records.iter_mut() {
+            if let Some(path) = renamed_path(&record.path, from, to) {
+                record.path = path;
+                renamed += 1;
+            }
+        }
+        renamed
+    }
+
+    /// Returns the playbacks, newest first.
+    pub fn recent(&self) -> impl DoubleEndedIterator<Item = &PlayRecord> {
+        self.records.iter().rev()
+    }
+
+    /// Returns the `TrackStats` of every played track.
+    pub fn stats(&self) -> HashMap<&Path, TrackStats> {
+        let mut stats: HashMap<&Path, TrackStats> = HashMap::new();
+        for record in &self.records {
+            let track = stats.entry(&record.path).or_default();
+            if record.skipped {
+                track.skips += 1;
+            } else {
+                track.plays += 1;
+                track.last_played = track.last_played.max(Some(record.started));
+            }
+            track.listened += record.listened;
+        }
+        stats
+    }
+
+    /// **Most Played Tracks**
+    ///
+    /// # Returns
+    /// * Every played track with its `TrackStats`, by play count, then by the time it was
+    ///   last played (most recent first).
+    pub fn top(&self) -> Vec<(&Path, TrackStats)> {
+        let mut top: Vec<(&Path, TrackStats)> = self.stats().into_iter().collect();
+        top.sort_by_key(|(path, stats)| (Reverse(stats.plays), Reverse(stats.last_played), *path));
+        top
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn half_the_track_or_four_minutes_is_a_play() {
+        let seconds = Duration::from_secs;
+        assert!(is_play(seconds(100), Some(seconds(200))));
+        assert!(!is_play(seconds(99), Some(seconds(200))));
+        assert!(is_play(seconds(240), Some(seconds(3600))));
+        assert!(!is_play(seconds(239), Some(seconds(3600))));
+        assert!(!is_play(seconds(200), None));
+        assert!(is_play(seconds(240), None));
+    }
+
+    #[test]
+    fn skips_do_not_count_as_last_played() {
+        let mut history = HistoryModel::default();
+        let at = |secs: u64| UNIX_EPOCH + Duration::from_secs(secs);
+        for (started, skipped) in [(100, false), (200, true), (50, false)] {
+            history.record(PlayRecord {
+                path: PathBuf::from("/m/a.mp3"),
+                started: at(started),
+                listened: Duration::from_secs(10),
+                skipped,
+            });
+        }
+        let stats = history.stats()[Path::new("/m/a.mp3")];
+        assert_eq!((stats.plays, stats.skips), (2, 1));
+        assert_eq!(stats.last_played, Some(at(100)));
+        assert_eq!(stats.listened, Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::audio_folder_model::natural_cmp;
+use super::folder_change_model::renamed_path;
+use super::search_model::SearchQuery;
+use super::track_metadata_model::TrackMetadata;
+
+/// `LibraryEntry` is one audio file known to the music library.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LibraryEntry {
+    /// Absolute path of the file.
+    pub path: PathBuf,
+    /// File size in bytes, compared with `metadata.modified` to detect changed files.
+    pub size: u64,
+    /// File format, the lowercase extension (`mp3`, `wav`, ...).
+    pub format: String,
+    /// Tags, duration and modification time, as read when the file was scanned. The
+    /// rating is kept when the file is read again and has none in its tags.
+    pub metadata: TrackMetadata,
+    /// Starred as a favourite, only kept in the library.
+    pub favourite: bool,
+}
+
+/// `ScanSummary` counts what a library rescan did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScanSummary {
+    /// New files, read for the first time.
+    pub added: usize,
+    /// Files whose size or modification time changed, read again.
+    pub updated: usize,
+    /// Files that are gone (or no longer match the scan options).
+    pub removed: usize,
+    /// Files taken from the library as they were.
+    pub unchanged: usize,
+}
+
+/// `FileUpdate` is what bringing the library entry of one file up to date did.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileUpdate {
+    /// The file is new, its entry was added.
+    Added,
+    /// The size or modification time changed, the file was read again.
+    Updated,
+    /// The entry was up to date.
+    Unchanged,
+    /// The file does not exist (anymore).
+    Missing,
+}
+
+/// `LibraryModel` is the music library: every scanned audio file with its metadata,
+/// kept on disk between runs (see `LibraryService`).
+#[derive(Clone, Debug, Default)]
+pub struct LibraryModel {
+    /// The entries by path.
+    entries: BTreeMap<PathBuf, LibraryEntry>,
+}
+
+impl LibraryModel {
+    /// Returns the number of files in the library.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the library has no files.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the entry of `path`, if it is in the library.
+    pub fn get(&self, path: &Path) -> Option<&LibraryEntry> {
+        self.entries.get(path)
+    }
+
+    /// Adds an entry, replacing the one with the same path.
+    pub fn insert(&mut self, entry: LibraryEntry) {
+        self.entries.insert(entry.path.clone(), entry);
+    }
+
+    /// Removes the entry of `path`, returning it if there was one.
+    pub fn remove(&mut self, path: &Path) -> Option<LibraryEntry> {
+        self.entries.remove(path)
+    }
+
+    /// Moves the entries of a renamed file or folder to their new paths.
+    ///
+    /// # Returns
+    /// * The number of entries moved.
+    pub fn rename(&mut self, from: &Path, to: &Path) -> usize {
+        let moved: Vec<PathBuf> = self
+            .entries
+            .keys()
+            .filter(|path| path.starts_with(from))
+            .cloned()
+            .collect();
+        for path in &moved {
+            if let (Some(mut entry), Some(new_path)) =
+                (self.entries.remove(path), renamed_path(path, from, to))
+            {
+                entry.path = new_path;
+                self.insert(entry);
+            }
+        }
+        moved.len()
+    }
+
+    /// Sets the rating of `path` to `rating` stars (1 to 5), `None` removes it.
+    ///
+    /// # Returns
+    /// * The updated entry, `None` if `path` is not in the library.
+    pub fn set_rating(&mut self, path: &Path, rating: Option<u8>) -> Option<&LibraryEntry> {
+        let entry = self.entries.get_mut(path)?;
+        entry.metadata.rating = rating;
+        Some(entry)
+    }
+
+    /// Stars `path` as a favourite, or unstars it.
+    ///
+    /// # Returns
+    /// * `false` if `path` is not in the library.
+    pub fn set_favourite(&mut self, path: &Path, favourite: bool) -> bool {
+        match self.entries.get_mut(path) {
+            Some(entry) => {
+                entry.favourite = favourite;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the favourites in natural path order.
+    pub fn favourites(&self) -> Vec<&LibraryEntry> {
+        self.query(|entry| entry.favourite)
+    }
+
+    /// Returns all entries, ordered by path.
+    pub fn entries(&self) -> impl Iterator<Item = &LibraryEntry> {
+        self.entries.values()
+    }
+
+    /// **Query the Library**
+    ///
+    /// # Arguments
+    /// * `predicate` - Selects the entries to return.
+    ///
+    /// # Returns
+    /// * The matching entries in natural path order (the order of `sort name`).
+    pub fn query(&self, predicate: impl Fn(&LibraryEntry) -> bool) -> Vec<&LibraryEntry> {
+        let mut entries: Vec<&LibraryEntry> = self
+            .entries
+            .values()
+            .filter(|entry| predicate(entry))
+            .collect();
+        entries.sort_by(|a, b| natural_cmp(&a.path.to_string_lossy(), &b.path.to_string_lossy()));
+        entries
+    }
+
+    /// **Search the Library**
+    ///
+    /// # Arguments
+    /// * `query` - A parsed search (words and field filters).
+    ///
+    /// # Returns
+    /// * The entries matching every part of the query, in natural path order.
+    pub fn search(&self, query: &SearchQuery) -> Vec<&LibraryEntry> {
+        self.query(|entry| query.matches(entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(paths: &[&str]) -> LibraryModel {
+        let mut library = LibraryModel::default();
+        for path in paths {
+            library.insert(LibraryEntry {
+                path: PathBuf::from(path),
+                size: 1,
+                format: String::from("mp3"),
+                metadata: TrackMetadata::default(),
+                favourite: path.contains("fav"),
+            });
+        }
+        library
+    }
+
+    fn paths(entries: Vec<&LibraryEntry>) -> Vec<&str> {
+        (entries.iter())
+            .map(|entry| entry.path.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn queries_return_natural_path_order() {
+        let library = library(&["/m/10 fav.mp3", "/m/2.mp3", "/m/1 fav.mp3", "/m/B/3.mp3"]);
+        assert_eq!(
+            paths(library.query(|_| true)),
+            ["/m/1 fav.mp3", "/m/2.mp3", "/m/10 fav.mp3", "/m/B/3.mp3"]
+        );
+        assert_eq!(
+            paths(library.favourites()),
+            ["/m/1 fav.mp3", "/m/10 fav.mp3"]
+        );
+        assert!(library.query(|entry| entry.size > 1).is_empty());
+    }
+
+    #[test]
+    fn renames_move_entries_with_their_data() {
+        let mut library = library(&["/m/old/a fav.mp3", "/m/old/b.mp3", "/m/older/c.mp3"]);
+        library.set_rating(Path::new("/m/old/b.mp3"), Some(3));
+        assert_eq!(library.rename(Path::new("/m/old"), Path::new("/m/new")), 2);
+        assert_eq!(
+            paths(library.query(|_| true)),
+            ["/m/new/a fav.mp3", "/m/new/b.mp3", "/m/older/c.mp3"]
+        );
+        let moved = library.get(Path::new("/m/new/b.mp3")).unwrap();
+        assert_eq!(moved.metadata.rating, Some(3));
+        assert!(
+            library
+                .get(Path::new("/m/new/a fav.mp3"))
+                .unwrap()
+                .favourite
+        );
+        assert!(library
+            .set_rating(Path::new("/m/old/b.mp3"), Some(1))
+            .is_none());
+    }
+}
@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::bookmark_model::Bookmark;
+use super::track_metadata_model::TrackMetadata;
+
+/// `Event` is reported by the player while it runs, so views and other listeners
+/// (notifications, scrobbling, logging) can follow playback without polling the controller.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// A new track was loaded and became the current one.
+    TrackChanged {
+        /// Index of the track in the `AudioFolderModel`, `None` for a queued track.
+        index: Option<usize>,
+        /// Path of the track's audio file.
+        path: PathBuf,
+        /// Tags of the track (title, artist, album, ...), empty fields if unknown.
+        metadata: TrackMetadata,
+    },
+    /// Playback started or resumed.
+    PlaybackStarted,
+    /// Playback was paused.
+    PlaybackPaused,
+    /// Playback was stopped and rewound.
+    PlaybackStopped,
+    /// Periodic playback position of the current track while playing.
+    PositionUpdated(Duration),
+    /// The current track played to its end (skipping a track does not count).
+    TrackFinished {
+        /// Index of the finished track in the `AudioFolderModel`, `None` for a queued track.
+        index: Option<usize>,
+        /// Path of the finished track's audio file.
+        path: PathBuf,
+    },
+    /// The play order changed (queue edits, shuffle or repeat mode), so the upcoming track may differ.
+    QueueChanged,
+    /// A bookmark was added to the current track.
+    BookmarkAdded {
+        /// Path of the bookmarked audio file.
+        path: PathBuf,
+        bookmark: Bookmark,
+    },
+    /// There are no tracks to load or play (an empty folder, or every track was deleted).
+    NothingToPlay,
+    /// Something went wrong, e.g. a file could not be decoded.
+    Error(String),
+}
@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::audio_folder_model::RepeatMode;
+use super::crossfade_model::CrossfadeModel;
+
+/// `PlayerStatus` is a snapshot of the player state, handed to views that run on another
+/// thread than the `PlayerController` (see `Command::GetStatus`). It leaves out the track
+/// list, the metadata and the bookmarks, which have their own queries (`Command::GetTracks`,
+/// `Command::GetMetadata`, `Command::GetBookmarks`).
+#[derive(Clone, Debug)]
+pub struct PlayerStatus {
+    /// Index of the current folder track.
+    pub current_index: usize,
+    /// The track that is loaded right now (a queued track or the current folder track),
+    /// `None` if there are no tracks.
+    pub current_track: Option<PathBuf>,
+    /// The queued tracks, the first one plays next.
+    pub queue: Vec<PathBuf>,
+    /// Playback position in the current track, if a stream is loaded.
+    pub position: Option<Duration>,
+    /// Whether playback is running.
+    pub is_playing: bool,
+    /// Whether the folder tracks play in shuffled order.
+    pub shuffle: bool,
+    /// The repeat mode.
+    pub repeat: RepeatMode,
+    /// The volume, from `0.0` (muted) to `1.0`.
+    pub volume: f32,
+    /// The crossfade duration and curve.
+    pub crossfade: CrossfadeModel,
+}
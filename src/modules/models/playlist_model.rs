@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// `PlaylistEntry` is one track of a playlist file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaylistEntry {
+    /// The resolved path of the track (relative entries are resolved against the playlist's folder).
+    pub path: PathBuf,
+    /// Display title (`#EXTINF` in M3U, `TitleN` in PLS, `title` in XSPF), if the playlist has one.
+    pub title: Option<String>,
+    /// Duration stated by the playlist, if any.
+    pub duration: Option<Duration>,
+}
+
+impl PlaylistEntry {
+    /// Creates an entry with only a path.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            title: None,
+            duration: None,
+        }
+    }
+}
+
+/// `PlaylistModel` is the content of a playlist file: the playable entries in order and
+/// the entries that point to files which do not exist (kept so they can be reported).
+#[derive(Clone, Debug, Default)]
+pub struct PlaylistModel {
+    /// Entries whose file exists, in playlist order.
+    pub entries: Vec<PlaylistEntry>,
+    /// Entries that could not be found, as written in the playlist.
+    pub missing: Vec<String>,
+}
@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+use super::folder_change_model::renamed_path;
+
+/// `QueueModel` is the list of tracks the user lined up to play next. It is separate from
+/// the `AudioFolderModel`: queued tracks can come from anywhere, they are played first and
+/// removed as they are played, then playback falls back to the folder order.
+#[derive(Default)]
+pub struct QueueModel {
+    /// The queued tracks, the front one plays next.
+    tracks: VecDeque<PathBuf>,
+}
+
+impl QueueModel {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the queued tracks in play order.
+    pub fn tracks(&self) -> Vec<PathBuf> {
+        self.tracks.iter().cloned().collect()
+    }
+
+    /// Returns the track that plays next, without removing it.
+    pub fn peek(&self) -> Option<&PathBuf> {
+        self.tracks.front()
+    }
+
+    /// Removes and returns the track that plays next.
+    pub fn pop(&mut self) -> Option<PathBuf> {
+        self.tracks.pop_front()
+    }
+
+    /// Adds a track to the end of the queue.
+    pub fn enqueue(&mut self, track: PathBuf) {
+        self.tracks.push_back(track);
+    }
+
+    /// Adds a track to the front of the queue, so it plays right after the current one.
+    pub fn play_next(&mut self, track: PathBuf) {
+        self.tracks.push_front(track);
+    }
+
+    /// Removes the track at `position` (0-based).
+    ///
+    /// # Returns
+    /// * `Some(PathBuf)` - The removed track, or `None` if the position is out of range.
+    pub fn remove(&mut self, position: usize) -> Option<PathBuf> {
+        self.tracks.remove(position)
+    }
+
+    /// Moves the track at `from` to position `to` (both 0-based).
+    ///
+    /// # Returns
+    /// * `true` if the track was moved, `false` if a position is out of range.
+    pub fn move_track(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.tracks.len() || to >= self.tracks.len() {
+            return false;
+        }
+        if let Some(track) = self.tracks.remove(from) {
+            self.tracks.insert(to, track);
+        }
+        true
+    }
+
+    /// Removes every queued track that is `path` or lies below it (a deleted folder).
+    ///
+    /// # Returns
+    /// * The number of tracks removed.
+    pub fn remove_tracks(&mut self, path: &Path) -> usize {
+        let before = self.tracks.len();
+        self.tracks.retain(|track| !track.starts_with(path));
+        before - self.tracks.len()
+    }
+
+    /// Points queued tracks of a renamed file or folder to their new paths.
+    pub fn rename_tracks(&mut self, from: &Path, to: &Path) {
+        for track in self.tracks.iter_mut() {
+            if let Some(renamed) = renamed_path(track, from, to) {
+                *track = renamed;
+            }
+        }
+    }
+
+    /// Removes every queued track.
+    pub fn clear(&mut self) {
+        self.tracks.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(tracks: &[&str]) -> QueueModel {
+        let mut queue = QueueModel::new();
+        for track in tracks {
+            queue.enqueue(PathBuf::from(track));
+        }
+        queue
+    }
+
+    fn paths(tracks: &[&str]) -> Vec<PathBuf> {
+        tracks.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn tracks_play_in_the_order_they_were_queued() {
+        let mut queue = queue(&["a.mp3", "b.mp3", "c.mp3"]);
+        assert_eq!(queue.tracks(), paths(&["a.mp3", "b.mp3", "c.mp3"]));
+        assert_eq!(queue.peek(), Some(&PathBuf::from("a.mp3")));
+        assert_eq!(queue.pop(), Some(PathBuf::from("a.mp3")));
+        assert_eq!(queue.pop(), Some(PathBuf::from("b.mp3")));
+        assert_eq!(queue.pop(), Some(PathBuf::from("c.mp3")));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn play_next_goes_in_front() {
+        let mut queue = queue(&["a.mp3", "b.mp3"]);
+        queue.play_next(PathBuf::from("next.mp3"));
+        queue.play_next(PathBuf::from("first.mp3"));
+        assert_eq!(
+            queue.tracks(),
+            paths(&["first.mp3", "next.mp3", "a.mp3", "b.mp3"])
+        );
+    }
+
+    #[test]
+    fn tracks_are_removed_and_moved_by_position() {
+        let mut queue = queue(&["a.mp3", "b.mp3", "c.mp3"]);
+        assert!(queue.move_track(0, 2));
+        assert_eq!(queue.tracks(), paths(&["b.mp3", "c.mp3", "a.mp3"]));
+        assert!(queue.move_track(2, 0));
+        assert_eq!(queue.tracks(), paths(&["a.mp3", "b.mp3", "c.mp3"]));
+
+        assert!(!queue.move_track(3, 0), "from out of range");
+        assert!(!queue.move_track(0, 3), "to out of range");
+        assert_eq!(queue.tracks(), paths(&["a.mp3", "b.mp3", "c.mp3"]));
+
+        assert_eq!(queue.remove(1), Some(PathBuf::from("b.mp3")));
+        assert_eq!(queue.remove(2), None);
+        assert_eq!(queue.tracks(), paths(&["a.mp3", "c.mp3"]));
+    }
+
+    #[test]
+    fn deleted_files_and_folders_leave_the_queue() {
+        let mut queue = queue(&["/m/x/a.mp3", "/m/y/b.mp3", "/m/x/c.mp3", "/m/xy/d.mp3"]);
+        assert_eq!(queue.remove_tracks(Path::new("/m/x")), 2);
+        assert_eq!(queue.tracks(), paths(&["/m/y/b.mp3", "/m/xy/d.mp3"]));
+        assert_eq!(queue.remove_tracks(Path::new("/m/y/b.mp3")), 1);
+        assert_eq!(queue.remove_tracks(Path::new("/m/z")), 0);
+        assert_eq!(queue.tracks(), paths(&["/m/xy/d.mp3"]));
+    }
+
+    #[test]
+    fn renamed_folders_are_followed() {
+        let mut queue = queue(&["/m/x/a.mp3", "/m/y/b.mp3"]);
+        queue.rename_tracks(Path::new("/m/x"), Path::new("/m/z"));
+        assert_eq!(queue.tracks(), paths(&["/m/z/a.mp3", "/m/y/b.mp3"]));
+    }
+}
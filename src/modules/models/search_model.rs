@@ -0,0 +1,389 @@
+use std::ops::RangeInclusive;
+
+use super::library_model::LibraryEntry;
+
+/// `SearchField` is a tag (or the path) a search filter looks at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchField {
+    Title,
+    /// The track artist or the album artist.
+    Artist,
+    Album,
+    Genre,
+    Path,
+    /// The file format (`mp3`, `flac`, ...).
+    Format,
+}
+
+impl SearchField {
+    /// Parses a filter name as typed in the CLI (`title:`, `artist:`, ... without the colon).
+    ///
+    /// # Returns
+    /// * `Some(SearchField)` if the name is known, otherwise `None`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "title" => Some(SearchField::Title),
+            "artist" | "albumartist" => Some(SearchField::Artist),
+            "album" => Some(SearchField::Album),
+            "genre" => Some(SearchField::Genre),
+            "path" | "folder" => Some(SearchField::Path),
+            "format" | "type" => Some(SearchField::Format),
+            _ => None,
+        }
+    }
+
+    /// Returns the texts of `entry` this field matches against.
+    fn values(self, entry: &LibraryEntry) -> Vec<String> {
+        let metadata = &entry.metadata;
+        let tags = match self {
+            SearchField::Title => vec![&metadata.title],
+            SearchField::Artist => vec![&metadata.artist, &metadata.album_artist],
+            SearchField::Album => vec![&metadata.album],
+            SearchField::Genre => vec![&metadata.genre],
+            SearchField::Path => return vec![entry.path.to_string_lossy().into_owned()],
+            SearchField::Format => return vec![entry.format.clone()],
+        };
+        tags.into_iter().flatten().cloned().collect()
+    }
+}
+
+/// `SearchQuery` is a parsed library search: free words that must all be found in the
+/// title, artist, album or path, plus field filters (`artist:`, `genre=jazz`,
+/// `year:2010..2015`, `rating:4..`, `fav:yes`, ...).
+///
+/// Words match case- and diacritic-insensitively at the start of a word, so `beyo`
+/// finds "Beyoncé" and `cafe` finds "Café del Mar".
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchQuery {
+    /// Folded words searched in the title, artist, album and path.
+    words: Vec<String>,
+    /// Folded words that must be found in one field.
+    filters: Vec<(SearchField, Vec<String>)>,
+    /// Folded texts one field must be equal to.
+    exact: Vec<(SearchField, String)>,
+    /// Allowed release years.
+    years: Option<RangeInclusive<u32>>,
+    /// Allowed ratings in stars, unrated tracks never match.
+    ratings: Option<RangeInclusive<u32>>,
+    /// Only favourites (`true`) or only tracks that are not (`false`).
+    favourite: Option<bool>,
+}
+
+impl SearchQuery {
+    /// Parses a search as typed in the CLI, e.g. `dark side artist:"pink floyd" year:1970..1979`.
+    ///
+    /// # Arguments
+    /// * `text` - Words and `field:value` filters, separated by spaces. Double quotes keep
+    ///   a value with spaces together. `field:value` finds the words of the value at the
+    ///   start of words of the field, `field=value` only matches a field equal to the whole
+    ///   value (`genre=jazz` skips "Jazz Fusion"). `year:` takes a year or a range (`2010..2015`,
+    ///   `2010..`, `..2015`), `rating:` stars the same way (`4..`), `fav:` `yes` or `no`.
+    ///
+    /// # Returns
+    /// * `Some(SearchQuery)` - If every filter is known and every year is a number.
+    /// * `None` - Otherwise, or if the query is empty.
+    pub fn parse(text: &str) -> Option<Self> {
+        Self::from_tokens(Self::tokens(text))
+    }
+
+    /// Parses a search already split by `tokens`, see `parse`.
+    pub fn from_tokens(tokens: Vec<String>) -> Option<Self> {
+        let mut query = SearchQuery::default();
+        for token in tokens {
+            let filter = token
+                .find([':', '='])
+                .map(|at| (&token[..at], token[at..].starts_with('='), &token[at + 1..]));
+            match filter {
+                Some((name, _, value)) if name.eq_ignore_ascii_case("year") => {
+                    query.years = Some(Self::parse_range(value)?);
+                }
+                Some((name, _, value)) if name.eq_ignore_ascii_case("rating") => {
+                    query.ratings = Some(Self::parse_range(value)?);
+                }
+                Some((name, _, value))
+                    if name.eq_ignore_ascii_case("fav")
+                        || name.eq_ignore_ascii_case("favourite") =>
+                {
+                    query.favourite = match value.to_lowercase().as_str() {
+                        "yes" | "y" | "1" | "true" => Some(true),
+                        "no" | "n" | "0" | "false" => Some(false),
+                        _ => return None,
+                    };
+                }
+                Some((name, true, value)) if !name.is_empty() => {
+                    let field = SearchField::from_name(name)?;
+                    let value = fold(value.trim());
+                    if value.is_empty() {
+                        return None;
+                    }
+                    query.exact.push((field, value));
+                }
+                Some((name, false, value)) if !name.is_empty() => {
+                    let field = SearchField::from_name(name)?;
+                    let words = Self::words(value);
+                    if words.is_empty() {
+                        return None;
+                    }
+                    query.filters.push((field, words));
+                }
+                _ => query.words.extend(Self::words(&token)),
+            }
+        }
+        if query.words.is_empty()
+            && query.filters.is_empty()
+            && query.exact.is_empty()
+            && query.years.is_none()
+            && query.ratings.is_none()
+            && query.favourite.is_none()
+        {
+            return None;
+        }
+        Some(query)
+    }
+
+    /// **Match a Library Entry**
+    ///
+    /// # Returns
+    /// * `true` if every word and every filter of the query is found in `entry`.
+    pub fn matches(&self, entry: &LibraryEntry) -> bool {
+        if let Some(ref years) = self.years {
+            if !entry
+                .metadata
+                .year
+                .is_some_and(|year| years.contains(&year))
+            {
+                return false;
+            }
+        }
+        if let Some(ref ratings) = self.ratings {
+            if !entry
+                .metadata
+                .rating
+                .is_some_and(|stars| ratings.contains(&u32::from(stars)))
+            {
+                return false;
+            }
+        }
+        if self
+            .favourite
+            .is_some_and(|favourite| favourite != entry.favourite)
+        {
+            return false;
+        }
+        let found = |words: &[String], texts: Vec<String>| {
+            let candidates: Vec<String> = texts.iter().flat_map(|text| Self::words(text)).collect();
+            words.iter().all(|word| {
+                candidates
+                    .iter()
+                    .any(|candidate| candidate.starts_with(word))
+            })
+        };
+        let anywhere = [
+            SearchField::Title,
+            SearchField::Artist,
+            SearchField::Album,
+            SearchField::Path,
+        ]
+        .iter()
+        .flat_map(|field| field.values(entry))
+        .collect();
+        found(&self.words, anywhere)
+            && self
+                .filters
+                .iter()
+                .all(|(field, words)| found(words, field.values(entry)))
+            && self.exact.iter().all(|(field, value)| {
+                field
+                    .values(entry)
+                    .iter()
+                    .any(|text| fold(text.trim()) == *value)
+            })
+    }
+
+    /// Splits the query at spaces outside double quotes, dropping the quotes.
+    pub fn tokens(text: &str) -> Vec<String> {
+        let mut tokens = Vec::new();
+        let mut token = String::new();
+        let mut quoted = false;
+        for c in text.chars() {
+            match c {
+                '"' => quoted = !quoted,
+                c if c.is_whitespace() && !quoted => {
+                    if !token.is_empty() {
+                        tokens.push(std::mem::take(&mut token));
+                    }
+                }
+                c => token.push(c),
+            }
+        }
+        if !token.is_empty() {
+            tokens.push(token);
+        }
+        tokens
+    }
+
+    /// Parses a number or a range of numbers: `1997`, `2010..2015`, `2010..` or `..2015`.
+    pub fn parse_range(value: &str) -> Option<RangeInclusive<u32>> {
+        let number = |text: &str, default: u32| match text.trim() {
+            "" => Some(default),
+            text => text.parse::<u32>().ok(),
+        };
+        match value.split_once("..") {
+            Some((from, to)) if !(from.trim().is_empty() && to.trim().is_empty()) => {
+                Some(number(from, 0)?..=number(to, u32::MAX)?)
+            }
+            Some(_) => None,
+            None => {
+                let number = value.trim().parse::<u32>().ok()?;
+                Some(number..=number)
+            }
+        }
+    }
+
+    /// Folds `text` and splits it into words at anything that is not a letter or digit.
+    fn words(text: &str) -> Vec<String> {
+        fold(text)
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// **Fold Text for Searching**
+///
+/// Lowercases `text` and replaces Latin letters with diacritics by their base letters
+/// (`Beyoncé` -> `beyonce`, `Straße` -> `strasse`), so searches ignore case and accents.
+/// Combining accents (decomposed file names) are dropped.
+pub fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        let base = match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+            'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+            'ď' | 'đ' | 'ð' => "d",
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+            'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+            'ĥ' | 'ħ' => "h",
+            'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+            'ĵ' => "j",
+            'ķ' => "k",
+            'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+            'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+            'ŕ' | 'ŗ' | 'ř' => "r",
+            'ś' | 'ŝ' | 'ş' | 'š' | 'ș' => "s",
+            'ţ' | 'ť' | 'ŧ' | 'ț' => "t",
+            'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+            'ŵ' => "w",
+            'ý' | 'ÿ' | 'ŷ' => "y",
+            'ź' | 'ż' | 'ž' => "z",
+            'ß' => "ss",
+            'æ' => "ae",
+            'œ' => "oe",
+            'þ' => "th",
+            '\u{300}'..='\u{36f}' => "", // Combining accents of decomposed text.
+            c => {
+                folded.push(c);
+                continue;
+            }
+        };
+        folded.push_str(base);
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::models::track_metadata_model::TrackMetadata;
+    use std::path::PathBuf;
+
+    fn entry(path: &str, title: &str, artist: &str, genre: &str) -> LibraryEntry {
+        LibraryEntry {
+            path: PathBuf::from(path),
+            size: 0,
+            format: "mp3".to_string(),
+            metadata: TrackMetadata {
+                title: Some(title.to_string()),
+                artist: Some(artist.to_string()),
+                genre: Some(genre.to_string()),
+                year: Some(1975),
+                rating: Some(4),
+                ..TrackMetadata::default()
+            },
+            favourite: false,
+        }
+    }
+
+    #[test]
+    fn fold_drops_case_and_accents() {
+        assert_eq!(fold("Beyoncé"), "beyonce");
+        assert_eq!(fold("Straße"), "strasse");
+        assert_eq!(fold("ÆON Œuvre"), "aeon oeuvre");
+        assert_eq!(fold("Cafe\u{301} del Mar"), "cafe del mar");
+        assert_eq!(fold("Łódź 2000"), "lodz 2000");
+    }
+
+    #[test]
+    fn tokens_keep_quoted_values_together() {
+        assert_eq!(
+            SearchQuery::tokens(r#"dark  side artist:"pink floyd" "#),
+            vec!["dark", "side", "artist:pink floyd"]
+        );
+        assert!(SearchQuery::tokens("   ").is_empty());
+    }
+
+    #[test]
+    fn parse_ranges() {
+        assert_eq!(SearchQuery::parse_range("1997"), Some(1997..=1997));
+        assert_eq!(SearchQuery::parse_range("2010..2015"), Some(2010..=2015));
+        assert_eq!(SearchQuery::parse_range("2010.."), Some(2010..=u32::MAX));
+        assert_eq!(SearchQuery::parse_range("..1980"), Some(0..=1980));
+        assert_eq!(SearchQuery::parse_range(".."), None);
+        assert_eq!(SearchQuery::parse_range("soon"), None);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_queries() {
+        assert_eq!(SearchQuery::parse(""), None);
+        assert_eq!(SearchQuery::parse("colour:red"), None);
+        assert_eq!(SearchQuery::parse("year:recent"), None);
+        assert_eq!(SearchQuery::parse("fav:maybe"), None);
+        assert_eq!(SearchQuery::parse("artist:"), None);
+        assert_eq!(SearchQuery::parse("genre="), None);
+    }
+
+    #[test]
+    fn words_match_at_word_starts() {
+        let track = entry(
+            "/music/Café del Mar.mp3",
+            "Café del Mar",
+            "Energy 52",
+            "Trance",
+        );
+        let matches = |text: &str| SearchQuery::parse(text).unwrap().matches(&track);
+        assert!(matches("cafe mar"));
+        assert!(matches("CAFÉ energy"));
+        assert!(!matches("afe"));
+        assert!(!matches("cafe jazz"));
+        assert!(matches("artist:energ"));
+        assert!(!matches("title:energy"));
+        assert!(matches("year:1970..1979 rating:4.. fav:no"));
+        assert!(!matches("year:..1970"));
+        assert!(!matches("fav:yes"));
+    }
+
+    #[test]
+    fn equals_matches_the_whole_field() {
+        let jazz = entry("/music/a.mp3", "So What", "Miles Davis", "Jazz");
+        let fusion = entry("/music/b.mp3", "Spain", "Chick Corea", "Jazz Fusion");
+        let query = SearchQuery::parse("genre=jazz").unwrap();
+        assert!(query.matches(&jazz));
+        assert!(!query.matches(&fusion));
+        assert!(SearchQuery::parse("genre:jazz").unwrap().matches(&fusion));
+        let quoted = SearchQuery::parse(r#"artist="MILES DAVIS""#).unwrap();
+        assert!(quoted.matches(&jazz));
+        assert!(!SearchQuery::parse("artist=miles").unwrap().matches(&jazz));
+    }
+}
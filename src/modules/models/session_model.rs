@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::audio_folder_model::RepeatMode;
+use super::crossfade_model::CrossfadeModel;
+use super::player_status_model::PlayerStatus;
+
+/// `SessionModel` is what the player was doing when it quit: where the tracks came from,
+/// the track and position it was at and its settings, so the next start can pick up there.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionModel {
+    /// The folder or playlist file the tracks were opened from.
+    pub source: PathBuf,
+    /// The track that was loaded (a folder track or a queued one).
+    pub track: Option<PathBuf>,
+    /// Index of the current folder track, tells apart the CUE tracks of one file.
+    pub index: usize,
+    /// Position in the track (from the start of the CUE track for a CUE track).
+    pub position: Duration,
+    /// Whether playback was running.
+    pub playing: bool,
+    /// The volume, from `0.0` (muted) to `1.0`.
+    pub volume: f32,
+    pub shuffle: bool,
+    pub repeat: RepeatMode,
+    /// The crossfade duration and curve.
+    pub crossfade: CrossfadeModel,
+    /// The queued tracks, the first one plays next.
+    pub queue: Vec<PathBuf>,
+}
+
+impl SessionModel {
+    /// Takes the session from a player status.
+    ///
+    /// # Arguments
+    /// * `source` - The folder or playlist file the tracks were opened from.
+    /// * `status` - The player state to keep.
+    pub fn from_status(source: PathBuf, status: &PlayerStatus) -> Self {
+        SessionModel {
+            source,
+            track: status.current_track.clone(),
+            index: status.current_index,
+            position: status.position.unwrap_or_default(),
+            playing: status.is_playing,
+            volume: status.volume,
+            shuffle: status.shuffle,
+            repeat: status.repeat,
+            crossfade: status.crossfade,
+            queue: status.queue.clone(),
+        }
+    }
+}
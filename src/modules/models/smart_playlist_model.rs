@@ -0,0 +1,348 @@
+use std::cmp::Ordering;
+use std::ops::RangeInclusive;
+use std::time::{Duration, UNIX_EPOCH};
+
+use super::history_model::{HistoryModel, TrackStats};
+use super::library_model::{LibraryEntry, LibraryModel};
+use super::search_model::SearchQuery;
+
+/// One day, the unit of `lastplayed:` ages without a unit.
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// `SmartSort` is the order of the tracks of a smart playlist (`sort:` rule).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmartSort {
+    /// Natural order of the paths, the order without a `sort:` rule.
+    Name,
+    /// Shortest first.
+    Duration,
+    /// Fewest stars first.
+    Rating,
+    /// Fewest plays first.
+    Plays,
+    /// Longest ago first.
+    LastPlayed,
+    /// Oldest release first.
+    Year,
+    /// Oldest modification time first.
+    Modified,
+}
+
+impl SmartSort {
+    /// Parses a sort key name as typed in a rule (`name`, `duration`, `rating`, `plays`,
+    /// `lastplayed`, `year`, `mtime`).
+    ///
+    /// # Returns
+    /// * `Some(SmartSort)` if the name is known, otherwise `None`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "name" | "path" => Some(SmartSort::Name),
+            "duration" | "length" => Some(SmartSort::Duration),
+            "rating" | "stars" => Some(SmartSort::Rating),
+            "plays" | "playcount" => Some(SmartSort::Plays),
+            "lastplayed" => Some(SmartSort::LastPlayed),
+            "year" => Some(SmartSort::Year),
+            "mtime" | "modified" => Some(SmartSort::Modified),
+            _ => None,
+        }
+    }
+
+    /// Returns what `entry` is sorted by, `None` if it is not known (sorted last).
+    fn key(self, entry: &LibraryEntry, stats: &TrackStats) -> Option<u128> {
+        let metadata = &entry.metadata;
+        let millis = |time: Option<std::time::SystemTime>| {
+            time.and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|time| time.as_millis())
+        };
+        match self {
+            SmartSort::Name => None,
+            SmartSort::Duration => metadata.duration.map(|duration| duration.as_millis()),
+            SmartSort::Rating => metadata.rating.map(u128::from),
+            SmartSort::Plays => Some(stats.plays as u128),
+            SmartSort::LastPlayed => millis(stats.last_played),
+            SmartSort::Year => metadata.year.map(u128::from),
+            SmartSort::Modified => millis(metadata.modified),
+        }
+    }
+}
+
+/// `SmartPlaylist` is a playlist defined by rules instead of tracks: the library entries
+/// matching the rules, evaluated again every time it is played, so it follows new tracks,
+/// ratings and plays.
+///
+/// The rules are a library search (`genre:jazz rating:4..`, `genre=jazz` for the whole
+/// field, see `SearchQuery`) plus:
+/// * `played:<count|from..to>` - Number of plays, playbacks of at least half the track or
+///   4 minutes (see `history_model::is_play`).
+/// * `lastplayed:<age|from..to>` - Time since the track was last played (skips do not
+///   count), in days or with `h`, `d` or `w`. A single age is the least one: `lastplayed:30d` = not played in the
+///   last 30 days (never played tracks too), `lastplayed:..7d` = played this week.
+/// * `sort:[-]<key>` - Order of the tracks, `-` reverses it (`sort:-duration` = longest
+///   first), see `SmartSort`.
+/// * `limit:<count>` - Keep only the first tracks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SmartPlaylist {
+    /// The name, also the file name of the definition.
+    pub name: String,
+    /// The rules as typed.
+    pub rules: String,
+    /// The search part of the rules, `None` matches every track.
+    query: Option<SearchQuery>,
+    /// Allowed play counts.
+    plays: Option<RangeInclusive<u32>>,
+    /// Allowed times since the last play, never played tracks count as played forever ago.
+    last_played: Option<RangeInclusive<Duration>>,
+    /// The order and whether it is reversed.
+    sort: Option<(SmartSort, bool)>,
+    /// Maximum number of tracks.
+    limit: Option<usize>,
+}
+
+impl SmartPlaylist {
+    /// **Parse a Smart Playlist**
+    ///
+    /// # Arguments
+    /// * `name` - The name: not empty, without `/` or `\` and not starting with a dot.
+    /// * `rules` - The rules, e.g. `rating:4.. lastplayed:30d` or
+    ///   `genre:jazz sort:-duration limit:50`.
+    ///
+    /// # Returns
+    /// * `Some(SmartPlaylist)` - If the name is valid and every rule is known.
+    /// * `None` - Otherwise, or if there are no rules.
+    pub fn parse(name: &str, rules: &str) -> Option<Self> {
+        if !Self::is_valid_name(name) {
+            return None;
+        }
+        let mut playlist = SmartPlaylist {
+            name: name.to_string(),
+            rules: rules.split_whitespace().collect::<Vec<_>>().join(" "),
+            query: None,
+            plays: None,
+            last_played: None,
+            sort: None,
+            limit: None,
+        };
+        let mut search = Vec::new();
+        for token in SearchQuery::tokens(rules) {
+            let Some((rule, value)) = token.split_once(':') else {
+                search.push(token);
+                continue;
+            };
+            match rule.to_lowercase().as_str() {
+                "played" => playlist.plays = Some(SearchQuery::parse_range(value)?),
+                "lastplayed" => playlist.last_played = Some(Self::parse_ages(value)?),
+                "sort" => {
+                    let (key, descending) = match value.strip_prefix('-') {
+                        Some(key) => (key, true),
+                        None => (value, false),
+                    };
+                    playlist.sort = Some((SmartSort::from_name(key)?, descending));
+                }
+                "limit" => playlist.limit = Some(value.parse().ok()?),
+                _ => search.push(token),
+            }
+        }
+        if !search.is_empty() {
+            playlist.query = Some(SearchQuery::from_tokens(search)?);
+        } else if playlist.rules.is_empty() {
+            return None;
+        }
+        Some(playlist)
+    }
+
+    /// Returns `true` if `name` can name a smart playlist: not empty, without `/` or `\`
+    /// and not starting with a dot, so its definition file stays in the playlist folder.
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\'])
+    }
+
+    /// **Evaluate the Rules**
+    ///
+    /// # Arguments
+    /// * `library` - The tracks to pick from.
+    /// * `history` - The play history, for `played:`, `lastplayed:` and their sort keys.
+    ///
+    /// # Returns
+    /// * The matching entries in the order of the `sort:` rule (natural path order without
+    ///   one, or for equal keys), at most `limit:` of them.
+    pub fn evaluate<'a>(
+        &self,
+        library: &'a LibraryModel,
+        history: &HistoryModel,
+    ) -> Vec<&'a LibraryEntry> {
+        let stats = history.stats();
+        let stats_of =
+            |entry: &LibraryEntry| stats.get(entry.path.as_path()).copied().unwrap_or_default();
+        let mut tracks = library.query(|entry| {
+            let stats = stats_of(entry);
+            let age = match stats.last_played {
+                Some(time) => time.elapsed().unwrap_or_default(),
+                None => Duration::MAX,
+            };
+            match &self.query {
+                Some(query) if !query.matches(entry) => return false,
+                _ => {}
+            }
+            match &self.plays {
+                Some(plays) if !plays.contains(&(stats.plays as u32)) => return false,
+                _ => {}
+            }
+            match &self.last_played {
+                Some(ages) => ages.contains(&age),
+                None => true,
+            }
+        });
+
+        match self.sort {
+            Some((SmartSort::Name, true)) => tracks.reverse(),
+            Some((sort, descending)) => tracks.sort_by(|a, b| {
+                match (sort.key(a, &stats_of(a)), sort.key(b, &stats_of(b))) {
+                    (Some(a), Some(b)) if descending => b.cmp(&a),
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                }
+            }),
+            None => {}
+        }
+        if let Some(limit) = self.limit {
+            tracks.truncate(limit);
+        }
+        tracks
+    }
+
+    /// Parses an age or a range of ages: `30d` (at least 30 days), `12h..2w` or `..7d`.
+    fn parse_ages(value: &str) -> Option<RangeInclusive<Duration>> {
+        let age = |text: &str, default: Duration| {
+            let text = text.trim();
+            if text.is_empty() {
+                return Some(default);
+            }
+            let (number, unit) = match text.char_indices().last()? {
+                (at, 'h') => (&text[..at], Duration::from_secs(60 * 60)),
+                (at, 'd') => (&text[..at], DAY),
+                (at, 'w') => (&text[..at], DAY * 7),
+                _ => (text, DAY),
+            };
+            unit.checked_mul(number.parse().ok()?)
+        };
+        match value.split_once("..") {
+            Some((from, to)) if !(from.trim().is_empty() && to.trim().is_empty()) => {
+                Some(age(from, Duration::ZERO)?..=age(to, Duration::MAX)?)
+            }
+            Some(_) => None,
+            None => Some(age(value, Duration::ZERO)?..=Duration::MAX),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::models::history_model::PlayRecord;
+    use crate::modules::models::track_metadata_model::TrackMetadata;
+    use std::path::PathBuf;
+    use std::time::SystemTime;
+
+    fn entry(path: &str, genre: &str, duration: u64) -> LibraryEntry {
+        LibraryEntry {
+            path: PathBuf::from(path),
+            size: 0,
+            format: "mp3".to_string(),
+            metadata: TrackMetadata {
+                genre: Some(genre.to_string()),
+                duration: Some(Duration::from_secs(duration)),
+                ..TrackMetadata::default()
+            },
+            favourite: false,
+        }
+    }
+
+    fn paths(tracks: Vec<&LibraryEntry>) -> Vec<&str> {
+        tracks
+            .iter()
+            .map(|entry| entry.path.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn parse_splits_the_rules() {
+        let playlist = SmartPlaylist::parse(
+            "best",
+            "  genre=jazz  played:2..  lastplayed:30d sort:-duration limit:50",
+        )
+        .unwrap();
+        assert_eq!(
+            playlist.rules,
+            "genre=jazz played:2.. lastplayed:30d sort:-duration limit:50"
+        );
+        assert!(playlist.query.is_some());
+        assert_eq!(playlist.plays, Some(2..=u32::MAX));
+        assert_eq!(playlist.last_played, Some(DAY * 30..=Duration::MAX));
+        assert_eq!(playlist.sort, Some((SmartSort::Duration, true)));
+        assert_eq!(playlist.limit, Some(50));
+
+        let recent = SmartPlaylist::parse("week", "lastplayed:..7d").unwrap();
+        assert!(recent.query.is_none());
+        assert_eq!(recent.last_played, Some(Duration::ZERO..=DAY * 7));
+        let hours = SmartPlaylist::parse("day", "lastplayed:12h..2w").unwrap();
+        assert_eq!(
+            hours.last_played,
+            Some(Duration::from_secs(12 * 3600)..=DAY * 14)
+        );
+    }
+
+    #[test]
+    fn parse_rejects_invalid_playlists() {
+        for name in ["", ".hidden", "a/b", "a\\b"] {
+            assert_eq!(SmartPlaylist::parse(name, "genre:jazz"), None, "{:?}", name);
+        }
+        for rules in [
+            "",
+            "   ",
+            "colour:red",
+            "played:many",
+            "lastplayed:..",
+            "lastplayed:soon",
+            "sort:random",
+            "limit:-1",
+        ] {
+            assert_eq!(SmartPlaylist::parse("name", rules), None, "{:?}", rules);
+        }
+    }
+
+    #[test]
+    fn evaluate_applies_the_rules() {
+        let mut library = LibraryModel::default();
+        library.insert(entry("/m/a.mp3", "Jazz", 300));
+        library.insert(entry("/m/b.mp3", "Jazz Fusion", 200));
+        library.insert(entry("/m/c.mp3", "Jazz", 400));
+        library.insert(entry("/m/d.mp3", "Rock", 100));
+        let mut history = HistoryModel::default();
+        for (path, skipped) in [("/m/a.mp3", true), ("/m/b.mp3", false), ("/m/c.mp3", false)] {
+            history.record(PlayRecord {
+                path: PathBuf::from(path),
+                started: SystemTime::now() - DAY,
+                listened: Duration::from_secs(10),
+                skipped,
+            });
+        }
+        let evaluate = |rules: &str| {
+            let playlist = SmartPlaylist::parse("test", rules).unwrap();
+            paths(playlist.evaluate(&library, &history))
+        };
+
+        assert_eq!(evaluate("genre:jazz"), ["/m/a.mp3", "/m/b.mp3", "/m/c.mp3"]);
+        assert_eq!(evaluate("genre=jazz"), ["/m/a.mp3", "/m/c.mp3"]);
+        // A skip is not a play, so the skipped track was not played in the last 30 days.
+        assert_eq!(evaluate("genre=jazz lastplayed:30d"), ["/m/a.mp3"]);
+        assert_eq!(evaluate("lastplayed:..7d"), ["/m/b.mp3", "/m/c.mp3"]);
+        assert_eq!(evaluate("played:0"), ["/m/a.mp3", "/m/d.mp3"]);
+        assert_eq!(
+            evaluate("genre:jazz sort:-duration limit:2"),
+            ["/m/c.mp3", "/m/a.mp3"]
+        );
+        assert_eq!(evaluate("sort:-name limit:1"), ["/m/d.mp3"]);
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use super::artwork_model::Artwork;
+
+/// `TrackMetadata` holds what is known about one audio file beyond its path.
+/// Every field is optional: files without tags (or unreadable ones) leave them empty.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrackMetadata {
+    /// Track title.
+    pub title: Option<String>,
+    /// Track artist.
+    pub artist: Option<String>,
+    /// Album title.
+    pub album: Option<String>,
+    /// Artist of the whole album (compilations: "Various Artists").
+    pub album_artist: Option<String>,
+    /// Track number from the tags (`3` for `3/12`).
+    pub track_number: Option<u32>,
+    /// Disc number from the tags (`1` for `1/2`).
+    pub disc_number: Option<u32>,
+    /// Release year (`1997` for `1997-05-21`).
+    pub year: Option<u32>,
+    /// Genre name.
+    pub genre: Option<String>,
+    /// Rating from 1 to 5 stars, from the tags (`POPM`, `FMPS_RATING`) or set in the library.
+    pub rating: Option<u8>,
+    /// Playing time, from the container headers.
+    pub duration: Option<Duration>,
+    /// Last modification time of the file.
+    pub modified: Option<SystemTime>,
+    /// Cover art, read when the track starts playing (embedded or from its folder). Not
+    /// kept in the library, the image data would bloat it.
+    pub artwork: Option<Arc<Artwork>>,
+}
+
+/// `TagField` names a tag that can be edited and written back to the file (`tag set <field>`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TagField {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    TrackNumber,
+    DiscNumber,
+    Year,
+    Genre,
+    /// Stars from 1 to 5, written as an ID3 `POPM` frame or an `FMPS_RATING` comment.
+    Rating,
+}
+
+impl TagField {
+    /// Parses a field name as typed in the CLI (`title`, `artist`, `album`, `albumartist`,
+    /// `track`, `disc`, `year`, `genre`, `rating`).
+    ///
+    /// # Returns
+    /// * `Some(TagField)` if the name is known, otherwise `None`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "title" => Some(TagField::Title),
+            "artist" => Some(TagField::Artist),
+            "album" => Some(TagField::Album),
+            "albumartist" | "album_artist" => Some(TagField::AlbumArtist),
+            "track" | "tracknumber" => Some(TagField::TrackNumber),
+            "disc" | "discnumber" => Some(TagField::DiscNumber),
+            "year" | "date" => Some(TagField::Year),
+            "genre" => Some(TagField::Genre),
+            "rating" => Some(TagField::Rating),
+            _ => None,
+        }
+    }
+}
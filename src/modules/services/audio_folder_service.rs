@@ -1,13 +1,98 @@
+use std::collections::HashSet;
+use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-pub struct AudioFolderService;
+use log::{debug, warn};
+
+use crate::modules::models::audio_folder_model::natural_cmp;
+
+/// Environment variable holding the maximum scan depth (`0` = only the chosen folder).
+pub const SCAN_DEPTH_VAR: &str = "SENSIT_SCAN_DEPTH";
+
+/// Environment variable switching symlink following on (`1`, `true`, `yes`, `on`).
+pub const SCAN_FOLLOW_LINKS_VAR: &str = "SENSIT_SCAN_FOLLOW_LINKS";
+
+/// Environment variable switching the scanning of hidden files and folders on.
+pub const SCAN_HIDDEN_VAR: &str = "SENSIT_SCAN_HIDDEN";
+
+/// Environment variable holding comma-separated glob patterns a file must match.
+pub const SCAN_INCLUDE_VAR: &str = "SENSIT_SCAN_INCLUDE";
+
+/// Environment variable holding comma-separated glob patterns of files and folders to skip.
+pub const SCAN_EXCLUDE_VAR: &str = "SENSIT_SCAN_EXCLUDE";
+
+/// **ScanOptions Struct**
+///
+/// Controls how `AudioFolderService` walks a music folder.
+///
+/// Glob patterns support `*` (any characters but `/`), `?` (one character) and `**` (any
+/// number of folders). A pattern without `/` is matched against the file or folder name,
+/// one with `/` against the path relative to the scanned folder, e.g. `*.mp3`, `Live*`,
+/// `**/Demos/*.wav`.
+#[derive(Clone, Debug, Default)]
+pub struct ScanOptions {
+    pub max_depth: Option<usize>, // Folder levels below the root to descend into, `None` = no limit.
+    pub follow_symlinks: bool,    // Follow symlinks (loops are detected), otherwise skip them.
+    pub include_hidden: bool,     // Also scan names starting with a dot.
+    pub include: Vec<String>,     // Only files matching one of these are kept (empty = all).
+    pub exclude: Vec<String>,     // Files and folders matching one of these are skipped.
+}
+
+impl ScanOptions {
+    /// Reads the options from the `SENSIT_SCAN_*` environment variables. Invalid values are
+    /// logged and replaced by the defaults (unlimited depth, no symlinks, no hidden files).
+    pub fn from_env() -> Self {
+        let flag = |name: &str| {
+            env::var(name)
+                .map(|value| {
+                    matches!(
+                        value.trim().to_lowercase().as_str(),
+                        "1" | "true" | "yes" | "on"
+                    )
+                })
+                .unwrap_or(false)
+        };
+        let patterns = |name: &str| {
+            env::var(name)
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|pattern| !pattern.is_empty())
+                .map(String::from)
+                .collect()
+        };
+        let max_depth = match env::var(SCAN_DEPTH_VAR) {
+            Ok(value) => match value.trim().parse() {
+                Ok(depth) => Some(depth),
+                Err(_) => {
+                    warn!("Ignoring invalid {} '{}'.", SCAN_DEPTH_VAR, value);
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+
+        ScanOptions {
+            max_depth,
+            follow_symlinks: flag(SCAN_FOLLOW_LINKS_VAR),
+            include_hidden: flag(SCAN_HIDDEN_VAR),
+            include: patterns(SCAN_INCLUDE_VAR),
+            exclude: patterns(SCAN_EXCLUDE_VAR),
+        }
+    }
+}
+
+pub struct AudioFolderService {
+    options: ScanOptions,
+}
 
 /// **AudioFolderService Implementation**
 ///
 /// This implementation provides functionality to load audio files from a specified folder path.
-/// It reads the contents of a directory, filters audio files based on their extension (e.g., `.mp3` and `.wav`),
-/// and returns the paths of the valid audio files.
+/// It walks the folder and its subfolders (e.g. `Artist/Album/track`), filters audio files based
+/// on their extension (`.mp3`, `.wav` and `.flac`) and the `ScanOptions`, and returns the paths of
+/// the valid audio files.
 ///
 /// # Usage
 /// - Use this service to retrieve a list of audio files from a user-specified folder.
@@ -15,7 +100,7 @@ pub struct AudioFolderService;
 ///
 /// # Example
 /// ```
-/// let audio_service = AudioFolderService;
+/// let audio_service = AudioFolderService::new(ScanOptions::default());
 /// let audio_files = audio_service.load_audio_files("/path/to/audio");
 /// if audio_files.is_empty() {
 ///     println!("No audio files found.");
@@ -24,54 +109,296 @@ pub struct AudioFolderService;
 /// }
 /// ```
 impl AudioFolderService {
+    /// Creates a service that scans folders according to `options`.
+    pub fn new(options: ScanOptions) -> Self {
+        AudioFolderService { options }
+    }
+
     /// **Load Audio Files from Folder**
     ///
-    /// This function reads the specified folder path and returns the paths of audio files
-    /// with supported extensions (currently `.mp3` and `.wav`).
+    /// This function walks the specified folder path and returns the paths of audio files
+    /// with supported extensions (`.mp3`, `.wav` and `.flac`, in any case).
     ///
     /// # Parameters
     /// - `folder_path`: A string slice representing the path to the folder containing audio files.
     ///
     /// # Returns
-    /// - A `Vec<PathBuf>` containing the paths of all audio files found in the folder.
+    /// - A `Vec<PathBuf>` containing the paths of all audio files found in the folder tree.
     /// - If the folder is invalid or contains no supported audio files, it returns an empty vector.
     ///
     /// # Behavior
     /// - If the folder cannot be read (e.g., it doesn’t exist), it returns an empty vector.
-    /// - Filters files by their extension (`mp3`, `wav`). This can be extended to support more formats.
+    ///   Unreadable subfolders are logged and skipped.
+    /// - Filters files by their extension (`mp3`, `wav`, `flac`, also `MP3`, ...). This can be
+    ///   extended to support more formats.
+    /// - The order is stable: each folder's entries are visited in natural order by name
+    ///   (`2 - x` before `10 - x`), and a subfolder's files are listed where the subfolder sorts.
+    /// - With `follow_symlinks`, a folder reached a second time (through a symlink loop or
+    ///   two links to the same place) is skipped.
     ///
     /// # Example
     /// ```
-    /// let audio_files = AudioFolderService.load_audio_files("/path/to/folder");
+    /// let audio_files = audio_service.load_audio_files("/path/to/folder");
     /// if audio_files.is_empty() {
     ///     println!("No audio files found or invalid folder path.");
     /// }
     /// ```
     pub fn load_audio_files(&self, folder_path: &str) -> Vec<PathBuf> {
         let mut files = Vec::new();
+        let root = Path::new(folder_path);
 
         // Attempt to read the directory. If it fails, return an empty vector.
-        let paths = match fs::read_dir(folder_path) {
-            Ok(paths) => paths,
-            Err(_) => return files, // Invalid folder path.
+        if !root.is_dir() {
+            return files; // Invalid folder path.
+        }
+
+        let mut visited = HashSet::new();
+        if let Ok(canonical) = fs::canonicalize(root) {
+            visited.insert(canonical);
+        }
+        self.scan_folder(root, root, 0, &mut visited, &mut files);
+
+        files // Return the vector of valid audio file paths.
+    }
+
+    /// **Check a Single File**
+    ///
+    /// Tells whether a scan of `root` would list `path`, without scanning: used for files
+    /// that appear while the folder is watched.
+    ///
+    /// # Parameters
+    /// - `root`: The scanned folder.
+    /// - `path`: A file below `root`.
+    ///
+    /// # Returns
+    /// - `true` if `path` is a supported audio file below `root` that the `ScanOptions`
+    ///   (depth, hidden names, include and exclude patterns) keep.
+    pub fn accepts(&self, root: &Path, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+        let depth = relative.components().count().saturating_sub(1);
+        if !Self::is_audio_file(path) || self.options.max_depth.is_some_and(|max| depth > max) {
+            return false;
+        }
+        // Every folder on the way is checked like the scan checks it before descending.
+        let mut below = PathBuf::new();
+        for component in relative.components() {
+            below.push(component);
+            let name = component.as_os_str().to_string_lossy();
+            if (!self.options.include_hidden && name.starts_with('.'))
+                || self
+                    .options
+                    .exclude
+                    .iter()
+                    .any(|pattern| Self::glob_matches(pattern, &below))
+            {
+                return false;
+            }
+        }
+        self.options.include.is_empty()
+            || self
+                .options
+                .include
+                .iter()
+                .any(|pattern| Self::glob_matches(pattern, relative))
+    }
+
+    /// Collects the audio files of `folder` and, depth permitting, of its subfolders.
+    ///
+    /// # Parameters
+    /// - `root`: The folder the scan started at, patterns are matched relative to it.
+    /// - `folder`: The folder to read.
+    /// - `depth`: How many levels `folder` is below `root`.
+    /// - `visited`: Canonical paths of the folders scanned so far (loop detection).
+    /// - `files`: Receives the audio files found.
+    fn scan_folder(
+        &self,
+        root: &Path,
+        folder: &Path,
+        depth: usize,
+        visited: &mut HashSet<PathBuf>,
+        files: &mut Vec<PathBuf>,
+    ) {
+        let entries = match fs::read_dir(folder) {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!("Skipping unreadable folder {:?}: {}", folder.display(), err);
+                return;
+            }
         };
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        // read_dir order depends on the file system, sort naturally for a stable result (`2` before `10`).
+        paths.sort_by(|a, b| {
+            natural_cmp(
+                &a.file_name().unwrap_or_default().to_string_lossy(),
+                &b.file_name().unwrap_or_default().to_string_lossy(),
+            )
+        });
 
-        // Iterate through the directory and collect valid audio files.
         for path in paths {
-            if let Ok(entry) = path {
-                let path = entry.path();
-                // Check if the file has a supported audio extension.
-                if path
-                    .extension()
-                    .and_then(|s| s.to_str())
-                    .map(|s| s == "mp3" || s == "wav")
-                    .unwrap_or(false)
-                {
-                    files.push(path); // Add valid audio file path to the vector.
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !self.options.include_hidden && name.starts_with('.') {
+                continue;
+            }
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            if self
+                .options
+                .exclude
+                .iter()
+                .any(|pattern| Self::glob_matches(pattern, relative))
+            {
+                continue;
+            }
+
+            // symlink_metadata does not follow links, so links can be told apart.
+            let is_symlink = fs::symlink_metadata(&path)
+                .map(|metadata| metadata.file_type().is_symlink())
+                .unwrap_or(false);
+            if is_symlink && !self.options.follow_symlinks {
+                continue;
+            }
+
+            if path.is_dir() {
+                if self.options.max_depth.is_some_and(|max| depth >= max) {
+                    continue;
+                }
+                let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                if !visited.insert(canonical) {
+                    debug!("Skipping {:?}, already scanned.", path.display());
+                    continue;
                 }
+                self.scan_folder(root, &path, depth + 1, visited, files);
+            } else if Self::is_audio_file(&path)
+                && (self.options.include.is_empty()
+                    || self
+                        .options
+                        .include
+                        .iter()
+                        .any(|pattern| Self::glob_matches(pattern, relative)))
+            {
+                files.push(path); // Add valid audio file path to the vector.
             }
         }
+    }
 
-        files // Return the vector of valid audio file paths.
+    /// Checks if the file has a supported audio extension.
+    fn is_audio_file(path: &Path) -> bool {
+        path.extension()
+            .and_then(|s| s.to_str())
+            .map(|s| {
+                ["mp3", "wav", "flac"]
+                    .iter()
+                    .any(|e| s.eq_ignore_ascii_case(e))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Returns `true` if `relative` (a path below the scanned folder) matches `pattern`.
+    /// A pattern without `/` only looks at the last component (the name).
+    fn glob_matches(pattern: &str, relative: &Path) -> bool {
+        let components: Vec<String> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let segments: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+        if !pattern.contains('/') {
+            return components
+                .last()
+                .is_some_and(|name| Self::wildcard_matches(pattern, name));
+        }
+        Self::segments_match(&segments, &components)
+    }
+
+    /// Matches pattern segments against path components, `**` standing for any number of them.
+    fn segments_match(segments: &[&str], components: &[String]) -> bool {
+        match segments.split_first() {
+            None => components.is_empty(),
+            Some((&"**", rest)) => {
+                (0..=components.len()).any(|skip| Self::segments_match(rest, &components[skip..]))
+            }
+            Some((segment, rest)) => match components.split_first() {
+                Some((component, remaining)) => {
+                    Self::wildcard_matches(segment, component)
+                        && Self::segments_match(rest, remaining)
+                }
+                None => false,
+            },
+        }
+    }
+
+    /// Matches one name against a pattern with `*` and `?` wildcards. `?` stands for one
+    /// character, not one byte, so it also matches accented and other non-ASCII letters.
+    fn wildcard_matches(pattern: &str, name: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let name: Vec<char> = name.chars().collect();
+        let (mut p, mut n) = (0, 0);
+        let mut backtrack: Option<(usize, usize)> = None; // Last `*` and the name position it matched up to.
+        while n < name.len() {
+            if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+                p += 1;
+                n += 1;
+            } else if p < pattern.len() && pattern[p] == '*' {
+                backtrack = Some((p, n));
+                p += 1;
+            } else if let Some((star, matched)) = backtrack {
+                // Let the last `*` swallow one more character and retry.
+                p = star + 1;
+                n = matched + 1;
+                backtrack = Some((star, n));
+            } else {
+                return false;
+            }
+        }
+        pattern[p..].iter().all(|&c| c == '*')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn question_mark_matches_one_character() {
+        assert!(AudioFolderService::wildcard_matches("caf?.mp3", "café.mp3"));
+        assert!(AudioFolderService::wildcard_matches("??.wav", "éé.wav"));
+        assert!(!AudioFolderService::wildcard_matches("?.wav", "éé.wav"));
+    }
+
+    #[test]
+    fn star_matches_any_run() {
+        assert!(AudioFolderService::wildcard_matches(
+            "*.mp3",
+            "01 Intro.mp3"
+        ));
+        assert!(AudioFolderService::wildcard_matches("a*b*c", "aXbYbZc"));
+        assert!(!AudioFolderService::wildcard_matches("*.mp3", "cover.jpg"));
+    }
+
+    #[test]
+    fn patterns_with_slashes_match_whole_paths() {
+        let path = Path::new("Live/1999/track.mp3");
+        assert!(AudioFolderService::glob_matches("**/track.mp3", path));
+        assert!(AudioFolderService::glob_matches("Live/*/*.mp3", path));
+        assert!(!AudioFolderService::glob_matches("Live/*.mp3", path));
+        assert!(AudioFolderService::glob_matches("*.mp3", path));
+    }
+
+    #[test]
+    fn extensions_match_in_any_case() {
+        for name in ["a.mp3", "B.MP3", "c.Flac", "d.wav"] {
+            assert!(
+                AudioFolderService::is_audio_file(Path::new(name)),
+                "{}",
+                name
+            );
+        }
+        for name in ["cover.jpg", "mp3", "notes.mp3.txt"] {
+            assert!(
+                !AudioFolderService::is_audio_file(Path::new(name)),
+                "{}",
+                name
+            );
+        }
     }
 }
@@ -1,111 +1,153 @@
 use cpal::traits::{DeviceTrait, HostTrait};
 use cpal::Stream;
+use log::error;
 use std::error::Error;
 use std::path::Path;
+use std::sync::Arc;
 
+use super::flac_loader::FlacLoader;
 use super::mp3_loader::Mp3Loader;
+use super::playback_handle::PlaybackHandle;
 use super::wav_loader::WavLoader;
 
+/// **DecodedAudio Struct**
+///
+/// The fully decoded, interleaved samples of one audio file. Decoding is the slow part of
+/// loading a track, so decoded audio is shared (`Arc`) between the preload cache and streams.
+pub struct DecodedAudio {
+    pub samples: Vec<f32>, // Interleaved samples.
+    pub channels: u16,     // Number of interleaved channels.
+    pub sample_rate: u32,  // Frames per second of the file, the output device may differ.
+}
+
+impl DecodedAudio {
+    /// Returns the memory taken by the samples, used for the preload cache budget.
+    pub fn size_in_bytes(&self) -> usize {
+        self.samples.len() * std::mem::size_of::<f32>()
+    }
+}
+
 /// **AudioLoader Trait**
 ///
 /// This trait defines the behavior for creating an audio stream from a given audio file.
 /// Implementations are responsible for decoding audio files and returning a `cpal::Stream` for playback.
-pub trait AudioLoader {
-    /// Creates an audio stream for playback.
+/// Decoding and stream creation are separate steps so decoding can run on a worker thread.
+pub trait AudioLoader: Send + Sync {
+    /// Decodes an audio file into memory.
     ///
     /// # Parameters:
     /// - `file_path`: The path to the audio file.
     ///
     /// # Returns:
+    /// - `Ok(DecodedAudio)`: The decoded samples, channel count and sample rate.
+    /// - `Err(Box<dyn Error>)`: If the format is unsupported or decoding fails.
+    fn decode(&self, file_path: &Path) -> Result<DecodedAudio, Box<dyn Error>>;
+
+    /// Creates an audio stream for playback.
+    ///
+    /// # Parameters:
+    /// - `audio`: The decoded audio to play.
+    /// - `handle`: The shared handle the stream renders through (gain ramps, position).
+    ///
+    /// # Returns:
     /// - `Ok(Stream)`: A `cpal::Stream` that can play the audio.
-    /// - `Err(Box<dyn Error>)`: If loading the file or creating the stream fails.
-    fn create_audio_stream(&self, file_path: &Path) -> Result<Stream, Box<dyn Error>>;
+    /// - `Err(Box<dyn Error>)`: If creating the stream fails.
+    fn create_audio_stream(
+        &self,
+        audio: Arc<DecodedAudio>,
+        handle: Arc<PlaybackHandle>,
+    ) -> Result<Stream, Box<dyn Error>>;
 }
 
 /// **AudioFileLoader Trait**
 ///
-/// Provides functionality to load audio samples from a file into a `Vec<f32>` along with the sample rate.
+/// Provides functionality to load audio samples from a file into a `Vec<f32>` along with the
+/// channel count and sample rate.
 pub trait AudioFileLoader {
-    /// Loads audio samples, sample rate, and channel count from a file.
+    /// Loads audio samples, channel count and sample rate from a file.
     ///
     /// # Parameters:
     /// - `path`: The path to the audio file.
     ///
     /// # Returns:
-    /// - `Ok((Vec<f32>, u32, u16))`: On success, returns the decoded samples, sample rate, and channels.
+    /// - `Ok(DecodedAudio)`: On success, returns the decoded samples, channels and sample rate.
     /// - `Err(Box<dyn Error>)`: On failure, returns an error.
-    fn load_samples(&self, path: &Path) -> Result<(Vec<f32>, u16), Box<dyn Error>>;
+    fn load_samples(&self, path: &Path) -> Result<DecodedAudio, Box<dyn Error>>;
 }
 
 /// **DynamicAudioLoader Struct**
 ///
-/// This struct selects the appropriate loader (e.g., MP3 or WAV) based on the file extension
+/// This struct selects the appropriate loader (e.g., MP3, FLAC or WAV) based on the file extension
 /// and creates a playback stream using `cpal`.
 pub struct DynamicAudioLoader;
 
 impl AudioLoader for DynamicAudioLoader {
-    /// Creates an audio stream based on the file type (MP3 or WAV).
-    ///
-    /// This method selects the correct loader, loads the audio samples, and builds a `cpal::Stream`.
+    /// Decodes the file with the loader matching its type (MP3, FLAC or WAV).
     ///
     /// # Parameters:
     /// - `file_path`: The path to the audio file.
     ///
     /// # Returns:
+    /// - `Ok(DecodedAudio)`: The decoded samples, channel count and sample rate.
+    /// - `Err(Box<dyn Error>)`: If the format is unsupported or the file loading fails.
+    fn decode(&self, file_path: &Path) -> Result<DecodedAudio, Box<dyn Error>> {
+        let extension = (file_path.extension().and_then(|e| e.to_str()))
+            .unwrap_or("")
+            .to_lowercase();
+
+        // Load the samples, channel count and sample rate using the appropriate loader.
+        match extension.as_str() {
+            "wav" => WavLoader.load_samples(file_path),
+            "mp3" => Mp3Loader.load_samples(file_path),
+            "flac" => FlacLoader.load_samples(file_path),
+            _ => Err("Unsupported audio format".into()),
+        }
+    }
+
+    /// Creates an audio stream for already decoded audio.
+    ///
+    /// This method builds a `cpal::Stream` on the default output device that renders the
+    /// samples through the playback handle. The stream runs at the device's sample rate, the
+    /// handle converts from the file's rate while rendering.
+    ///
+    /// # Parameters:
+    /// - `audio`: The decoded audio to play.
+    /// - `handle`: The shared handle the stream renders through (gain ramps, position).
+    ///
+    /// # Returns:
     /// - `Ok(Stream)`: A ready-to-use stream for playback.
-    /// - `Err(Box<dyn Error>)`: If the file loading or stream creation fails.
-    fn create_audio_stream(&self, file_path: &Path) -> Result<Stream, Box<dyn Error>> {
+    /// - `Err(Box<dyn Error>)`: If the stream creation fails.
+    fn create_audio_stream(
+        &self,
+        audio: Arc<DecodedAudio>,
+        handle: Arc<PlaybackHandle>,
+    ) -> Result<Stream, Box<dyn Error>> {
         let host = cpal::default_host();
         let device = host
             .default_output_device()
             .ok_or("No output device available")?;
 
         let config = device.default_output_config()?;
-        let extension = file_path.extension().and_then(|e| e.to_str()).unwrap_or("");
-
-        // Load the samples, sample rate, and channel count using the appropriate loader.
-        let (samples, channels) = match extension {
-            "wav" => WavLoader.load_samples(file_path)?,
-            "mp3" => Mp3Loader.load_samples(file_path)?,
-            _ => return Err("Unsupported audio format".into()),
-        };
-
-        // Here we should add more logics for mp3 files with stereo or mono, or I don't know more
-        // But in some cases playback speed doesn't work well
-        // let mut prefered_channels = channels;
-        // let prefered_channels = config.channels();
-        // Set prefered_channels to config.channels() if sample rates differ. Due to a few formats are not playing correctly in mp3 files.
-        // if config.sample_rate().0 != sample_rate {
-        //     eprintln!(
-        //         "[WARNING] Sample rate mismatch: device {} Hz, file {} Hz. Using the device default output config.",
-        //         config.sample_rate().0,
-        //         sample_rate
-        //     );
-        // In a more advenced programm we should add some logic to convert the rate here
-        // prefered_channels = config.channels();
-        // }
+        let channels = audio.channels;
 
         // Error callback function for handling stream errors.
-        let err_fn = |err| eprintln!("An error occurred on the output stream: {}", err);
+        let err_fn = |err| error!("An error occurred on the output stream: {}", err);
 
-        // Track the current sample index during playback.
-        let mut sample_index = 0;
+        // The handle tracks the playback position in frames of the file and applies fades in
+        // output frames, so it needs both rates.
+        let output_channels = config.channels() as usize;
+        handle.set_format(
+            audio.sample_rate,
+            config.sample_rate().0,
+            audio.samples.len() / channels.max(1) as usize,
+        );
 
         match config.sample_format() {
             cpal::SampleFormat::F32 => device
                 .build_output_stream(
                     &config.config(),
                     move |data: &mut [f32], _| {
-                        for frame in data.chunks_mut(config.channels() as usize) {
-                            if sample_index + channels as usize <= samples.len() {
-                                for (i, sample) in frame.iter_mut().enumerate() {
-                                    *sample = samples[sample_index + i % channels as usize];
-                                }
-                                sample_index += channels as usize;
-                            } else {
-                                frame.fill(0.0);
-                            }
-                        }
+                        handle.render(data, output_channels, &audio.samples, channels as usize);
                     },
                     err_fn,
                     None,
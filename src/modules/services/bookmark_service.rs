@@ -0,0 +1,193 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use log::{info, warn};
+
+use crate::modules::models::bookmark_model::BookmarkModel;
+use crate::modules::services::state_file::{self, StateFolder};
+
+/// Environment variable with the path of the bookmarks file.
+pub const BOOKMARKS_FILE_VAR: &str = "SENSIT_BOOKMARKS";
+
+/// Environment variable with the length in minutes from which files resume (`0` = never).
+pub const RESUME_AFTER_VAR: &str = "SENSIT_RESUME_AFTER";
+
+/// Files at least this long resume unless `SENSIT_RESUME_AFTER` says otherwise.
+pub const DEFAULT_RESUME_AFTER: Duration = Duration::from_secs(20 * 60);
+
+/// First line of a bookmarks file, the version changes when the columns do.
+const BOOKMARKS_HEADER: &str = "# sensit bookmarks v1";
+
+/// **BookmarkService Struct**
+///
+/// Keeps the bookmarks and resume points in a single file, one tab-separated line each:
+/// `bookmark <path> <milliseconds> <name>` and `resume <path> <milliseconds>`.
+pub struct BookmarkService {
+    /// The bookmarks file.
+    path: PathBuf,
+    /// Files at least this long resume, `None` = never.
+    resume_after: Option<Duration>,
+}
+
+impl BookmarkService {
+    /// Creates a service that keeps the bookmarks in `path`.
+    ///
+    /// # Parameters:
+    /// - `resume_after`: Handed to the loaded `BookmarkModel`, files at least this long
+    ///   resume from where they were left. `None` turns resuming off.
+    pub fn new(path: PathBuf, resume_after: Option<Duration>) -> Self {
+        BookmarkService { path, resume_after }
+    }
+
+    /// Uses the file named by `SENSIT_BOOKMARKS`, or `sensit/bookmarks.tsv` in the user's
+    /// data folder (`$XDG_DATA_HOME`, `~/.local/share`), or `bookmarks.tsv` in the working
+    /// folder. Files resume from `SENSIT_RESUME_AFTER` minutes on (20 by default, `0` = never).
+    pub fn from_env() -> Self {
+        let path = state_file::path(BOOKMARKS_FILE_VAR, StateFolder::Data, "bookmarks.tsv");
+        let resume_after = match env::var(RESUME_AFTER_VAR) {
+            Ok(minutes) => match minutes.trim().parse::<f64>() {
+                Ok(minutes) if minutes > 0.0 => Duration::try_from_secs_f64(minutes * 60.0).ok(),
+                Ok(_) => None,
+                Err(_) => {
+                    warn!("Ignoring {}={:?}, not a number.", RESUME_AFTER_VAR, minutes);
+                    Some(DEFAULT_RESUME_AFTER)
+                }
+            },
+            Err(_) => Some(DEFAULT_RESUME_AFTER),
+        };
+        Self::new(path, resume_after)
+    }
+
+    /// Returns the length from which files resume, `None` if resuming is off.
+    pub fn resume_after(&self) -> Option<Duration> {
+        self.resume_after
+    }
+
+    /// **Load the Bookmarks**
+    ///
+    /// # Returns:
+    /// - `Ok(BookmarkModel)`: The saved bookmarks and resume points, empty if there is no
+    ///   bookmarks file yet. Lines that cannot be parsed are logged and skipped.
+    /// - `Err(Box<dyn Error>)`: If the file exists but cannot be read or has another version.
+    pub fn load(&self) -> Result<BookmarkModel, Box<dyn Error>> {
+        let mut bookmarks = BookmarkModel::new(self.resume_after);
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(bookmarks),
+            Err(err) => return Err(err.into()),
+        };
+        let mut lines = text.lines();
+        if lines.next() != Some(BOOKMARKS_HEADER) {
+            return Err(format!("{:?} is not a bookmarks file", self.path.display()).into());
+        }
+        for (number, line) in lines.enumerate() {
+            let columns: Vec<String> = line.split('\t').map(state_file::unescape).collect();
+            let position = |column: &String| column.parse().ok().map(Duration::from_millis);
+            match columns.as_slice() {
+                [kind, path, millis, name] if kind == "bookmark" => {
+                    if let Some(position) = position(millis) {
+                        bookmarks.add(Path::new(path), Some(name.clone()), position);
+                        continue;
+                    }
+                }
+                [kind, path, millis] if kind == "resume" => {
+                    if let Some(position) = position(millis) {
+                        bookmarks.set_resume_point(PathBuf::from(path), position);
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            warn!("Skipping broken bookmarks line {}.", number + 2);
+        }
+        info!(
+            "Loaded {} bookmarks from {:?}.",
+            bookmarks.all().count(),
+            self.path.display()
+        );
+        Ok(bookmarks)
+    }
+
+    /// **Save the Bookmarks**
+    ///
+    /// Writes a temporary file next to the bookmarks file and renames it over the old one,
+    /// so an interrupted save keeps the previous bookmarks.
+    pub fn save(&self, bookmarks: &BookmarkModel) -> Result<(), Box<dyn Error>> {
+        let path = |path: &Path| state_file::escape(&path.to_string_lossy());
+        let mut text = String::from(BOOKMARKS_HEADER);
+        text.push('\n');
+        for (file, bookmark) in bookmarks.all() {
+            text.push_str(&format!(
+                "bookmark\t{}\t{}\t{}\n",
+                path(file),
+                bookmark.position.as_millis(),
+                state_file::escape(&bookmark.name)
+            ));
+        }
+        for (file, position) in bookmarks.resume_points() {
+            text.push_str(&format!(
+                "resume\t{}\t{}\n",
+                path(file),
+                position.as_millis()
+            ));
+        }
+
+        state_file::write(&self.path, &text)?;
+        info!("Saved the bookmarks to {:?}.", self.path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::services::temp_folder::TempFolder;
+
+    #[test]
+    fn bookmarks_and_resume_points_survive_a_round_trip() {
+        let folder = TempFolder::new("bookmarks-save");
+        let service = BookmarkService::new(folder.path().join("bookmarks.tsv"), None);
+        let mut bookmarks = service.load().unwrap();
+        assert_eq!(bookmarks.all().count(), 0, "no file yet");
+        let file = Path::new("/m/tab\there.mp3");
+        bookmarks.add(
+            file,
+            Some("Chapter\t2\nend".to_string()),
+            Duration::from_millis(90_500),
+        );
+        bookmarks.add(file, None, Duration::from_secs(30));
+        bookmarks.set_resume_point(file.to_path_buf(), Duration::from_millis(61_001));
+        service.save(&bookmarks).unwrap();
+
+        let service = BookmarkService::new(service.path.clone(), Some(DEFAULT_RESUME_AFTER));
+        let loaded = service.load().unwrap();
+        assert_eq!(loaded.bookmarks(file), bookmarks.bookmarks(file));
+        assert_eq!(
+            loaded.resume_points().collect::<Vec<_>>(),
+            [(file, Duration::from_millis(61_001))]
+        );
+        assert_eq!(loaded.resume_after(), Some(DEFAULT_RESUME_AFTER));
+    }
+
+    #[test]
+    fn broken_lines_are_skipped() {
+        let folder = TempFolder::new("bookmarks-broken");
+        let path = folder.write(
+            "bookmarks.tsv",
+            format!(
+                "{}\nbookmark\t/m/a.mp3\t1000\tIntro\nbookmark\t/m/a.mp3\tsoon\tLater\n\
+                 resume\t/m/a.mp3\nunknown\t/m/a.mp3\t5\n",
+                BOOKMARKS_HEADER
+            ),
+        );
+        let loaded = BookmarkService::new(path, None).load().unwrap();
+        assert_eq!(loaded.all().count(), 1);
+        assert_eq!(loaded.resume_points().count(), 0);
+
+        let other = folder.write("other.tsv", "# sensit session v1\n");
+        assert!(BookmarkService::new(other, None).load().is_err());
+    }
+}
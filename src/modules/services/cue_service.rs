@@ -0,0 +1,285 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use log::{info, warn};
+
+use crate::modules::models::cue_model::{CueFile, CueTrack};
+
+/// CUE sheet times are `mm:ss:ff` with 75 frames per second (CD sectors).
+const CUE_FRAMES_PER_SECOND: u64 = 75;
+
+/// **CueService Struct**
+///
+/// Reads CUE sheets, which split one large audio file (a whole album rip) into tracks.
+pub struct CueService;
+
+impl CueService {
+    /// **Find the CUE Sheets of Audio Files**
+    ///
+    /// Reads every `.cue` file in the folders of `files`.
+    ///
+    /// # Parameters:
+    /// - `files`: The audio files about to be played.
+    ///
+    /// # Returns:
+    /// - The `CueFile`s of all sheets found. Sheets that cannot be read are logged and skipped.
+    pub fn sheets_for(&self, files: &[PathBuf]) -> Vec<CueFile> {
+        let folders: HashSet<&Path> = files.iter().filter_map(|file| file.parent()).collect();
+        let mut sheets = Vec::new();
+        for folder in folders {
+            let Ok(entries) = fs::read_dir(folder) else {
+                continue;
+            };
+            for path in entries.flatten().map(|entry| entry.path()) {
+                let is_cue = path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| e.eq_ignore_ascii_case("cue"));
+                if !is_cue {
+                    continue;
+                }
+                match self.load(&path) {
+                    Ok(cue_files) => sheets.extend(cue_files),
+                    Err(err) => warn!("Skipping CUE sheet {:?}: {}", path.display(), err),
+                }
+            }
+        }
+        sheets
+    }
+
+    /// **Load a CUE Sheet**
+    ///
+    /// # Parameters:
+    /// - `path`: The path to the `.cue` file.
+    ///
+    /// # Returns:
+    /// - `Ok(Vec<CueFile>)`: The audio files of the sheet with their tracks. Tracks without
+    ///   an `INDEX 01` are left out.
+    /// - `Err(Box<dyn Error>)`: If the sheet cannot be read.
+    pub fn load(&self, path: &Path) -> Result<Vec<CueFile>, Box<dyn Error>> {
+        // CUE sheets written by older rippers are Latin-1 rather than UTF-8.
+        let text = match String::from_utf8(fs::read(path)?) {
+            Ok(text) => text,
+            Err(err) => err.into_bytes().iter().map(|&b| b as char).collect(),
+        };
+        let folder = path.parent().unwrap_or(Path::new(""));
+
+        let mut files: Vec<CueFile> = Vec::new();
+        let mut album_performer: Option<String> = None;
+        let mut in_track = false;
+        for line in text.trim_start_matches('\u{feff}').lines().map(str::trim) {
+            let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            match command.to_uppercase().as_str() {
+                "FILE" => {
+                    // FILE "name" TYPE, the quotes are optional for names without spaces.
+                    let name = match Self::quoted(rest) {
+                        Some(name) => name,
+                        None => rest
+                            .rsplit_once(' ')
+                            .map_or(rest, |(name, _)| name)
+                            .to_string(),
+                    };
+                    files.push(CueFile {
+                        path: Self::resolve_file(folder, &name),
+                        tracks: Vec::new(),
+                    });
+                    in_track = false;
+                }
+                "TRACK" => {
+                    let mut words = rest.split_whitespace();
+                    let number = words.next().and_then(|n| n.parse().ok()).unwrap_or(0);
+                    let is_audio = words
+                        .next()
+                        .is_some_and(|t| t.eq_ignore_ascii_case("AUDIO"));
+                    in_track = false;
+                    if let (Some(file), true) = (files.last_mut(), is_audio) {
+                        file.tracks.push(CueTrack {
+                            number,
+                            title: None,
+                            performer: None,
+                            start: Duration::MAX, // Replaced by INDEX 01.
+                            end: None,
+                        });
+                        in_track = true;
+                    }
+                }
+                "TITLE" | "PERFORMER" => {
+                    let value = Self::quoted(rest).unwrap_or_else(|| rest.to_string());
+                    let track = files.last_mut().and_then(|file| file.tracks.last_mut());
+                    match (command.to_uppercase().as_str(), in_track, track) {
+                        ("TITLE", true, Some(track)) => track.title = Some(value),
+                        ("PERFORMER", true, Some(track)) => track.performer = Some(value),
+                        ("PERFORMER", false, _) => album_performer = Some(value),
+                        _ => {} // The album title.
+                    }
+                }
+                "INDEX" => {
+                    let mut words = rest.split_whitespace();
+                    let is_start = words.next().and_then(|n| n.parse::<u32>().ok()) == Some(1);
+                    let time = words.next().and_then(Self::parse_time);
+                    let track = files.last_mut().and_then(|file| file.tracks.last_mut());
+                    if let (true, Some(time), true, Some(track)) = (is_start, time, in_track, track)
+                    {
+                        track.start = time;
+                    }
+                }
+                _ => {} // REM, CATALOG, FLAGS, ...
+            }
+        }
+
+        for file in &mut files {
+            file.tracks.retain(|track| track.start != Duration::MAX);
+            for i in 0..file.tracks.len() {
+                file.tracks[i].end = file.tracks.get(i + 1).map(|next| next.start);
+                if file.tracks[i].performer.is_none() {
+                    file.tracks[i].performer = album_performer.clone();
+                }
+            }
+        }
+        files.retain(|file| !file.tracks.is_empty());
+        info!(
+            "Loaded CUE sheet {:?} ({} tracks).",
+            path.display(),
+            files.iter().map(|file| file.tracks.len()).sum::<usize>()
+        );
+        Ok(files)
+    }
+
+    /// Returns the text between the first and the last double quote, if quoted.
+    fn quoted(text: &str) -> Option<String> {
+        let start = text.find('"')?;
+        let end = text.rfind('"')?;
+        (end > start).then(|| text[start + 1..end].to_string())
+    }
+
+    /// Parses a CUE time (`mm:ss:ff`).
+    fn parse_time(text: &str) -> Option<Duration> {
+        let mut parts = text.split(':').map(|part| part.parse::<u64>().ok());
+        let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+        let frames = (minutes * 60 + seconds) * CUE_FRAMES_PER_SECOND + frames;
+        Some(Duration::from_secs_f64(
+            frames as f64 / CUE_FRAMES_PER_SECOND as f64,
+        ))
+    }
+
+    /// Resolves the `FILE` of a sheet against the sheet's folder. Rips are often converted
+    /// after the sheet was written (`album.wav` -> `album.flac`), so a missing file is
+    /// looked up with the supported extensions as well.
+    fn resolve_file(folder: &Path, name: &str) -> PathBuf {
+        let relative: PathBuf = Path::new(name)
+            .components()
+            .filter(|component| !matches!(component, Component::CurDir))
+            .collect();
+        let path = folder.join(relative);
+        if path.is_file() {
+            return path;
+        }
+        ["flac", "wav", "mp3"]
+            .iter()
+            .map(|extension| path.with_extension(extension))
+            .find(|candidate| candidate.is_file())
+            .unwrap_or(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::services::temp_folder::TempFolder;
+
+    /// Creates a temporary folder holding `sheet` as `album.cue` and the given (empty)
+    /// audio files.
+    fn fixture(name: &str, sheet: &[u8], files: &[&str]) -> TempFolder {
+        let folder = TempFolder::new(&format!("cue-{}", name));
+        for file in files {
+            folder.write(file, b"");
+        }
+        folder.write("album.cue", sheet);
+        folder
+    }
+
+    #[test]
+    fn sheet_splits_file_into_tracks() {
+        let sheet = "\u{feff}REM GENRE Jazz\nPERFORMER \"The Band\"\nTITLE \"Live\"\nFILE \"album.flac\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"Intro\"\n    INDEX 00 00:00:00\n    INDEX 01 00:00:32\n  TRACK 02 AUDIO\n    TITLE \"Song\"\n    PERFORMER \"Guest\"\n    INDEX 01 01:02:37\n  TRACK 03 DATA\n    INDEX 01 02:00:00\n  TRACK 04 AUDIO\n    TITLE \"No index\"\n  TRACK 05 AUDIO\n    INDEX 01 03:15:74\n";
+        let temp = fixture("split", sheet.as_bytes(), &["album.flac"]);
+        let folder = temp.path();
+        let files = CueService.load(&folder.join("album.cue")).unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, folder.join("album.flac"));
+        let tracks = &files[0].tracks;
+        let numbers: Vec<u32> = tracks.iter().map(|track| track.number).collect();
+        assert_eq!(numbers, vec![1, 2, 5]);
+
+        assert_eq!(tracks[0].title.as_deref(), Some("Intro"));
+        assert_eq!(tracks[0].performer.as_deref(), Some("The Band"));
+        assert_eq!(tracks[0].start, Duration::from_secs_f64(32.0 / 75.0));
+        assert_eq!(tracks[0].end, Some(tracks[1].start));
+
+        assert_eq!(tracks[1].performer.as_deref(), Some("Guest"));
+        assert_eq!(tracks[1].start, Duration::from_secs_f64(62.0 + 37.0 / 75.0));
+        assert_eq!(tracks[1].end, Some(tracks[2].start));
+
+        assert_eq!(tracks[2].title, None);
+        assert_eq!(
+            tracks[2].start,
+            Duration::from_secs_f64(195.0 + 74.0 / 75.0)
+        );
+        assert_eq!(tracks[2].end, None);
+    }
+
+    #[test]
+    fn sheet_with_several_files_and_latin1_text() {
+        let mut sheet = b"FILE side_a.wav WAVE\nTRACK 01 AUDIO\nTITLE \"Caf".to_vec();
+        sheet.push(0xe9); // `é` in Latin-1.
+        sheet.extend_from_slice(b"\"\nINDEX 01 00:00:00\nFILE \"side b.wav\" WAVE\nTRACK 02 AUDIO\nINDEX 01 00:00:00\nTRACK 03 AUDIO\nINDEX 01 04:00:00\nFILE empty.wav WAVE\n");
+        let temp = fixture("files", &sheet, &["side_a.wav", "side b.wav", "empty.wav"]);
+        let folder = temp.path();
+        let files = CueService.load(&folder.join("album.cue")).unwrap();
+
+        assert_eq!(files.len(), 2, "files without tracks are left out");
+        assert_eq!(files[0].path, folder.join("side_a.wav"));
+        assert_eq!(files[0].tracks[0].title.as_deref(), Some("Café"));
+        assert_eq!(
+            files[0].tracks[0].end, None,
+            "tracks end with their own file"
+        );
+        assert_eq!(files[1].path, folder.join("side b.wav"));
+        assert_eq!(files[1].tracks.len(), 2);
+        assert_eq!(files[1].tracks[0].end, Some(Duration::from_secs(240)));
+    }
+
+    #[test]
+    fn converted_rips_are_found_with_other_extensions() {
+        let sheet = b"FILE \"album.wav\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n";
+        let temp = fixture("converted", sheet, &["album.flac"]);
+        let folder = temp.path();
+        let files = CueService.load(&folder.join("album.cue")).unwrap();
+        assert_eq!(files[0].path, folder.join("album.flac"));
+
+        let found = CueService.sheets_for(&[folder.join("album.flac")]);
+        assert_eq!(found, files);
+    }
+
+    #[test]
+    fn times_are_minutes_seconds_and_frames() {
+        assert_eq!(
+            CueService::parse_time("00:01:00"),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            CueService::parse_time("61:00:00"),
+            Some(Duration::from_secs(3660))
+        );
+        assert_eq!(
+            CueService::parse_time("00:00:75"),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(CueService::parse_time("00:01"), None);
+        assert_eq!(CueService::parse_time("aa:00:00"), None);
+    }
+}
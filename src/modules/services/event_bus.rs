@@ -0,0 +1,46 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+use crate::modules::models::player_event_model::Event;
+
+/// **EventBus Struct**
+///
+/// Fans player `Event`s out to any number of subscribers. Each subscriber gets its own
+/// channel, so a slow listener (scrobbler, notifier, logger) never blocks the player, and
+/// subscribers that dropped their receiver are removed on the next publish.
+pub struct EventBus {
+    subscribers: Mutex<Vec<Sender<Event>>>,
+}
+
+impl EventBus {
+    /// Creates a bus without subscribers.
+    pub fn new() -> Self {
+        EventBus {
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Registers a new subscriber.
+    ///
+    /// # Returns:
+    /// - A `Receiver` that gets every event published from now on.
+    pub fn subscribe(&self) -> Receiver<Event> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Sends `event` to every subscriber, dropping the ones that went away.
+    pub fn publish(&self, event: Event) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,25 @@
+use std::error::Error;
+use std::path::Path;
+
+use super::audio_loader::{AudioFileLoader, DecodedAudio};
+use super::symphonia_loader;
+
+/// **FlacLoader Struct**
+///
+/// This struct loads FLAC files through symphonia, extracting interleaved samples and the
+/// channel count.
+pub struct FlacLoader;
+
+impl AudioFileLoader for FlacLoader {
+    /// Loads audio samples, channel count and sample rate from a FLAC file.
+    ///
+    /// # Parameters
+    /// - path: The path to the FLAC file.
+    ///
+    /// # Returns
+    /// - Ok(DecodedAudio): The samples of all channels, interleaved, the channel count and sample rate.
+    /// - Err(Box<dyn Error>): An error if the file cannot be opened or decoded.
+    fn load_samples(&self, path: &Path) -> Result<DecodedAudio, Box<dyn Error>> {
+        symphonia_loader::decode(path, "flac")
+    }
+}
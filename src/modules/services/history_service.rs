@@ -0,0 +1,246 @@
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+use log::{info, warn};
+
+use crate::modules::models::history_model::{HistoryModel, PlayRecord};
+use crate::modules::services::state_file::{self, StateFolder};
+
+/// Environment variable with the path of the history file.
+pub const HISTORY_FILE_VAR: &str = "SENSIT_HISTORY";
+
+/// First line of a history file, the version changes when the columns do.
+const HISTORY_HEADER: &str = "# sensit history v1";
+
+/// **HistoryService Struct**
+///
+/// Keeps the play history in a file that only grows: every playback is appended as one
+/// tab-separated line with its start time, listened milliseconds, `played` or `skipped`,
+/// and the path of the track. Renamed tracks are followed in memory and written to the
+/// file by `save`.
+#[derive(Clone)]
+pub struct HistoryService {
+    /// The history file.
+    path: PathBuf,
+    /// Renames not written to the file yet, shared by the clones of the service. Also held
+    /// while the file is written, so an append never races a rewrite.
+    renames: Arc<Mutex<Vec<(PathBuf, PathBuf)>>>,
+}
+
+impl HistoryService {
+    /// Creates a service that keeps the history in `path`.
+    pub fn new(path: PathBuf) -> Self {
+        HistoryService {
+            path,
+            renames: Arc::default(),
+        }
+    }
+
+    /// Uses the file named by `SENSIT_HISTORY`, or `sensit/history.tsv` in the user's data
+    /// folder (`$XDG_DATA_HOME`, `~/.local/share`), or `history.tsv` in the working folder.
+    pub fn from_env() -> Self {
+        let path = state_file::path(HISTORY_FILE_VAR, StateFolder::Data, "history.tsv");
+        Self::new(path)
+    }
+
+    /// **Load the History**
+    ///
+    /// # Returns:
+    /// - `Ok(HistoryModel)`: Every recorded playback, empty if nothing was played yet, with
+    ///   the renames not saved yet applied. Lines that cannot be parsed are logged and
+    ///   skipped.
+    /// - `Err(Box<dyn Error>)`: If the file exists but cannot be read or has another version.
+    pub fn load(&self) -> Result<HistoryModel, Box<dyn Error>> {
+        let renames = self.renames.lock().unwrap();
+        let mut history = self.read()?;
+        for (from, to) in renames.iter() {
+            history.rename(from, to);
+        }
+        Ok(history)
+    }
+
+    /// Reads the history file as it is.
+    fn read(&self) -> Result<HistoryModel, Box<dyn Error>> {
+        let mut history = HistoryModel::default();
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(history),
+            Err(err) => return Err(err.into()),
+        };
+        let mut lines = text.lines();
+        if lines.next() != Some(HISTORY_HEADER) {
+            return Err(format!("{:?} is not a history file", self.path.display()).into());
+        }
+        for (number, line) in lines.enumerate() {
+            match Self::parse_record(line) {
+                Some(record) => history.record(record),
+                None => warn!("Skipping broken history line {}.", number + 2),
+            }
+        }
+        info!(
+            "Loaded {} playbacks from {:?}.",
+            history.len(),
+            self.path.display()
+        );
+        Ok(history)
+    }
+
+    /// **Record a Playback**
+    ///
+    /// Appends `record` to the history file, creating the file (and its folder) first if
+    /// needed.
+    pub fn append(&self, record: &PlayRecord) -> Result<(), Box<dyn Error>> {
+        let _renames = self.renames.lock().unwrap(); // Not while `save` rewrites the file.
+        if let Some(folder) = self.path.parent().filter(|f| !f.as_os_str().is_empty()) {
+            fs::create_dir_all(folder)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", HISTORY_HEADER)?;
+        }
+        writeln!(file, "{}", Self::format_record(record))?;
+        Ok(())
+    }
+
+    /// **Follow a Renamed File or Folder**
+    ///
+    /// Points the recorded playbacks of a renamed file or folder to the new paths, so play
+    /// counts stay with the tracks. Only remembered here, `load` applies it right away and
+    /// `save` writes it to the file. Renames of hidden files (the temporary files of tag
+    /// writes and other tools) are ignored, they were never played.
+    pub fn rename(&self, from: &Path, to: &Path) {
+        let hidden = from
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if !hidden {
+            (self.renames.lock().unwrap()).push((from.to_path_buf(), to.to_path_buf()));
+        }
+    }
+
+    /// **Save the History**
+    ///
+    /// Writes the renames since the last save to the history file, rewriting it through a
+    /// temporary file only if a playback changed.
+    ///
+    /// # Returns:
+    /// - `Ok(usize)`: The number of playbacks changed.
+    /// - `Err(Box<dyn Error>)`: If the history cannot be read or written. The renames are
+    ///   kept for the next save.
+    pub fn save(&self) -> Result<usize, Box<dyn Error>> {
+        let mut renames = self.renames.lock().unwrap();
+        if renames.is_empty() {
+            return Ok(0);
+        }
+        let mut history = self.read()?;
+        let renamed: usize = (renames.iter())
+            .map(|(from, to)| history.rename(from, to))
+            .sum();
+        if renamed == 0 {
+            renames.clear();
+            return Ok(0);
+        }
+        let mut text = format!("{}\n", HISTORY_HEADER);
+        for record in history.recent().rev() {
+            text.push_str(&Self::format_record(record));
+            text.push('\n');
+        }
+        state_file::write(&self.path, &text)?;
+        renames.clear();
+        info!(
+            "Saved {} renamed playbacks to {:?}.",
+            renamed,
+            self.path.display()
+        );
+        Ok(renamed)
+    }
+
+    /// Formats `record` as a line of the history file, without the line break.
+    fn format_record(record: &PlayRecord) -> String {
+        let started = record
+            .started
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        format!(
+            "{}.{:09}\t{}\t{}\t{}",
+            started.as_secs(),
+            started.subsec_nanos(),
+            record.listened.as_millis(),
+            if record.skipped { "skipped" } else { "played" },
+            state_file::escape(&record.path.to_string_lossy())
+        )
+    }
+
+    /// Parses a line written by `append`.
+    fn parse_record(line: &str) -> Option<PlayRecord> {
+        let columns: Vec<String> = line.split('\t').map(state_file::unescape).collect();
+        let [started, listened, outcome, path] = columns.as_slice() else {
+            return None;
+        };
+        let (secs, nanos) = started.split_once('.')?;
+        Some(PlayRecord {
+            path: PathBuf::from(path),
+            started: UNIX_EPOCH + Duration::new(secs.parse().ok()?, nanos.parse().ok()?),
+            listened: Duration::from_millis(listened.parse().ok()?),
+            skipped: match outcome.as_str() {
+                "played" => false,
+                "skipped" => true,
+                _ => return None,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::services::temp_folder::TempFolder;
+
+    #[test]
+    fn renames_rewrite_the_history() {
+        let folder = TempFolder::new("history-rename");
+        let history = HistoryService::new(folder.path().join("history.tsv"));
+        for track in ["/m/old/a.mp3", "/m/b.mp3", "/m/old/a.mp3", "/m/.a.mp3.tmp"] {
+            let record = PlayRecord {
+                path: PathBuf::from(track),
+                started: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+                listened: Duration::from_millis(1500),
+                skipped: false,
+            };
+            history.append(&record).unwrap();
+        }
+
+        let paths = |history: &HistoryService| -> Vec<PathBuf> {
+            (history.load().unwrap().recent())
+                .map(|record| record.path.clone())
+                .collect()
+        };
+        let before = ["/m/.a.mp3.tmp", "/m/old/a.mp3", "/m/b.mp3", "/m/old/a.mp3"]; // Newest first.
+        let after = ["/m/.a.mp3.tmp", "/m/new/a.mp3", "/m/b.mp3", "/m/new/a.mp3"];
+
+        history.rename(Path::new("/m/x"), Path::new("/m/y"));
+        history.rename(Path::new("/m/.a.mp3.tmp"), Path::new("/m/b.mp3"));
+        history.rename(Path::new("/m/old"), Path::new("/m/new"));
+        assert_eq!(
+            paths(&history),
+            after.map(PathBuf::from),
+            "applied in memory"
+        );
+        let reopened = HistoryService::new(folder.path().join("history.tsv"));
+        assert_eq!(
+            paths(&reopened),
+            before.map(PathBuf::from),
+            "not written yet"
+        );
+
+        assert_eq!(history.save().unwrap(), 2);
+        assert_eq!(paths(&reopened), after.map(PathBuf::from));
+        assert_eq!(history.save().unwrap(), 0, "nothing left to write");
+    }
+}
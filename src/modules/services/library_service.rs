@@ -0,0 +1,463 @@
+use std::collections::HashSet;
+use std::env;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use log::{info, warn};
+
+use crate::modules::models::library_model::{FileUpdate, LibraryEntry, LibraryModel, ScanSummary};
+use crate::modules::models::track_metadata_model::{TagField, TrackMetadata};
+use crate::modules::services::audio_folder_service::AudioFolderService;
+use crate::modules::services::metadata_service::MetadataService;
+use crate::modules::services::state_file::{self, StateFolder};
+use crate::modules::services::tag_service::TagService;
+
+/// Environment variable with the path of the library file.
+pub const LIBRARY_FILE_VAR: &str = "SENSIT_LIBRARY";
+
+/// Environment variable that makes ratings also be written to the tags of the files.
+pub const WRITE_RATINGS_VAR: &str = "SENSIT_WRITE_RATINGS";
+
+/// First line of a library file, the version changes when the columns do.
+const LIBRARY_HEADER: &str = "# sensit library v1";
+
+/// **LibraryService Struct**
+///
+/// Keeps the music library in a single file: one line per audio file with its path,
+/// size, modification time, format, duration, tags, rating and favourite star,
+/// tab-separated. Rescanning a folder only reads the tags of files that are new or
+/// changed since the last scan.
+pub struct LibraryService {
+    /// The library file.
+    path: PathBuf,
+    /// Ratings are also written to the tags of the files.
+    write_ratings: bool,
+}
+
+impl LibraryService {
+    /// Creates a service that keeps the library in `path`.
+    ///
+    /// # Parameters:
+    /// - `write_ratings`: Also write ratings to the files (ID3 `POPM`, `FMPS_RATING`).
+    pub fn new(path: PathBuf, write_ratings: bool) -> Self {
+        LibraryService {
+            path,
+            write_ratings,
+        }
+    }
+
+    /// Uses the file named by `SENSIT_LIBRARY`, or `sensit/library.tsv` in the user's data
+    /// folder (`$XDG_DATA_HOME`, `~/.local/share`), or `library.tsv` in the working folder.
+    /// Ratings are written to the files too if `SENSIT_WRITE_RATINGS` is set (`1`, `yes`).
+    pub fn from_env() -> Self {
+        let path = state_file::path(LIBRARY_FILE_VAR, StateFolder::Data, "library.tsv");
+        let write_ratings = env::var(WRITE_RATINGS_VAR).is_ok_and(|value| {
+            matches!(
+                value.trim().to_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        });
+        Self::new(path, write_ratings)
+    }
+
+    /// **Load the Library**
+    ///
+    /// # Returns:
+    /// - `Ok(LibraryModel)`: The saved library, empty if there is no library file yet.
+    ///   Lines that cannot be parsed are logged and skipped.
+    /// - `Err(Box<dyn Error>)`: If the file exists but cannot be read or has another version.
+    pub fn load(&self) -> Result<LibraryModel, Box<dyn Error>> {
+        let mut library = LibraryModel::default();
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(library),
+            Err(err) => return Err(err.into()),
+        };
+        let mut lines = text.lines();
+        if lines.next() != Some(LIBRARY_HEADER) {
+            return Err(format!("{:?} is not a library file", self.path.display()).into());
+        }
+        for (number, line) in lines.enumerate() {
+            match Self::parse_entry(line) {
+                Some(entry) => library.insert(entry),
+                None => warn!("Skipping broken library line {}.", number + 2),
+            }
+        }
+        info!(
+            "Loaded {} library entries from {:?}.",
+            library.len(),
+            self.path.display()
+        );
+        Ok(library)
+    }
+
+    /// **Save the Library**
+    ///
+    /// Writes a temporary file next to the library file and renames it over the old one,
+    /// so an interrupted save keeps the previous library.
+    pub fn save(&self, library: &LibraryModel) -> Result<(), Box<dyn Error>> {
+        let mut text = String::from(LIBRARY_HEADER);
+        text.push('\n');
+        for entry in library.entries() {
+            text.push_str(&Self::format_entry(entry));
+            text.push('\n');
+        }
+        state_file::write(&self.path, &text)?;
+        info!(
+            "Saved {} library entries to {:?}.",
+            library.len(),
+            self.path.display()
+        );
+        Ok(())
+    }
+
+    /// **Scan a Folder into the Library**
+    ///
+    /// Scans `folder` with `scanner` and brings its part of the library up to date: new
+    /// files and files whose size or modification time changed are read, the others keep
+    /// their entry, entries of files that are gone are removed.
+    ///
+    /// # Parameters:
+    /// - `library`: The library to update.
+    /// - `folder`: The folder to scan, stored with its absolute path.
+    /// - `scanner`: Finds the audio files (with the user's `ScanOptions`).
+    ///
+    /// # Returns:
+    /// - What changed, see `ScanSummary`.
+    pub fn scan(
+        &self,
+        library: &mut LibraryModel,
+        folder: &Path,
+        scanner: &AudioFolderService,
+    ) -> ScanSummary {
+        let folder = fs::canonicalize(folder).unwrap_or_else(|_| folder.to_path_buf());
+        let files = scanner.load_audio_files(&folder.to_string_lossy());
+        let mut summary = ScanSummary::default();
+
+        for file in &files {
+            match self.update_file(library, file) {
+                FileUpdate::Added => summary.added += 1,
+                FileUpdate::Updated => summary.updated += 1,
+                FileUpdate::Unchanged => summary.unchanged += 1,
+                FileUpdate::Missing => {} // Gone since the scan, removed below.
+            }
+        }
+
+        let scanned: HashSet<&PathBuf> = files.iter().collect();
+        let gone: Vec<PathBuf> = library
+            .query(|entry| entry.path.starts_with(&folder) && !scanned.contains(&entry.path))
+            .into_iter()
+            .map(|entry| entry.path.clone())
+            .collect();
+        for path in gone {
+            library.remove(&path);
+            summary.removed += 1;
+        }
+        info!("Scanned {:?}: {:?}.", folder.display(), summary);
+        summary
+    }
+
+    /// **Update One File**
+    ///
+    /// Reads the tags of `file` into the library if it is new or its size or modification
+    /// time changed since it was read. A file read again keeps its favourite star, and its
+    /// rating unless the tags have one.
+    ///
+    /// # Returns:
+    /// - What was done, see `FileUpdate`. A missing file keeps its entry.
+    pub fn update_file(&self, library: &mut LibraryModel, file: &Path) -> FileUpdate {
+        let Ok(stat) = fs::metadata(file) else {
+            return FileUpdate::Missing;
+        };
+        let modified = stat.modified().ok();
+        let (update, rating, favourite) = match library.get(file) {
+            Some(entry) if entry.size == stat.len() && entry.metadata.modified == modified => {
+                return FileUpdate::Unchanged;
+            }
+            Some(entry) => (FileUpdate::Updated, entry.metadata.rating, entry.favourite),
+            None => (FileUpdate::Added, None, false),
+        };
+        let mut metadata = MetadataService.read(file);
+        metadata.rating = metadata.rating.or(rating);
+        library.insert(LibraryEntry {
+            path: file.to_path_buf(),
+            size: stat.len(),
+            format: file
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default(),
+            metadata,
+            favourite,
+        });
+        update
+    }
+
+    /// **Rate a Track**
+    ///
+    /// Sets the rating of `file` in the library (adding the file first if needed) and, if
+    /// ratings are written to files, in its tags too.
+    ///
+    /// # Parameters:
+    /// - `rating`: 1 to 5 stars, `None` removes the rating.
+    ///
+    /// # Returns:
+    /// - `Ok(())`: If the rating was stored.
+    /// - `Err(Box<dyn Error>)`: If the file does not exist, or its tags could not be written.
+    ///   The library has the new rating in the latter case.
+    pub fn rate(
+        &self,
+        library: &mut LibraryModel,
+        file: &Path,
+        rating: Option<u8>,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.update_file(library, file) == FileUpdate::Missing {
+            return Err(format!("{:?} does not exist", file.display()).into());
+        }
+        library.set_rating(file, rating);
+        if self.write_ratings {
+            let value = rating.map(|stars| stars.to_string());
+            TagService
+                .write(file, &[(TagField::Rating, value)])
+                .map_err(|err| format!("Rated in the library only: {}", err))?;
+            self.update_file(library, file); // New size and modification time.
+        }
+        Ok(())
+    }
+
+    /// **Star a Favourite**
+    ///
+    /// Stars `file` as a favourite (or unstars it) in the library, adding the file first if
+    /// needed.
+    ///
+    /// # Returns:
+    /// - `Ok(())`: If the star was set.
+    /// - `Err(Box<dyn Error>)`: If the file does not exist.
+    pub fn star(
+        &self,
+        library: &mut LibraryModel,
+        file: &Path,
+        favourite: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.update_file(library, file) == FileUpdate::Missing {
+            return Err(format!("{:?} does not exist", file.display()).into());
+        }
+        library.set_favourite(file, favourite);
+        Ok(())
+    }
+
+    /// Formats an entry as one tab-separated line.
+    fn format_entry(entry: &LibraryEntry) -> String {
+        let metadata = &entry.metadata;
+        let text =
+            |value: &Option<String>| value.as_deref().map(state_file::escape).unwrap_or_default();
+        let number = |value: Option<u32>| value.map(|n| n.to_string()).unwrap_or_default();
+        let modified = metadata
+            .modified
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|time| format!("{}.{:09}", time.as_secs(), time.subsec_nanos()))
+            .unwrap_or_default();
+        let duration = metadata
+            .duration
+            .map(|d| d.as_millis().to_string())
+            .unwrap_or_default();
+
+        let mut line = String::new();
+        let _ = write!(
+            line,
+            "{}\t{}\t{}\t{}\t{}",
+            state_file::escape(&entry.path.to_string_lossy()),
+            entry.size,
+            modified,
+            state_file::escape(&entry.format),
+            duration
+        );
+        for column in [
+            text(&metadata.title),
+            text(&metadata.artist),
+            text(&metadata.album),
+            text(&metadata.album_artist),
+            number(metadata.track_number),
+            number(metadata.disc_number),
+            number(metadata.year),
+            text(&metadata.genre),
+            number(metadata.rating.map(u32::from)),
+            String::from(if entry.favourite { "1" } else { "" }),
+        ] {
+            line.push('\t');
+            line.push_str(&column);
+        }
+        line
+    }
+
+    /// Parses a line written by `format_entry`.
+    fn parse_entry(line: &str) -> Option<LibraryEntry> {
+        let columns: Vec<String> = line.split('\t').map(state_file::unescape).collect();
+        if columns.len() != 15 {
+            return None;
+        }
+        let text = |i: usize| Some(columns[i].clone()).filter(|value| !value.is_empty());
+        let number = |i: usize| columns[i].parse::<u32>().ok();
+        let modified = match columns[2].split_once('.') {
+            Some((secs, nanos)) => {
+                Some(UNIX_EPOCH + Duration::new(secs.parse().ok()?, nanos.parse().ok()?))
+            }
+            None => None,
+        };
+        Some(LibraryEntry {
+            path: PathBuf::from(&columns[0]),
+            size: columns[1].parse().ok()?,
+            format: columns[3].clone(),
+            metadata: TrackMetadata {
+                title: text(5),
+                artist: text(6),
+                album: text(7),
+                album_artist: text(8),
+                track_number: number(9),
+                disc_number: number(10),
+                year: number(11),
+                genre: text(12),
+                rating: columns[13]
+                    .parse()
+                    .ok()
+                    .filter(|stars| (1..=5).contains(stars)),
+                duration: columns[4].parse().ok().map(Duration::from_millis),
+                modified,
+                artwork: None,
+            },
+            favourite: columns[14] == "1",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::services::audio_folder_service::ScanOptions;
+    use crate::modules::services::temp_folder::TempFolder;
+
+    fn tagged_entry() -> LibraryEntry {
+        LibraryEntry {
+            path: PathBuf::from("/music/Tab\tand\\back/01 a.mp3"),
+            size: 4_096,
+            format: String::from("mp3"),
+            metadata: TrackMetadata {
+                title: Some(String::from("Line\nbreak\tand tab")),
+                artist: Some(String::from("Artist")),
+                album: Some(String::from("Album\r")),
+                album_artist: Some(String::from("Various Artists")),
+                track_number: Some(3),
+                disc_number: Some(1),
+                year: Some(1997),
+                genre: Some(String::from("Rock")),
+                rating: Some(4),
+                duration: Some(Duration::from_millis(215_250)),
+                modified: Some(UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789)),
+                artwork: None,
+            },
+            favourite: true,
+        }
+    }
+
+    #[test]
+    fn entries_survive_a_round_trip() {
+        let entry = tagged_entry();
+        let line = LibraryService::format_entry(&entry);
+        assert_eq!(line.split('\t').count(), 15, "escaped tabs add no columns");
+        assert!(!line.contains('\n'));
+        assert_eq!(LibraryService::parse_entry(&line), Some(entry));
+
+        let untagged = LibraryEntry {
+            path: PathBuf::from("/music/b.wav"),
+            size: 0,
+            format: String::from("wav"),
+            metadata: TrackMetadata::default(),
+            favourite: false,
+        };
+        let line = LibraryService::format_entry(&untagged);
+        assert_eq!(LibraryService::parse_entry(&line), Some(untagged));
+    }
+
+    #[test]
+    fn broken_lines_are_skipped() {
+        let line = LibraryService::format_entry(&tagged_entry());
+        let (short, _) = line.rsplit_once('\t').unwrap();
+        assert_eq!(LibraryService::parse_entry(short), None, "a missing column");
+        assert_eq!(
+            LibraryService::parse_entry(&line.replacen("4096", "big", 1)),
+            None
+        );
+
+        let folder = TempFolder::new("library-broken");
+        let path = folder.write(
+            "library.tsv",
+            format!("{}\n{}\nnot an entry\n", LIBRARY_HEADER, line),
+        );
+        let library = LibraryService::new(path, false).load().unwrap();
+        assert_eq!(library.len(), 1);
+        let other = folder.write("other.tsv", "# sensit history v1\n");
+        assert!(LibraryService::new(other, false).load().is_err());
+    }
+
+    #[test]
+    fn saved_libraries_load_again() {
+        let folder = TempFolder::new("library-save");
+        let service = LibraryService::new(folder.path().join("state/library.tsv"), false);
+        assert!(service.load().unwrap().is_empty(), "no file yet");
+        let mut library = LibraryModel::default();
+        library.insert(tagged_entry());
+        service.save(&library).unwrap();
+        let loaded = service.load().unwrap();
+        assert_eq!(loaded.get(&tagged_entry().path), Some(&tagged_entry()));
+    }
+
+    #[test]
+    fn rescans_read_only_new_and_changed_files() {
+        let folder = TempFolder::new("library-scan");
+        let service = LibraryService::new(folder.path().join("library.tsv"), false);
+        let scanner = AudioFolderService::new(ScanOptions::default());
+        let music = folder.path().join("music");
+        folder.write("music/a.wav", b"first");
+        folder.write("music/sub/b.wav", b"second");
+        folder.write("music/notes.txt", b"not audio");
+        let mut library = LibraryModel::default();
+
+        let summary = service.scan(&mut library, &music, &scanner);
+        assert_eq!((summary.added, summary.unchanged), (2, 0));
+        let a = fs::canonicalize(music.join("a.wav")).unwrap();
+        library.set_rating(&a, Some(5));
+        library.set_favourite(&a, true);
+
+        let summary = service.scan(&mut library, &music, &scanner);
+        assert_eq!(
+            (summary.added, summary.updated, summary.unchanged),
+            (0, 0, 2)
+        );
+
+        folder.write("music/a.wav", b"rewritten with a new size");
+        fs::remove_file(music.join("sub/b.wav")).unwrap();
+        folder.write("music/c.wav", b"third");
+        let summary = service.scan(&mut library, &music, &scanner);
+        assert_eq!(
+            summary,
+            ScanSummary {
+                added: 1,
+                updated: 1,
+                removed: 1,
+                unchanged: 0,
+            }
+        );
+        let entry = library.get(&a).unwrap();
+        assert_eq!(entry.size, 25);
+        assert_eq!(entry.metadata.rating, Some(5), "kept, the tags have none");
+        assert!(entry.favourite);
+        assert_eq!(library.len(), 2);
+
+        assert_eq!(service.update_file(&mut library, &a), FileUpdate::Unchanged);
+        fs::remove_file(&a).unwrap();
+        assert_eq!(service.update_file(&mut library, &a), FileUpdate::Missing);
+        assert!(library.get(&a).is_some(), "a missing file keeps its entry");
+    }
+}
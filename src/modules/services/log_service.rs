@@ -0,0 +1,257 @@
+use std::cmp::Reverse;
+use std::env;
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Environment variable holding the log filter, e.g. `info,preload_service=debug`.
+pub const LOG_FILTER_VAR: &str = "SENSIT_LOG";
+
+/// Environment variable holding the path of a log file. Without it, logs go to stderr.
+pub const LOG_FILE_VAR: &str = "SENSIT_LOG_FILE";
+
+/// **LogConfig Struct**
+///
+/// Configuration of the logging layer.
+///
+/// The filter is a comma-separated list of `level` and `module=level` entries, where
+/// `module` is any part of a module path (`player_controller`, `services`,
+/// `symphonia_bundle_mp3`, ...). The most specific matching entry wins, modules without a
+/// matching entry use the bare `level` (default `warn`, so the CLI prompt stays clean).
+pub struct LogConfig {
+    pub filter: String,        // Level filter, see above.
+    pub file: Option<PathBuf>, // Append logs to this file instead of stderr.
+}
+
+impl LogConfig {
+    /// Reads the configuration from the `SENSIT_LOG` and `SENSIT_LOG_FILE` environment variables.
+    pub fn from_env() -> Self {
+        LogConfig {
+            filter: env::var(LOG_FILTER_VAR).unwrap_or_default(),
+            file: env::var_os(LOG_FILE_VAR).map(PathBuf::from),
+        }
+    }
+}
+
+/// **LogService Struct**
+///
+/// A small `log` backend: level and per-module filtering, UTC timestamps, and output to
+/// stderr or an append-only log file.
+pub struct LogService {
+    default_level: LevelFilter,
+    module_levels: Vec<(String, LevelFilter)>, // Sorted longest module name first.
+    target: Mutex<Box<dyn Write + Send>>,
+}
+
+impl LogService {
+    /// **Install the Logger**
+    ///
+    /// Builds a `LogService` from `config` and registers it as the global logger.
+    ///
+    /// # Returns:
+    /// - `Ok(())` once the logger is installed.
+    /// - `Err(Box<dyn Error>)` if the filter is invalid, the log file cannot be opened or a
+    ///   logger is already set.
+    pub fn init(config: LogConfig) -> Result<(), Box<dyn Error>> {
+        let service = Self::new(config)?;
+        let max_level = service
+            .module_levels
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(service.default_level, |a, b| a.max(b));
+
+        log::set_boxed_logger(Box::new(service))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+
+    /// Builds a `LogService` from `config` without registering it.
+    ///
+    /// # Returns:
+    /// - `Ok(LogService)` with the parsed filter and opened target.
+    /// - `Err(Box<dyn Error>)` if the filter is invalid or the log file cannot be opened.
+    fn new(config: LogConfig) -> Result<Self, Box<dyn Error>> {
+        let target: Box<dyn Write + Send> = match config.file {
+            Some(ref path) => Box::new(OpenOptions::new().create(true).append(true).open(path)?),
+            None => Box::new(io::stderr()),
+        };
+
+        let mut default_level = LevelFilter::Warn;
+        let mut module_levels = Vec::new();
+        for entry in config
+            .filter
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+        {
+            match entry.split_once('=') {
+                Some((module, level)) => match level.trim().parse() {
+                    Ok(level) => module_levels.push((module.trim().to_string(), level)),
+                    Err(_) => return Err(format!("Invalid log level in '{}'", entry).into()),
+                },
+                None => match entry.parse() {
+                    Ok(level) => default_level = level,
+                    Err(_) => return Err(format!("Invalid log level '{}'", entry).into()),
+                },
+            }
+        }
+        module_levels.sort_by_key(|(module, _): &(String, LevelFilter)| Reverse(module.len()));
+
+        Ok(LogService {
+            default_level,
+            module_levels,
+            target: Mutex::new(target),
+        })
+    }
+
+    /// Returns the level that applies to a record target (its module path).
+    /// A filter module matches whole path segments, so `services` matches
+    /// `cli_audio_player::modules::services::mp3_loader` but `mp3` does not.
+    fn level_for(&self, target: &str) -> LevelFilter {
+        let target = format!("::{}::", target);
+        self.module_levels
+            .iter()
+            .find(|(module, _)| target.contains(&format!("::{}::", module)))
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default_level)
+    }
+
+    /// Formats a time, given as the time since the Unix epoch, as an ISO-8601 UTC timestamp
+    /// with milliseconds.
+    fn timestamp(since_epoch: Duration) -> String {
+        let seconds = since_epoch.as_secs();
+        let (hour, minute, second) = ((seconds / 3600) % 24, (seconds / 60) % 60, seconds % 60);
+
+        // Convert days since 1970-01-01 into a civil date (Howard Hinnant's algorithm).
+        let days = (seconds / 86_400) as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            since_epoch.subsec_millis()
+        )
+    }
+}
+
+impl Log for LogService {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // Only the last part of the module path, the full path is mostly `cli_audio_player::modules`.
+        let module = record.target().rsplit("::").next().unwrap_or("");
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut target = self.target.lock().unwrap();
+        let _ = writeln!(
+            target,
+            "{} {:<5} {}: {}",
+            Self::timestamp(now),
+            record.level(),
+            module,
+            record.args()
+        );
+    }
+
+    fn flush(&self) {
+        let _ = self.target.lock().unwrap().flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(filter: &str) -> Result<LogService, Box<dyn Error>> {
+        LogService::new(LogConfig {
+            filter: filter.to_string(),
+            file: None,
+        })
+    }
+
+    #[test]
+    fn filter_sets_the_default_and_module_levels() {
+        let service = parse(" info , services=debug,player_controller=off,,").unwrap();
+        assert_eq!(service.default_level, LevelFilter::Info);
+        assert_eq!(
+            service.module_levels,
+            vec![
+                ("player_controller".to_string(), LevelFilter::Off),
+                ("services".to_string(), LevelFilter::Debug),
+            ],
+            "longest module first"
+        );
+        assert_eq!(parse("").unwrap().default_level, LevelFilter::Warn);
+    }
+
+    #[test]
+    fn bad_filters_are_rejected() {
+        for filter in ["loud", "services=loud", "services=", "=debug,warn=x"] {
+            assert!(parse(filter).is_err(), "'{}' should be rejected", filter);
+        }
+    }
+
+    #[test]
+    fn modules_match_whole_path_segments() {
+        let service = parse("error,services=debug,modules::services::mp3_loader=trace").unwrap();
+        let level_for = |target| service.level_for(target);
+        assert_eq!(
+            level_for("cli_audio_player::modules::services::mp3_loader"),
+            LevelFilter::Trace
+        );
+        assert_eq!(
+            level_for("cli_audio_player::modules::services::wav_loader"),
+            LevelFilter::Debug
+        );
+        assert_eq!(
+            level_for("cli_audio_player::modules::my_services::wav_loader"),
+            LevelFilter::Error
+        );
+        assert_eq!(level_for("services"), LevelFilter::Debug, "a bare target");
+        assert_eq!(level_for("symphonia_bundle_mp3"), LevelFilter::Error);
+    }
+
+    #[test]
+    fn timestamps_are_civil_utc_dates() {
+        fn at(seconds: u64, millis: u32) -> String {
+            LogService::timestamp(Duration::new(seconds, millis * 1_000_000))
+        }
+        assert_eq!(at(0, 0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(at(951_782_400, 5), "2000-02-29T00:00:00.005Z", "leap day");
+        assert_eq!(at(1_709_251_199, 999), "2024-02-29T23:59:59.999Z");
+        assert_eq!(at(1_735_689_599, 0), "2024-12-31T23:59:59.000Z");
+        assert_eq!(
+            at(4_107_542_400, 0),
+            "2100-03-01T00:00:00.000Z",
+            "2100 is no leap year"
+        );
+    }
+}
@@ -0,0 +1,597 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+
+use log::debug;
+use symphonia::core::codecs::CODEC_TYPE_NULL;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{
+    MetadataOptions, MetadataQueue, StandardTagKey, StandardVisualKey, Tag,
+};
+use symphonia::core::probe::{Hint, ProbeResult};
+
+use crate::modules::models::artwork_model::{Artwork, ArtworkSource};
+use crate::modules::models::track_metadata_model::TrackMetadata;
+use crate::modules::services::tag_service::TagService;
+
+/// Names of cover images in album folders (without extension), best match first.
+const COVER_NAMES: [&str; 4] = ["cover", "folder", "front", "album"];
+
+/// Extensions of cover images in album folders, best match first.
+const COVER_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+/// ID3v2 `APIC` picture type of the front cover.
+const APIC_FRONT_COVER: u8 = 3;
+
+/// Size of an ID3v1 tag, stored in the last bytes of the file.
+const ID3V1_SIZE: u64 = 128;
+
+/// The genres an ID3v1 tag (or an ID3v2 `(17)` reference) can point to.
+pub const ID3V1_GENRES: [&str; 80] = [
+    "Blues",
+    "Classic Rock",
+    "Country",
+    "Dance",
+    "Disco",
+    "Funk",
+    "Grunge",
+    "Hip-Hop",
+    "Jazz",
+    "Metal",
+    "New Age",
+    "Oldies",
+    "Other",
+    "Pop",
+    "R&B",
+    "Rap",
+    "Reggae",
+    "Rock",
+    "Techno",
+    "Industrial",
+    "Alternative",
+    "Ska",
+    "Death Metal",
+    "Pranks",
+    "Soundtrack",
+    "Euro-Techno",
+    "Ambient",
+    "Trip-Hop",
+    "Vocal",
+    "Jazz+Funk",
+    "Fusion",
+    "Trance",
+    "Classical",
+    "Instrumental",
+    "Acid",
+    "House",
+    "Game",
+    "Sound Clip",
+    "Gospel",
+    "Noise",
+    "AlternRock",
+    "Bass",
+    "Soul",
+    "Punk",
+    "Space",
+    "Meditative",
+    "Instrumental Pop",
+    "Instrumental Rock",
+    "Ethnic",
+    "Gothic",
+    "Darkwave",
+    "Techno-Industrial",
+    "Electronic",
+    "Pop-Folk",
+    "Eurodance",
+    "Dream",
+    "Southern Rock",
+    "Comedy",
+    "Cult",
+    "Gangsta",
+    "Top 40",
+    "Christian Rap",
+    "Pop/Funk",
+    "Jungle",
+    "Native American",
+    "Cabaret",
+    "New Wave",
+    "Psychedelic",
+    "Rave",
+    "Showtunes",
+    "Trailer",
+    "Lo-Fi",
+    "Tribal",
+    "Acid Punk",
+    "Acid Jazz",
+    "Polka",
+    "Retro",
+    "Musical",
+    "Rock & Roll",
+    "Hard Rock",
+];
+
+/// **MetadataService Struct**
+///
+/// Reads `TrackMetadata` from audio files without decoding them: the tags found while
+/// probing the container (ID3v2.3/2.4, RIFF INFO, Vorbis comments, MP4 atoms), an ID3v1
+/// tag at the end of the file for whatever those left empty, and the track length from
+/// the container headers.
+pub struct MetadataService;
+
+impl MetadataService {
+    /// **Read the Metadata of a File**
+    ///
+    /// # Parameters:
+    /// - `path`: The path to the audio file.
+    ///
+    /// # Returns:
+    /// - The `TrackMetadata` of the file. Whatever cannot be read is left empty, a file
+    ///   that cannot be probed at all only gets its modification time (and ID3v1 tag).
+    pub fn read(&self, path: &Path) -> TrackMetadata {
+        let mut metadata = TrackMetadata {
+            modified: fs::metadata(path).and_then(|file| file.modified()).ok(),
+            ..TrackMetadata::default()
+        };
+        if let Err(err) = Self::probe(path, &mut metadata) {
+            debug!("No metadata for {:?}: {}", path.display(), err);
+        }
+        if let Err(err) = Self::read_id3v1(path, &mut metadata) {
+            debug!("No ID3v1 tag in {:?}: {}", path.display(), err);
+        }
+        metadata
+    }
+
+    /// **Read the Artwork of a File**
+    ///
+    /// Looks for a picture in the file (ID3v2 `APIC`, FLAC `PICTURE`, MP4 `covr`, front
+    /// covers first), then for a cover image in its folder (`cover.jpg`, `folder.png`, ...).
+    ///
+    /// # Parameters:
+    /// - `path`: The path to the audio file.
+    ///
+    /// # Returns:
+    /// - `Some(Artwork)` with the encoded image, `None` if there is none.
+    pub fn read_artwork(&self, path: &Path) -> Option<Artwork> {
+        match Self::embedded_artwork(path) {
+            Ok(Some(artwork)) => return Some(artwork),
+            Ok(None) => {}
+            Err(err) => debug!("No embedded artwork in {:?}: {}", path.display(), err),
+        }
+        Self::folder_artwork(path)
+    }
+
+    /// Opens `path` with the symphonia probe, guided by the file extension.
+    fn open(path: &Path) -> Result<ProbeResult, Box<dyn Error>> {
+        let mss = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(extension);
+        }
+        Ok(symphonia::default::get_probe().format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?)
+    }
+
+    /// Probes the container of `path` and fills in the tags and duration it reports.
+    fn probe(path: &Path, metadata: &mut TrackMetadata) -> Result<(), Box<dyn Error>> {
+        let probed = Self::open(path)?;
+
+        // Tags read ahead of the container (ID3v2) and by the format reader itself
+        // (RIFF INFO, Vorbis comments, MP4 atoms).
+        Self::apply_queue(&probed.metadata, metadata);
+        Self::apply_queue(probed.format.metadata(), metadata);
+
+        if let Some(track) = probed
+            .format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        {
+            let params = &track.codec_params;
+            if let (Some(frames), Some(sample_rate)) = (params.n_frames, params.sample_rate) {
+                metadata.duration = Some(Duration::from_secs_f64(
+                    frames as f64 / sample_rate.max(1) as f64,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies every metadata revision of a queue, later revisions overriding earlier ones.
+    fn apply_queue(queue: &MetadataQueue, metadata: &mut TrackMetadata) {
+        loop {
+            if let Some(revision) = queue.current() {
+                for tag in revision.tags() {
+                    Self::apply_tag(tag, metadata);
+                }
+            }
+            if queue.pop().is_none() {
+                break; // `current` was the newest revision.
+            }
+        }
+    }
+
+    /// Copies one tag into `metadata` if it is a field the player uses.
+    fn apply_tag(tag: &Tag, metadata: &mut TrackMetadata) {
+        let value = tag
+            .value
+            .to_string()
+            .trim_matches(char::from(0))
+            .trim()
+            .to_string();
+        if value.is_empty() {
+            return;
+        }
+        match tag.std_key {
+            Some(StandardTagKey::TrackTitle) => metadata.title = Some(value),
+            Some(StandardTagKey::Artist) => metadata.artist = Some(value),
+            Some(StandardTagKey::Album) => metadata.album = Some(value),
+            Some(StandardTagKey::AlbumArtist) => metadata.album_artist = Some(value),
+            Some(StandardTagKey::Genre) => metadata.genre = Some(Self::genre_name(&value)),
+            Some(StandardTagKey::TrackNumber) => {
+                metadata.track_number = Self::parse_number(&value).or(metadata.track_number)
+            }
+            Some(StandardTagKey::DiscNumber) => {
+                metadata.disc_number = Self::parse_number(&value).or(metadata.disc_number)
+            }
+            // ID3v2.3 splits the date (TYER, TDAT, TIME), keep the first year seen.
+            Some(StandardTagKey::Date)
+            | Some(StandardTagKey::ReleaseDate)
+            | Some(StandardTagKey::OriginalDate)
+                if metadata.year.is_none() =>
+            {
+                metadata.year = Self::parse_year(&value)
+            }
+            _ if matches!(tag.std_key, Some(StandardTagKey::Rating))
+                || tag.key.eq_ignore_ascii_case("FMPS_RATING") =>
+            {
+                metadata.rating = Self::parse_rating(&tag.key, &value).or(metadata.rating)
+            }
+            _ => {}
+        }
+    }
+
+    /// Reads the picture stored in the file: ID3v2 `APIC` frames are read here, FLAC and
+    /// MP4 pictures are reported by symphonia.
+    fn embedded_artwork(path: &Path) -> Result<Option<Artwork>, Box<dyn Error>> {
+        let mut file = File::open(path)?;
+        let mut tag = vec![0u8; 10];
+        if file.read_exact(&mut tag).is_ok() && tag.starts_with(b"ID3") {
+            let size = tag[6..10]
+                .iter()
+                .fold(0, |size, &b| (size << 7) | usize::from(b & 0x7f));
+            tag.resize(10 + size, 0);
+            file.read_exact(&mut tag[10..])?;
+            let (frames, _) = TagService::read_id3v2_frames(&tag)?;
+            let mut pictures: Vec<(u8, Artwork)> = frames
+                .iter()
+                // Compressed, encrypted or unsynchronised frames are rare for pictures.
+                .filter(|(id, flags, _)| id == b"APIC" && flags[1] & 0x0f == 0)
+                .filter_map(|(_, _, data)| Self::parse_apic(data))
+                .collect();
+            pictures.sort_by_key(|(kind, _)| *kind != APIC_FRONT_COVER);
+            if let Some((_, artwork)) = pictures.into_iter().next() {
+                return Ok(Some(artwork));
+            }
+        }
+
+        let probed = Self::open(path)?;
+        let mut pictures: Vec<(bool, Artwork)> = Vec::new();
+        for queue in [&probed.metadata, probed.format.metadata()] {
+            loop {
+                if let Some(revision) = queue.current() {
+                    pictures.extend(revision.visuals().iter().map(|visual| {
+                        let is_front = matches!(visual.usage, Some(StandardVisualKey::FrontCover));
+                        let artwork = Artwork {
+                            media_type: visual.media_type.clone(),
+                            data: visual.data.to_vec(),
+                            source: ArtworkSource::Embedded,
+                        };
+                        (is_front, artwork)
+                    }));
+                }
+                if queue.pop().is_none() {
+                    break;
+                }
+            }
+        }
+        pictures.sort_by_key(|(is_front, _)| !is_front);
+        Ok(pictures.into_iter().next().map(|(_, artwork)| artwork))
+    }
+
+    /// Parses an `APIC` frame: text encoding, MIME type, picture type, description, image.
+    ///
+    /// # Returns:
+    /// - The picture type and the artwork, `None` for linked (`-->`) or broken frames.
+    fn parse_apic(data: &[u8]) -> Option<(u8, Artwork)> {
+        let (&encoding, rest) = data.split_first()?;
+        let mime_end = rest.iter().position(|&b| b == 0)?;
+        let mime = String::from_utf8_lossy(&rest[..mime_end]).to_lowercase();
+        let (&kind, rest) = rest[mime_end + 1..].split_first()?;
+        // The description ends with a NUL, two for UTF-16.
+        let image_start = match encoding {
+            1 | 2 => rest.chunks(2).position(|pair| pair == [0, 0])? * 2 + 2,
+            _ => rest.iter().position(|&b| b == 0)? + 1,
+        };
+        let media_type = match mime.as_str() {
+            "-->" => return None,
+            "image/jpg" | "jpg" | "jpeg" => "image/jpeg".to_string(),
+            mime if !mime.contains('/') => format!("image/{}", mime),
+            mime => mime.to_string(),
+        };
+        Some((
+            kind,
+            Artwork {
+                media_type,
+                data: rest.get(image_start..)?.to_vec(),
+                source: ArtworkSource::Embedded,
+            },
+        ))
+    }
+
+    /// Finds a cover image next to the track (`cover.jpg`, `folder.png`, ..., any case).
+    fn folder_artwork(path: &Path) -> Option<Artwork> {
+        let folder = path.parent()?;
+        let rank = |image: &Path| {
+            let stem = image.file_stem()?.to_str()?.to_lowercase();
+            let extension = image.extension()?.to_str()?.to_lowercase();
+            let name = COVER_NAMES.iter().position(|&name| name == stem)?;
+            let format = COVER_EXTENSIONS.iter().position(|&e| e == extension)?;
+            Some((name, format))
+        };
+        let image = fs::read_dir(folder)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|image| image.is_file())
+            .filter_map(|image| rank(&image).map(|rank| (rank, image)))
+            .min()?
+            .1;
+        let media_type = match rank(&image)?.1 {
+            2 => "image/png",
+            _ => "image/jpeg",
+        };
+        match fs::read(&image) {
+            Ok(data) => Some(Artwork {
+                media_type: media_type.to_string(),
+                data,
+                source: ArtworkSource::Folder(image),
+            }),
+            Err(err) => {
+                debug!("Could not read {:?}: {}", image.display(), err);
+                None
+            }
+        }
+    }
+
+    /// Reads an ID3v1 tag (`TAG` + fixed-size Latin-1 fields in the last 128 bytes) and
+    /// fills the fields that are still empty. ID3v1.1 stores the track number in the
+    /// last two bytes of the comment.
+    fn read_id3v1(path: &Path, metadata: &mut TrackMetadata) -> Result<(), Box<dyn Error>> {
+        let mut file = File::open(path)?;
+        if file.metadata()?.len() < ID3V1_SIZE {
+            return Ok(());
+        }
+        file.seek(SeekFrom::End(-(ID3V1_SIZE as i64)))?;
+        let mut tag = [0u8; ID3V1_SIZE as usize];
+        file.read_exact(&mut tag)?;
+        if &tag[..3] != b"TAG" {
+            return Ok(());
+        }
+
+        let text = |bytes: &[u8]| {
+            let text: String = bytes
+                .iter()
+                .take_while(|&&b| b != 0)
+                .map(|&b| b as char)
+                .collect();
+            Some(text.trim().to_string()).filter(|t| !t.is_empty())
+        };
+        metadata.title = metadata.title.take().or_else(|| text(&tag[3..33]));
+        metadata.artist = metadata.artist.take().or_else(|| text(&tag[33..63]));
+        metadata.album = metadata.album.take().or_else(|| text(&tag[63..93]));
+        metadata.year = metadata
+            .year
+            .or_else(|| text(&tag[93..97]).and_then(|y| Self::parse_year(&y)));
+        if tag[125] == 0 && tag[126] != 0 {
+            metadata.track_number = metadata.track_number.or(Some(u32::from(tag[126])));
+        }
+        if metadata.genre.is_none() {
+            metadata.genre = ID3V1_GENRES
+                .get(usize::from(tag[127]))
+                .map(|genre| genre.to_string());
+        }
+        Ok(())
+    }
+
+    /// Resolves numeric genre references (`(17)`, `17`, `(17)Rock`) to the genre name.
+    fn genre_name(value: &str) -> String {
+        let number = value
+            .strip_prefix('(')
+            .and_then(|rest| rest.split_once(')'))
+            .map_or(value, |(number, _)| number);
+        number
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| ID3V1_GENRES.get(index))
+            .map_or_else(|| value.to_string(), |genre| genre.to_string())
+    }
+
+    /// Parses the leading number of a tag value, so `3/12` gives `3`.
+    fn parse_number(value: &str) -> Option<u32> {
+        let digits: String = value
+            .trim()
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        digits.parse().ok()
+    }
+
+    /// Converts a rating tag to 1 to 5 stars: a `POPM` byte (0 to 255), an `FMPS_RATING`
+    /// (0.0 to 1.0) or a plain `RATING` (stars, or a percentage above 5).
+    ///
+    /// # Returns:
+    /// - The stars, `None` for unrated (`0`) or unreadable values.
+    fn parse_rating(key: &str, value: &str) -> Option<u8> {
+        let key = key.to_uppercase();
+        if key.starts_with("POPM") {
+            // The ranges around the bytes players write (1, 64, 128, 196, 255).
+            return match value.trim().parse::<u8>().ok()? {
+                0 => None,
+                1..=31 => Some(1),
+                32..=95 => Some(2),
+                96..=159 => Some(3),
+                160..=223 => Some(4),
+                _ => Some(5),
+            };
+        }
+        let number: f64 = value.trim().parse().ok()?;
+        let stars = match number {
+            _ if key == "FMPS_RATING" => number * 5.0,
+            _ if number <= 5.0 => number,
+            _ => number / 20.0,
+        }
+        .round();
+        (1.0..=5.0).contains(&stars).then_some(stars as u8)
+    }
+
+    /// Parses the year of a date tag (`1997`, `1997-05-21`, `1997-05-21T10:00`).
+    fn parse_year(value: &str) -> Option<u32> {
+        Self::parse_number(value).filter(|year| (1000..=9999).contains(year))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::services::temp_folder::TempFolder;
+    use std::path::PathBuf;
+
+    /// Builds an ID3v1 tag; a `track` turns it into ID3v1.1.
+    fn id3v1(title: &[u8], artist: &str, year: &str, track: Option<u8>, genre: u8) -> Vec<u8> {
+        let field = |text: &[u8], size: usize| {
+            let mut field = text.to_vec();
+            field.resize(size, 0);
+            field
+        };
+        let mut tag = b"TAG".to_vec();
+        tag.extend(field(title, 30));
+        tag.extend(field(artist.as_bytes(), 30));
+        tag.extend(field(b"Album   ", 30));
+        tag.extend(field(year.as_bytes(), 4));
+        let mut comment = field(b"comment", 30);
+        if let Some(track) = track {
+            comment[28] = 0;
+            comment[29] = track;
+        }
+        tag.extend(comment);
+        tag.push(genre);
+        tag
+    }
+
+    /// Writes `bytes` after some audio-like padding to a file in a temporary folder.
+    fn file_with(name: &str, bytes: &[u8]) -> (TempFolder, PathBuf) {
+        let folder = TempFolder::new(&format!("id3v1-{}", name));
+        let mut data = vec![0x55u8; 300];
+        data.extend_from_slice(bytes);
+        let path = folder.write("track.mp3", data);
+        (folder, path)
+    }
+
+    #[test]
+    fn id3v1_fills_the_fields() {
+        let (_folder, path) = file_with("fields", &id3v1(b"Caf\xe9", "Artist", "1997", Some(7), 8));
+        let mut metadata = TrackMetadata::default();
+        MetadataService::read_id3v1(&path, &mut metadata).unwrap();
+
+        assert_eq!(metadata.title.as_deref(), Some("Café"), "Latin-1 text");
+        assert_eq!(metadata.artist.as_deref(), Some("Artist"));
+        assert_eq!(
+            metadata.album.as_deref(),
+            Some("Album"),
+            "padding is trimmed"
+        );
+        assert_eq!(metadata.year, Some(1997));
+        assert_eq!(metadata.track_number, Some(7));
+        assert_eq!(metadata.genre.as_deref(), Some("Jazz"));
+    }
+
+    #[test]
+    fn id3v1_keeps_what_other_tags_set() {
+        let (_folder, path) = file_with("keep", &id3v1(b"Old", "Old", "19xx", None, 255));
+        let mut metadata = TrackMetadata {
+            title: Some("New".to_string()),
+            ..TrackMetadata::default()
+        };
+        MetadataService::read_id3v1(&path, &mut metadata).unwrap();
+
+        assert_eq!(metadata.title.as_deref(), Some("New"));
+        assert_eq!(metadata.artist.as_deref(), Some("Old"));
+        assert_eq!(metadata.year, None, "not a year");
+        assert_eq!(metadata.track_number, None, "ID3v1.0 has no track number");
+        assert_eq!(metadata.genre, None, "255 is no genre");
+    }
+
+    #[test]
+    fn files_without_id3v1_are_left_alone() {
+        let mut metadata = TrackMetadata::default();
+        let (folder, path) = file_with("none", &[0u8; 128]);
+        MetadataService::read_id3v1(&path, &mut metadata).unwrap();
+        assert_eq!(metadata, TrackMetadata::default());
+
+        let short = folder.write("short.mp3", b"TAG");
+        MetadataService::read_id3v1(&short, &mut metadata).unwrap();
+        assert_eq!(metadata, TrackMetadata::default());
+    }
+
+    #[test]
+    fn flac_pictures_are_read() {
+        let mut picture = 3u32.to_be_bytes().to_vec(); // Front cover.
+        for text in ["image/png", ""] {
+            picture.extend_from_slice(&(text.len() as u32).to_be_bytes());
+            picture.extend_from_slice(text.as_bytes());
+        }
+        picture.extend_from_slice(&[0; 16]); // Width, height, depth, colours.
+        picture.extend_from_slice(&4u32.to_be_bytes());
+        picture.extend_from_slice(b"\x89PNG");
+
+        let mut bytes = b"fLaC\x00\x00\x00\x22".to_vec();
+        let packed: u64 = (44_100 << 44) | (1 << 41) | (15 << 36);
+        bytes.extend_from_slice(&[0x10, 0x00, 0x10, 0x00, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&packed.to_be_bytes());
+        bytes.extend_from_slice(&[0; 16]);
+        bytes.extend_from_slice(&[0x86, 0, 0, picture.len() as u8]);
+        bytes.extend_from_slice(&picture);
+        let folder = TempFolder::new("picture");
+        let path = folder.write("track.flac", bytes);
+
+        let artwork = MetadataService.read_artwork(&path).unwrap();
+        assert_eq!(artwork.media_type, "image/png");
+        assert_eq!(artwork.data, b"\x89PNG");
+        assert_eq!(artwork.source, ArtworkSource::Embedded);
+    }
+
+    #[test]
+    fn numeric_genres_are_named() {
+        assert_eq!(MetadataService::genre_name("(17)"), "Rock");
+        assert_eq!(MetadataService::genre_name("(17)Rock"), "Rock");
+        assert_eq!(MetadataService::genre_name("0"), "Blues");
+        assert_eq!(MetadataService::genre_name("Post-Rock"), "Post-Rock");
+        assert_eq!(MetadataService::genre_name("(999)"), "(999)");
+    }
+
+    #[test]
+    fn numbers_and_years_take_the_leading_digits() {
+        assert_eq!(MetadataService::parse_number("3/12"), Some(3));
+        assert_eq!(MetadataService::parse_number(" 04 "), Some(4));
+        assert_eq!(MetadataService::parse_number("x"), None);
+        assert_eq!(MetadataService::parse_year("1997-05-21T10:00"), Some(1997));
+        assert_eq!(MetadataService::parse_year("97"), None);
+    }
+}
@@ -0,0 +1,355 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::modules::models::crossfade_model::FadeCurve;
+
+/// **PlaybackHandle Struct**
+///
+/// Shared between the `PlayerController` and the `cpal` output callback of one stream.
+/// The callback renders samples through the handle, the controller uses it to read the
+/// playback position, to start volume ramps (crossfades, click-free pause/resume) and to
+/// seek without touching the stream.
+pub struct PlaybackHandle {
+    state: Mutex<PlaybackState>,
+}
+
+/// The mutable part of the handle, guarded by a mutex because the audio callback runs on its own thread.
+struct PlaybackState {
+    sample_rate: u32, // Sample rate of the track, used to convert positions into frames.
+    output_rate: u32, // Sample rate of the output device, used to convert fades into frames.
+    position: usize,  // Index of the next frame to play.
+    fraction: f64,    // How far playback is between `position` and the next frame.
+    total_frames: usize, // Number of frames in the decoded track.
+    end_frame: Option<usize>, // Frame playback stops at (CUE tracks), `None` = end of the track.
+    ramp: GainRamp,   // Current volume ramp (a settled ramp is just a constant gain).
+    seek: Option<PendingSeek>, // Seek waiting for the fade-out before jumping.
+    volume: f32,      // The user's volume, applied on top of the ramps.
+}
+
+/// A seek that jumps once the stream has faded to silence, then fades back in.
+struct PendingSeek {
+    frame: usize,       // Frame to jump to.
+    fade_frames: usize, // Length of the fade-out and the fade-in.
+    restore_gain: f32,  // Gain to fade back in to after the jump.
+}
+
+/// A volume ramp from one gain to another over a number of frames.
+struct GainRamp {
+    from: f32,
+    to: f32,
+    curve: FadeCurve,
+    length: usize,
+    elapsed: usize,
+}
+
+impl GainRamp {
+    /// A ramp that has already settled at `gain`.
+    fn constant(gain: f32) -> Self {
+        Self {
+            from: gain,
+            to: gain,
+            curve: FadeCurve::Linear,
+            length: 0,
+            elapsed: 0,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.elapsed >= self.length
+    }
+
+    /// Gain at the current point of the ramp.
+    fn gain(&self) -> f32 {
+        if self.is_done() {
+            return self.to;
+        }
+        let progress = self.elapsed as f32 / self.length as f32;
+        if self.to >= self.from {
+            self.from + (self.to - self.from) * self.curve.gain(progress)
+        } else {
+            // Fading out: mirror the curve in time so equal-power gives the cosine half.
+            self.to + (self.from - self.to) * self.curve.gain(1.0 - progress)
+        }
+    }
+
+    fn advance(&mut self) {
+        if !self.is_done() {
+            self.elapsed += 1;
+        }
+    }
+}
+
+impl PlaybackState {
+    /// The frame playback stops at: the end set with `set_end`, or the end of the track.
+    fn last_frame(&self) -> usize {
+        self.end_frame
+            .map_or(self.total_frames, |end| end.min(self.total_frames))
+    }
+}
+
+impl PlaybackHandle {
+    /// Creates a new handle.
+    ///
+    /// # Parameters:
+    /// - `initial_gain`: The gain the stream starts with. Use `0.0` for a stream that fades in.
+    pub fn new(initial_gain: f32) -> Self {
+        PlaybackHandle {
+            state: Mutex::new(PlaybackState {
+                sample_rate: 44_100,
+                output_rate: 44_100,
+                position: 0,
+                fraction: 0.0,
+                total_frames: 0,
+                end_frame: None,
+                ramp: GainRamp::constant(initial_gain),
+                seek: None,
+                volume: 1.0,
+            }),
+        }
+    }
+
+    /// Records the sample rates of the track and the output device and the length of the
+    /// decoded track. Called by the loader before the stream is built.
+    pub fn set_format(&self, sample_rate: u32, output_rate: u32, total_frames: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.sample_rate = sample_rate.max(1);
+        state.output_rate = output_rate.max(1);
+        state.total_frames = total_frames;
+    }
+
+    /// Sets where playback stops, for a track that is only a section of the decoded audio
+    /// (a CUE track). `None` plays to the end.
+    pub fn set_end(&self, end: Option<Duration>) {
+        let mut state = self.state.lock().unwrap();
+        state.end_frame = end.map(|end| (end.as_secs_f64() * state.sample_rate as f64) as usize);
+    }
+
+    /// Sets the volume (`0.0` to `1.0`) the samples are scaled by, on top of the fades.
+    pub fn set_volume(&self, volume: f32) {
+        self.state.lock().unwrap().volume = volume.clamp(0.0, 1.0);
+    }
+
+    /// Starts a volume ramp from the current gain to `target` over `duration`.
+    /// A pending seek jumps right away, as the new ramp takes over from its fade.
+    pub fn ramp_to(&self, target: f32, duration: Duration, curve: FadeCurve) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(pending) = state.seek.take() {
+            state.position = pending.frame;
+            state.fraction = 0.0;
+        }
+        let from = state.ramp.gain();
+        let length = if from == target {
+            0 // Nothing to fade, settle right away (also works on a paused stream).
+        } else {
+            (duration.as_secs_f64() * state.output_rate as f64) as usize
+        };
+        state.ramp = GainRamp {
+            from,
+            to: target,
+            curve,
+            length,
+            elapsed: 0,
+        };
+    }
+
+    /// Moves the playback position to `position`.
+    ///
+    /// While the stream is audible the jump is wrapped in a fade-out and fade-in of `fade`
+    /// so it does not click. With a zero `fade` (or a silent stream, e.g. paused) the
+    /// position changes immediately.
+    pub fn seek(&self, position: Duration, fade: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let frame =
+            ((position.as_secs_f64() * state.sample_rate as f64) as usize).min(state.total_frames);
+        let current_gain = state.ramp.gain();
+        let restore_gain = match state.seek.take() {
+            Some(pending) => pending.restore_gain, // A second seek replaces the first one.
+            None => state.ramp.to,
+        };
+
+        if fade.is_zero() || current_gain <= 0.0 {
+            state.position = frame;
+            state.fraction = 0.0;
+            return;
+        }
+        let fade_frames = (fade.as_secs_f64() * state.output_rate as f64) as usize;
+        state.ramp = GainRamp {
+            from: current_gain,
+            to: 0.0,
+            curve: FadeCurve::Linear,
+            length: fade_frames,
+            elapsed: 0,
+        };
+        state.seek = Some(PendingSeek {
+            frame,
+            fade_frames,
+            restore_gain,
+        });
+    }
+
+    /// Returns the current playback position.
+    pub fn position(&self) -> Duration {
+        let state = self.state.lock().unwrap();
+        let frame = match state.seek {
+            Some(ref pending) => pending.frame, // Report where the stream is heading.
+            None => state.position,
+        };
+        Duration::from_secs_f64(frame as f64 / state.sample_rate as f64)
+    }
+
+    /// Returns `true` once a fade-out has reached silence.
+    pub fn is_silent(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.ramp.is_done() && state.ramp.to <= 0.0
+    }
+
+    /// Returns `true` when every frame of the track (up to its end) has been played.
+    pub fn is_finished(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.position >= state.last_frame()
+    }
+
+    /// Returns the playback time left until the end of the track.
+    pub fn remaining(&self) -> Duration {
+        let state = self.state.lock().unwrap();
+        let frames = state.last_frame().saturating_sub(state.position);
+        Duration::from_secs_f64(frames as f64 / state.sample_rate as f64)
+    }
+
+    /// Fills an output buffer from the decoded samples, applying the current gain ramp.
+    /// When the track and the output device run at different rates, the samples are
+    /// resampled by interpolating between neighbouring frames.
+    ///
+    /// # Parameters:
+    /// - `data`: The interleaved output buffer handed to the `cpal` callback.
+    /// - `output_channels`: Channel count of the output device.
+    /// - `samples`: The decoded, interleaved samples of the track.
+    /// - `channels`: Channel count of the decoded samples.
+    pub fn render(
+        &self,
+        data: &mut [f32],
+        output_channels: usize,
+        samples: &[f32],
+        channels: usize,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        let step = state.sample_rate as f64 / state.output_rate as f64; // Track frames per output frame.
+        for frame in data.chunks_mut(output_channels) {
+            if state.ramp.is_done() {
+                // The fade-out of a pending seek has finished, jump and fade back in.
+                if let Some(pending) = state.seek.take() {
+                    state.position = pending.frame;
+                    state.fraction = 0.0;
+                    state.ramp = GainRamp {
+                        from: 0.0,
+                        to: pending.restore_gain,
+                        curve: FadeCurve::Linear,
+                        length: pending.fade_frames,
+                        elapsed: 0,
+                    };
+                }
+            }
+            let sample_index = state.position * channels;
+            if state.position < state.last_frame() && sample_index + channels <= samples.len() {
+                // The last frame has no neighbour to interpolate towards, it is held instead.
+                let next_index = if state.position + 1 < state.last_frame()
+                    && sample_index + 2 * channels <= samples.len()
+                {
+                    sample_index + channels
+                } else {
+                    sample_index
+                };
+                let gain = state.ramp.gain() * state.volume;
+                let weight = state.fraction as f32;
+                for (i, sample) in frame.iter_mut().enumerate() {
+                    let current = samples[sample_index + i % channels];
+                    let next = samples[next_index + i % channels];
+                    *sample = (current + (next - current) * weight) * gain;
+                }
+                state.fraction += step;
+                let whole = state.fraction.floor();
+                state.position += whole as usize;
+                state.fraction -= whole;
+                state.ramp.advance();
+            } else {
+                // Past the end there is nothing left to fade, settle the ramp so a pause or
+                // stop started close to the end still reaches silence.
+                state.ramp.elapsed = state.ramp.length;
+                frame.fill(0.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fades_settle_at_the_end_of_the_track() {
+        let handle = PlaybackHandle::new(1.0);
+        handle.set_format(1000, 1000, 10);
+        let samples = [0.5; 10];
+        let mut data = [0.0; 8];
+        handle.render(&mut data, 1, &samples, 1);
+        handle.ramp_to(0.0, Duration::from_secs(1), FadeCurve::Linear);
+        assert!(!handle.is_silent());
+        handle.render(&mut data, 1, &samples, 1);
+        assert!(handle.is_finished());
+        assert!(handle.is_silent(), "the fade ends with the track");
+    }
+
+    #[test]
+    fn a_ramp_reaches_its_target_over_the_given_frames() {
+        let handle = PlaybackHandle::new(1.0);
+        handle.set_format(1000, 1000, 100);
+        let samples = [1.0; 100];
+        let mut data = [0.0; 6];
+        handle.ramp_to(0.0, Duration::from_millis(4), FadeCurve::Linear);
+        handle.render(&mut data, 1, &samples, 1);
+        assert_eq!(data, [1.0, 0.75, 0.5, 0.25, 0.0, 0.0]);
+        assert!(handle.is_silent());
+
+        handle.ramp_to(1.0, Duration::from_millis(2), FadeCurve::Linear);
+        handle.render(&mut data, 1, &samples, 1);
+        assert_eq!(data, [0.0, 0.5, 1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn a_new_ramp_replaces_one_in_progress() {
+        let handle = PlaybackHandle::new(0.0);
+        handle.set_format(1000, 1000, 100);
+        let samples = [1.0; 100];
+        let mut data = [0.0; 5];
+        handle.ramp_to(1.0, Duration::from_millis(10), FadeCurve::Linear);
+        handle.render(&mut data, 1, &samples, 1);
+        assert_eq!(data, [0.0, 0.1, 0.2, 0.3, 0.4]);
+
+        // The fade-out starts from the gain the fade-in had reached, without a jump.
+        handle.ramp_to(0.0, Duration::from_millis(2), FadeCurve::Linear);
+        handle.render(&mut data, 1, &samples, 1);
+        assert_eq!(data, [0.5, 0.25, 0.0, 0.0, 0.0]);
+        assert!(handle.is_silent());
+    }
+
+    #[test]
+    fn positions_use_the_rate_of_the_track() {
+        let handle = PlaybackHandle::new(1.0);
+        handle.set_format(1000, 2000, 1000);
+        let samples: Vec<f32> = (0..1000).map(|frame| frame as f32).collect();
+        let mut data = [0.0; 4];
+        handle.render(&mut data, 1, &samples, 1);
+        assert_eq!(
+            data,
+            [0.0, 0.5, 1.0, 1.5],
+            "upsampling interpolates between frames"
+        );
+        assert_eq!(handle.position(), Duration::from_millis(2));
+
+        handle.set_end(Some(Duration::from_millis(500)));
+        handle.seek(Duration::from_millis(400), Duration::ZERO);
+        assert_eq!(handle.remaining(), Duration::from_millis(100));
+        handle.render(&mut data, 1, &samples, 1);
+        assert_eq!(data[0], 400.0, "seeks land on the frame of the track");
+    }
+}
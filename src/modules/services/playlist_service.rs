@@ -0,0 +1,565 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use log::{info, warn};
+
+use crate::modules::models::playlist_model::{PlaylistEntry, PlaylistModel};
+
+/// **PlaylistService Struct**
+///
+/// Reads and writes playlist files. The format is picked from the file extension:
+/// `.m3u` / `.m3u8` (plain or extended M3U), `.pls` (read only) and `.xspf`.
+pub struct PlaylistService;
+
+impl PlaylistService {
+    /// Returns `true` if `path` has the extension of a supported playlist format.
+    pub fn is_playlist(path: &Path) -> bool {
+        matches!(
+            Self::extension(path).as_str(),
+            "m3u" | "m3u8" | "pls" | "xspf"
+        )
+    }
+
+    /// **Load a Playlist**
+    ///
+    /// # Parameters:
+    /// - `path`: The path to the playlist file.
+    ///
+    /// # Returns:
+    /// - `Ok(PlaylistModel)`: The entries found. Entries pointing to missing files are listed
+    ///   in `missing` (and logged) instead of being dropped.
+    /// - `Err(Box<dyn Error>)`: If the file cannot be read or the format is not supported.
+    pub fn load(&self, path: &Path) -> Result<PlaylistModel, Box<dyn Error>> {
+        let bytes = fs::read(path)?;
+        let base = Self::folder_of(path);
+        let playlist = match Self::extension(path).as_str() {
+            "m3u8" => Self::parse_m3u(&String::from_utf8_lossy(&bytes), &base),
+            // Plain `.m3u` is usually UTF-8 nowadays, older files are Latin-1.
+            "m3u" => match String::from_utf8(bytes) {
+                Ok(text) => Self::parse_m3u(&text, &base),
+                Err(err) => {
+                    let text: String = err.into_bytes().iter().map(|&b| b as char).collect();
+                    Self::parse_m3u(&text, &base)
+                }
+            },
+            "pls" => Self::parse_pls(&String::from_utf8_lossy(&bytes), &base),
+            "xspf" => Self::parse_xspf(&String::from_utf8_lossy(&bytes), &base),
+            other => return Err(format!("Unsupported playlist format '{}'", other).into()),
+        };
+
+        for missing in &playlist.missing {
+            warn!("Playlist entry not found: {}", missing);
+        }
+        info!(
+            "Loaded {} entries from {:?} ({} missing).",
+            playlist.entries.len(),
+            path.display(),
+            playlist.missing.len()
+        );
+        Ok(playlist)
+    }
+
+    /// **Save a Playlist**
+    ///
+    /// Writes `entries` in UTF-8 as an extended M3U playlist, or as XSPF for `.xspf`.
+    /// M3U tracks below the playlist's folder are written as relative paths, so the folder
+    /// can be moved as a whole. XSPF locations are absolute `file://` URIs.
+    ///
+    /// # Parameters:
+    /// - `path`: The playlist file to write (`.m3u8`, `.m3u` or `.xspf`).
+    /// - `entries`: The tracks, in play order.
+    ///
+    /// # Returns:
+    /// - `Ok(())` once the file is written.
+    /// - `Err(Box<dyn Error>)`: If the format is not supported or writing fails.
+    pub fn save(&self, path: &Path, entries: &[PlaylistEntry]) -> Result<(), Box<dyn Error>> {
+        let base = Self::folder_of(path);
+        let text = match Self::extension(path).as_str() {
+            "m3u8" | "m3u" => Self::write_m3u(entries, &base),
+            "xspf" => Self::write_xspf(entries),
+            other => return Err(format!("Unsupported playlist format '{}'", other).into()),
+        };
+        fs::write(path, text)?;
+        info!("Saved {} entries to {:?}.", entries.len(), path.display());
+        Ok(())
+    }
+
+    /// Parses M3U text. `#EXTINF:<seconds>,<title>` lines describe the entry that follows,
+    /// other `#` lines are comments or unsupported directives and are skipped.
+    fn parse_m3u(text: &str, base: &Path) -> PlaylistModel {
+        let mut playlist = PlaylistModel::default();
+        let mut info: Option<(Option<Duration>, Option<String>)> = None;
+
+        for line in text.trim_start_matches('\u{feff}').lines().map(str::trim) {
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+                let (seconds, title) = extinf.split_once(',').unwrap_or((extinf, ""));
+                // Attributes (`tvg-id="..."`) may follow the duration, only the number counts.
+                let duration = seconds
+                    .split_whitespace()
+                    .next()
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .filter(|s| s.is_finite() && *s >= 0.0) // -1 means unknown.
+                    .map(Duration::from_secs_f64);
+                let title = Some(title.trim().to_string()).filter(|t| !t.is_empty());
+                info = Some((duration, title));
+                continue;
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+
+            let (duration, title) = info.take().unwrap_or_default();
+            match Self::resolve(line, base) {
+                Some(path) => playlist.entries.push(PlaylistEntry {
+                    path,
+                    title,
+                    duration,
+                }),
+                None => playlist.missing.push(line.to_string()),
+            }
+        }
+        playlist
+    }
+
+    /// Parses a PLS playlist: `FileN=`, `TitleN=` and `LengthN=` keys of the `[playlist]`
+    /// section, ordered by `N`.
+    fn parse_pls(text: &str, base: &Path) -> PlaylistModel {
+        let mut entries: BTreeMap<u32, (Option<String>, PlaylistEntry)> = BTreeMap::new();
+        for line in text.trim_start_matches('\u{feff}').lines().map(str::trim) {
+            let Some((key, value)) = line.split_once('=') else {
+                continue; // Section headers, comments and blank lines.
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+            let split = key.find(|c: char| c.is_ascii_digit()).unwrap_or(key.len());
+            let Ok(number) = key[split..].parse::<u32>() else {
+                continue; // `NumberOfEntries`, `Version`.
+            };
+            let (file, entry) = entries
+                .entry(number)
+                .or_insert_with(|| (None, PlaylistEntry::new(PathBuf::new())));
+            match &key[..split] {
+                "file" => *file = Some(value.to_string()),
+                "title" if !value.is_empty() => entry.title = Some(value.to_string()),
+                "length" => {
+                    entry.duration = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|s| s.is_finite() && *s >= 0.0) // -1 means unknown.
+                        .map(Duration::from_secs_f64)
+                }
+                _ => {}
+            }
+        }
+
+        let mut playlist = PlaylistModel::default();
+        for (file, entry) in entries.into_values() {
+            let Some(file) = file else {
+                continue; // A title or length without a file.
+            };
+            match Self::resolve(&file, base) {
+                Some(path) => playlist.entries.push(PlaylistEntry { path, ..entry }),
+                None => playlist.missing.push(file),
+            }
+        }
+        playlist
+    }
+
+    /// Parses an XSPF playlist: the `location`, `title` and `duration` (milliseconds) of
+    /// every `track` in the `trackList`. Only the first location of a track is used.
+    fn parse_xspf(text: &str, base: &Path) -> PlaylistModel {
+        let mut playlist = PlaylistModel::default();
+        for track in Self::xml_elements(text, "track") {
+            let Some(location) = Self::xml_elements(track, "location").next() else {
+                continue;
+            };
+            let location = Self::xml_unescape(location.trim());
+            let title = Self::xml_elements(track, "title")
+                .next()
+                .map(|title| Self::xml_unescape(title.trim()))
+                .filter(|title| !title.is_empty());
+            let duration = Self::xml_elements(track, "duration")
+                .next()
+                .and_then(|millis| millis.trim().parse::<u64>().ok())
+                .map(Duration::from_millis);
+
+            // XSPF locations are URIs, relative ones are percent-encoded as well.
+            let resolved = if location.contains("://") {
+                Self::resolve(&location, base)
+            } else {
+                Self::resolve(&Self::percent_decode(&location), base)
+            };
+            match resolved {
+                Some(path) => playlist.entries.push(PlaylistEntry {
+                    path,
+                    title,
+                    duration,
+                }),
+                None => playlist.missing.push(location),
+            }
+        }
+        playlist
+    }
+
+    /// Turns a playlist entry into the path of an existing file. Relative paths are relative
+    /// to the playlist's folder, `file://` URIs are decoded, other URLs are not playable.
+    fn resolve(line: &str, base: &Path) -> Option<PathBuf> {
+        let decoded;
+        let line = if let Some(uri_path) = Self::file_uri_path(line) {
+            decoded = Self::percent_decode(uri_path);
+            decoded.as_str()
+        } else if line.contains("://") {
+            return None; // Streams and URLs are not playable.
+        } else {
+            line
+        };
+        let path = Path::new(line);
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            base.join(path)
+        };
+        if path.is_file() {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    /// Writes entries as extended M3U text.
+    fn write_m3u(entries: &[PlaylistEntry], base: &Path) -> String {
+        let mut text = String::from("#EXTM3U\n");
+        for entry in entries {
+            let seconds = entry
+                .duration
+                .map(|d| d.as_secs_f64().round() as i64)
+                .unwrap_or(-1);
+            let title = entry.title.clone().unwrap_or_else(|| {
+                entry
+                    .path
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            });
+            let _ = writeln!(text, "#EXTINF:{},{}", seconds, title);
+            let _ = writeln!(text, "{}", Self::relative_to(&entry.path, base).display());
+        }
+        text
+    }
+
+    /// Writes entries as an XSPF document.
+    fn write_xspf(entries: &[PlaylistEntry]) -> String {
+        let mut text = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n  <trackList>\n",
+        );
+        for entry in entries {
+            let absolute = fs::canonicalize(&entry.path).unwrap_or_else(|_| entry.path.clone());
+            text.push_str("    <track>\n");
+            let _ = writeln!(
+                text,
+                "      <location>{}</location>",
+                Self::xml_escape(&Self::path_to_file_uri(&absolute))
+            );
+            if let Some(ref title) = entry.title {
+                let _ = writeln!(text, "      <title>{}</title>", Self::xml_escape(title));
+            }
+            if let Some(duration) = entry.duration {
+                let _ = writeln!(text, "      <duration>{}</duration>", duration.as_millis());
+            }
+            text.push_str("    </track>\n");
+        }
+        text.push_str("  </trackList>\n</playlist>\n");
+        text
+    }
+
+    /// Returns the contents of every `<name>...</name>` element in `xml`, in document order.
+    /// Attributes and namespace prefixes (`<xspf:track>`) are allowed, nesting of the same
+    /// element is not (XSPF has none).
+    fn xml_elements<'a>(xml: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        let mut rest = xml;
+        std::iter::from_fn(move || loop {
+            let start = rest.find('<')?;
+            rest = &rest[start + 1..];
+            let tag_end = rest.find('>')?;
+            let tag = &rest[..tag_end];
+            let tag_name = tag.split_whitespace().next().unwrap_or("");
+            let local_name = tag_name.rsplit(':').next().unwrap_or("");
+            rest = &rest[tag_end + 1..];
+            if local_name != name || tag.ends_with('/') {
+                continue; // Another element, a closing tag or an empty element.
+            }
+            let close = format!("</{}>", tag_name);
+            let end = rest.find(&close)?;
+            let content = &rest[..end];
+            rest = &rest[end + close.len()..];
+            return Some(content);
+        })
+    }
+
+    /// Replaces the XML entities (`&amp;`, `&#233;`, ...) and strips CDATA markers.
+    fn xml_unescape(text: &str) -> String {
+        if let Some(cdata) = text
+            .strip_prefix("<![CDATA[")
+            .and_then(|t| t.strip_suffix("]]>"))
+        {
+            return cdata.to_string();
+        }
+        let mut unescaped = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('&') {
+            unescaped.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let entity = rest.find(';').map(|end| (&rest[1..end], end));
+            let replacement = entity.and_then(|(name, _)| match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => name
+                    .strip_prefix("#x")
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| name.strip_prefix('#').map(str::parse))
+                    .and_then(Result::ok)
+                    .and_then(char::from_u32),
+            });
+            match (replacement, entity) {
+                (Some(c), Some((_, end))) => {
+                    unescaped.push(c);
+                    rest = &rest[end + 1..];
+                }
+                _ => {
+                    unescaped.push('&'); // Not an entity, keep the ampersand.
+                    rest = &rest[1..];
+                }
+            }
+        }
+        unescaped.push_str(rest);
+        unescaped
+    }
+
+    /// Escapes the characters that are special in XML text.
+    fn xml_escape(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&apos;")
+    }
+
+    /// Returns the path part of a `file://` URI (`file:///music/a.mp3` or
+    /// `file://localhost/music/a.mp3`), still percent-encoded.
+    fn file_uri_path(uri: &str) -> Option<&str> {
+        let rest = uri
+            .get(..7)
+            .filter(|scheme| scheme.eq_ignore_ascii_case("file://"))
+            .map(|_| &uri[7..])?;
+        Some(rest.strip_prefix("localhost").unwrap_or(rest))
+    }
+
+    /// Decodes `%XX` escapes (UTF-8), leaving malformed escapes as they are.
+    fn percent_decode(text: &str) -> String {
+        let bytes = text.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let escaped = (bytes[i] == b'%')
+                .then(|| text.get(i + 1..i + 3))
+                .flatten()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            match escaped {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 3;
+                }
+                None => {
+                    decoded.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&decoded).into_owned()
+    }
+
+    /// Builds a `file://` URI for an absolute path, percent-encoding everything but
+    /// unreserved characters and `/`.
+    fn path_to_file_uri(path: &Path) -> String {
+        let mut uri = String::from("file://");
+        for &byte in path.to_string_lossy().as_bytes() {
+            if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+                uri.push(byte as char);
+            } else {
+                let _ = write!(uri, "%{:02X}", byte);
+            }
+        }
+        uri
+    }
+
+    /// Returns `path` relative to `base` if it lies below it, otherwise as an absolute path.
+    fn relative_to(path: &Path, base: &Path) -> PathBuf {
+        let absolute = |p: &Path| fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+        let (path_abs, base_abs) = (absolute(path), absolute(base));
+        match path_abs.strip_prefix(&base_abs) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => path_abs,
+        }
+    }
+
+    /// Returns the folder a playlist lives in, relative entries are relative to it.
+    fn folder_of(path: &Path) -> PathBuf {
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+
+    /// Returns the lowercase extension of `path`.
+    fn extension(path: &Path) -> String {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::services::temp_folder::TempFolder;
+
+    /// Creates a temporary folder with the given (empty) files.
+    fn fixture(name: &str, files: &[&str]) -> TempFolder {
+        let folder = TempFolder::new(&format!("playlist-{}", name));
+        for file in files {
+            folder.write(file, b"");
+        }
+        folder
+    }
+
+    #[test]
+    fn m3u_reads_extinf_and_skips_comments() {
+        let folder = fixture("m3u", &["a.mp3", "sub/b.mp3"]);
+        let base = folder.path();
+        let text = "\u{feff}#EXTM3U\n#EXTINF:123.5 tvg-id=\"x\",Artist - Song\na.mp3\n# comment\n#EXTINF:-1,\nsub/b.mp3\nmissing.mp3\nhttp://radio/stream\n";
+        let playlist = PlaylistService::parse_m3u(text, base);
+
+        assert_eq!(playlist.entries.len(), 2);
+        assert_eq!(playlist.entries[0].path, base.join("a.mp3"));
+        assert_eq!(playlist.entries[0].title.as_deref(), Some("Artist - Song"));
+        assert_eq!(
+            playlist.entries[0].duration,
+            Some(Duration::from_millis(123_500))
+        );
+        assert_eq!(playlist.entries[1].path, base.join("sub/b.mp3"));
+        assert_eq!(playlist.entries[1].title, None);
+        assert_eq!(playlist.entries[1].duration, None);
+        assert_eq!(playlist.missing, vec!["missing.mp3", "http://radio/stream"]);
+    }
+
+    #[test]
+    fn m3u_resolves_file_uris() {
+        let folder = fixture("m3u-uri", &["my song.mp3"]);
+        let base = folder.path();
+        let uri = format!("file://{}/my%20song.mp3", base.display());
+        let playlist = PlaylistService::parse_m3u(&uri, Path::new("/elsewhere"));
+        assert_eq!(playlist.entries.len(), 1);
+        assert_eq!(playlist.entries[0].path, base.join("my song.mp3"));
+    }
+
+    #[test]
+    fn pls_orders_entries_by_number() {
+        let folder = fixture("pls", &["one.mp3", "two.mp3"]);
+        let base = folder.path();
+        let text = "[playlist]\nFile2=two.mp3\nTitle2=Second\nFile1=one.mp3\nLength1=61\nLength2=-1\nTitle3=No file\nFile4=gone.mp3\nNumberOfEntries=4\nVersion=2\n";
+        let playlist = PlaylistService::parse_pls(text, base);
+
+        let paths: Vec<&Path> = playlist.entries.iter().map(|e| e.path.as_path()).collect();
+        assert_eq!(paths, vec![base.join("one.mp3"), base.join("two.mp3")]);
+        assert_eq!(playlist.entries[0].duration, Some(Duration::from_secs(61)));
+        assert_eq!(playlist.entries[0].title, None);
+        assert_eq!(playlist.entries[1].title.as_deref(), Some("Second"));
+        assert_eq!(playlist.entries[1].duration, None);
+        assert_eq!(playlist.missing, vec!["gone.mp3"]);
+    }
+
+    #[test]
+    fn xspf_reads_locations_titles_and_durations() {
+        let folder = fixture("xspf", &["a b.mp3", "café.mp3"]);
+        let base = folder.path();
+        let text = format!(
+            "<?xml version=\"1.0\"?>\n<playlist xmlns=\"http://xspf.org/ns/0/\"><trackList>\
+             <track><location>file://{}/a%20b.mp3</location><title>Rock &amp; Roll</title><duration>1500</duration></track>\
+             <track><location>caf%C3%A9.mp3</location><title><![CDATA[<Live>]]></title></track>\
+             <track><title>No location</title></track>\
+             <track><location>missing.mp3</location></track>\
+             </trackList></playlist>",
+            base.display()
+        );
+        let playlist = PlaylistService::parse_xspf(&text, base);
+
+        assert_eq!(playlist.entries.len(), 2);
+        assert_eq!(playlist.entries[0].path, base.join("a b.mp3"));
+        assert_eq!(playlist.entries[0].title.as_deref(), Some("Rock & Roll"));
+        assert_eq!(
+            playlist.entries[0].duration,
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(playlist.entries[1].path, base.join("café.mp3"));
+        assert_eq!(playlist.entries[1].title.as_deref(), Some("<Live>"));
+        assert_eq!(playlist.missing, vec!["missing.mp3"]);
+    }
+
+    #[test]
+    fn percent_decode_handles_utf8_and_malformed_escapes() {
+        assert_eq!(PlaylistService::percent_decode("a%20b%C3%A9"), "a bé");
+        assert_eq!(PlaylistService::percent_decode("100%"), "100%");
+        assert_eq!(PlaylistService::percent_decode("%zz%4"), "%zz%4");
+        assert_eq!(PlaylistService::percent_decode("%2f%2F"), "//");
+    }
+
+    #[test]
+    fn file_uris_round_trip() {
+        let path = Path::new("/music/AC DC/Ça & co #1.mp3");
+        let uri = PlaylistService::path_to_file_uri(path);
+        assert_eq!(uri, "file:///music/AC%20DC/%C3%87a%20%26%20co%20%231.mp3");
+        let decoded =
+            PlaylistService::percent_decode(PlaylistService::file_uri_path(&uri).unwrap());
+        assert_eq!(Path::new(&decoded), path);
+        assert_eq!(
+            PlaylistService::file_uri_path("FILE://localhost/x"),
+            Some("/x")
+        );
+        assert_eq!(PlaylistService::file_uri_path("http://x"), None);
+    }
+
+    #[test]
+    fn saved_playlists_load_with_titles_and_durations() {
+        let folder = fixture("save", &["a.mp3", "b.mp3"]);
+        let base = folder.path();
+        let entries = vec![
+            PlaylistEntry {
+                title: Some("Tom & Jerry <live>".to_string()),
+                duration: Some(Duration::from_secs(90)),
+                ..PlaylistEntry::new(base.join("a.mp3"))
+            },
+            PlaylistEntry::new(base.join("b.mp3")),
+        ];
+        for name in ["list.m3u8", "list.m3u", "list.xspf"] {
+            let path = base.join(name);
+            PlaylistService.save(&path, &entries).unwrap();
+            let loaded = PlaylistService.load(&path).unwrap();
+            assert_eq!(loaded.entries.len(), 2, "{}", name);
+            assert_eq!(loaded.entries[0].title, entries[0].title, "{}", name);
+            assert_eq!(loaded.entries[0].duration, entries[0].duration, "{}", name);
+            assert_eq!(loaded.entries[1].duration, None, "{}", name);
+        }
+    }
+}
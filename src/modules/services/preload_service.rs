@@ -0,0 +1,373 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Instant, SystemTime};
+
+use log::{debug, error};
+
+use super::audio_loader::{AudioLoader, DecodedAudio};
+
+/// Default memory budget of the decoded-audio cache (about 12 minutes of 44.1 kHz stereo).
+pub const DEFAULT_CACHE_BUDGET: usize = 256 * 1024 * 1024;
+
+/// A decode request handed to the worker thread.
+struct PreloadJob {
+    path: PathBuf,
+    generation: usize, // The request is stale once the service generation moved past it.
+}
+
+/// Identifies the contents of a file by its path, modification time and size, so audio
+/// decoded before the file was rewritten (e.g. its tags) is not mistaken for the new one.
+#[derive(Clone, Debug, PartialEq)]
+struct FileKey {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    size: u64,
+}
+
+impl FileKey {
+    /// Reads the key of `path` from the file system. A file that cannot be read gets an
+    /// empty key, decoding it fails anyway.
+    fn of(path: &Path) -> Self {
+        let metadata = fs::metadata(path).ok();
+        FileKey {
+            path: path.to_path_buf(),
+            modified: metadata
+                .as_ref()
+                .and_then(|metadata| metadata.modified().ok()),
+            size: metadata.map_or(0, |metadata| metadata.len()),
+        }
+    }
+}
+
+/// The decode the worker is busy with, so `load` can wait for it instead of decoding the
+/// same file a second time.
+#[derive(Default)]
+struct InFlight {
+    key: Option<FileKey>,
+    wanted: bool, // `load` waits for the result, keep it even if the request went stale.
+}
+
+/// **PreloadCache Struct**
+///
+/// Decoded tracks ordered from least to most recently used, bounded by a byte budget.
+struct PreloadCache {
+    entries: VecDeque<(FileKey, Arc<DecodedAudio>)>,
+    bytes: usize,
+    budget: usize,
+}
+
+impl PreloadCache {
+    fn get(&mut self, key: &FileKey) -> Option<Arc<DecodedAudio>> {
+        let position = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(position)?;
+        let audio = Arc::clone(&entry.1);
+        self.entries.push_back(entry); // Mark as most recently used.
+        Some(audio)
+    }
+
+    fn contains(&self, key: &FileKey) -> bool {
+        self.entries.iter().any(|(k, _)| k == key)
+    }
+
+    /// Inserts decoded audio, evicting the least recently used entries to stay in budget.
+    /// Older decodes of the same file are dropped. Audio larger than the whole budget is
+    /// not cached.
+    fn insert(&mut self, key: FileKey, audio: Arc<DecodedAudio>) {
+        let size = audio.size_in_bytes();
+        if self.contains(&key) {
+            return;
+        }
+        let bytes = &mut self.bytes;
+        self.entries.retain(|(k, stale)| {
+            let keep = k.path != key.path;
+            if !keep {
+                *bytes -= stale.size_in_bytes();
+            }
+            keep
+        });
+        if size > self.budget {
+            return;
+        }
+        while self.bytes + size > self.budget {
+            match self.entries.pop_front() {
+                Some((_, evicted)) => self.bytes -= evicted.size_in_bytes(),
+                None => break,
+            }
+        }
+        self.bytes += size;
+        self.entries.push_back((key, audio));
+    }
+}
+
+/// **PreloadService Struct**
+///
+/// Decodes upcoming tracks on a background worker thread so that switching tracks does not
+/// block the caller on decoding. Only the most recent request is worked on: a new request
+/// (or `cancel`) makes older ones stale, and their results are thrown away.
+pub struct PreloadService {
+    loader: Arc<dyn AudioLoader>,
+    cache: Arc<Mutex<PreloadCache>>,
+    /// The audio `load` handed out last, kept outside the budget: the stream playing it holds
+    /// it anyway, and the other CUE tracks of a large file must not decode it again.
+    last_loaded: Mutex<Option<(FileKey, Arc<DecodedAudio>)>>,
+    /// The worker's current decode, signalled when it is done.
+    in_flight: Arc<(Mutex<InFlight>, Condvar)>,
+    generation: Arc<AtomicUsize>,
+    jobs: Sender<PreloadJob>,
+}
+
+impl PreloadService {
+    /// Creates the service and starts its worker thread.
+    ///
+    /// # Parameters:
+    /// - `loader`: The loader used to decode files, shared with the caller.
+    /// - `budget`: The maximum number of bytes of decoded audio kept in the cache.
+    pub fn new(loader: Arc<dyn AudioLoader>, budget: usize) -> Self {
+        let cache = Arc::new(Mutex::new(PreloadCache {
+            entries: VecDeque::new(),
+            bytes: 0,
+            budget,
+        }));
+        let in_flight = Arc::new((Mutex::new(InFlight::default()), Condvar::new()));
+        let generation = Arc::new(AtomicUsize::new(0));
+        let (jobs, receiver) = mpsc::channel::<PreloadJob>();
+
+        let worker_loader = Arc::clone(&loader);
+        let worker_cache = Arc::clone(&cache);
+        let worker_in_flight = Arc::clone(&in_flight);
+        let worker_generation = Arc::clone(&generation);
+        thread::spawn(move || {
+            while let Ok(mut job) = receiver.recv() {
+                // Skip to the newest request, the older ones are stale anyway.
+                while let Ok(newer) = receiver.try_recv() {
+                    job = newer;
+                }
+                let is_current = || worker_generation.load(Ordering::SeqCst) == job.generation;
+                // Taken before decoding, a file changed meanwhile is decoded again on load.
+                let key = FileKey::of(&job.path);
+                let (in_flight, done) = &*worker_in_flight;
+                {
+                    let mut in_flight = in_flight.lock().unwrap();
+                    if !is_current() || worker_cache.lock().unwrap().contains(&key) {
+                        continue;
+                    }
+                    *in_flight = InFlight {
+                        key: Some(key.clone()),
+                        wanted: false,
+                    };
+                }
+
+                let start_time = Instant::now();
+                // A panicking decoder must not take the worker down with it, later requests
+                // would never be served.
+                let decoded =
+                    panic::catch_unwind(AssertUnwindSafe(|| worker_loader.decode(&job.path)));
+                let mut in_flight = in_flight.lock().unwrap();
+                match decoded {
+                    Ok(Ok(audio)) if is_current() || in_flight.wanted => {
+                        debug!(
+                            "Preloaded {:?} in {:?}.",
+                            job.path.display(),
+                            start_time.elapsed()
+                        );
+                        worker_cache.lock().unwrap().insert(key, Arc::new(audio));
+                    }
+                    Ok(Ok(_)) => {} // Cancelled while decoding, drop the result.
+                    Ok(Err(err)) => error!("Failed to preload {:?}: {}", job.path, err),
+                    Err(_) => error!("Decoder panicked while preloading {:?}.", job.path),
+                }
+                *in_flight = InFlight::default();
+                done.notify_all();
+            }
+        });
+
+        PreloadService {
+            loader,
+            cache,
+            last_loaded: Mutex::new(None),
+            in_flight,
+            generation,
+            jobs,
+        }
+    }
+
+    /// Asks the worker to decode `path` ahead of time, cancelling any earlier request.
+    /// The file loaded last (the next CUE track of the same file) is not decoded again,
+    /// unless it changed since.
+    pub fn request(&self, path: &Path) {
+        let key = FileKey::of(path);
+        let is_last_loaded = matches!(
+            *self.last_loaded.lock().unwrap(),
+            Some((ref last_key, _)) if *last_key == key
+        );
+        if is_last_loaded {
+            self.cancel();
+            return;
+        }
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let _ = self.jobs.send(PreloadJob {
+            path: path.to_path_buf(),
+            generation,
+        });
+    }
+
+    /// Cancels outstanding requests, e.g. because the play order changed.
+    pub fn cancel(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Returns the decoded audio for `path`, from the cache when it was preloaded or is the
+    /// file loaded last (another CUE track of it), otherwise decoded right away on the
+    /// calling thread (and cached). A file whose modification time or size changed since
+    /// it was decoded is decoded again. If the worker is decoding the file right now, its
+    /// result is waited for instead.
+    ///
+    /// # Returns:
+    /// - `Ok(Arc<DecodedAudio>)`: The decoded audio.
+    /// - `Err(Box<dyn Error>)`: If decoding fails.
+    pub fn load(&self, path: &Path) -> Result<Arc<DecodedAudio>, Box<dyn Error>> {
+        let key = FileKey::of(path);
+        let mut last_loaded = self.last_loaded.lock().unwrap();
+        match *last_loaded {
+            Some((ref last_key, ref audio)) if *last_key == key => {
+                debug!("Reusing the decoded audio of {:?}.", path.display());
+                return Ok(Arc::clone(audio));
+            }
+            _ => {}
+        }
+        let cached = {
+            let (in_flight, done) = &*self.in_flight;
+            let mut in_flight = in_flight.lock().unwrap();
+            while in_flight.key.as_ref() == Some(&key) {
+                debug!("Waiting for the preload of {:?}.", path.display());
+                in_flight.wanted = true;
+                in_flight = done.wait(in_flight).unwrap();
+            }
+            self.cache.lock().unwrap().get(&key)
+        };
+        let audio = match cached {
+            Some(audio) => {
+                debug!("Using preloaded audio for {:?}.", path.display());
+                audio
+            }
+            None => {
+                let audio = Arc::new(self.loader.decode(path)?);
+                self.cache
+                    .lock()
+                    .unwrap()
+                    .insert(key.clone(), Arc::clone(&audio));
+                audio
+            }
+        };
+        *last_loaded = Some((key, Arc::clone(&audio)));
+        Ok(audio)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::services::audio_loader::DynamicAudioLoader;
+    use crate::modules::services::playback_handle::PlaybackHandle;
+    use crate::modules::services::temp_folder::TempFolder;
+    use cpal::Stream;
+    use hound::{SampleFormat, WavSpec, WavWriter};
+    use std::sync::mpsc::Receiver;
+    use std::time::Duration;
+
+    /// Counts its decodes and reports when one starts, then takes a while to finish it.
+    struct SlowLoader {
+        started: Mutex<Sender<()>>,
+        decodes: AtomicUsize,
+    }
+
+    impl AudioLoader for SlowLoader {
+        fn decode(&self, _file_path: &Path) -> Result<DecodedAudio, Box<dyn Error>> {
+            self.decodes.fetch_add(1, Ordering::SeqCst);
+            let _ = self.started.lock().unwrap().send(());
+            thread::sleep(Duration::from_millis(50));
+            Ok(DecodedAudio {
+                samples: vec![0.0; 4],
+                channels: 1,
+                sample_rate: 8000,
+            })
+        }
+
+        fn create_audio_stream(
+            &self,
+            _audio: Arc<DecodedAudio>,
+            _handle: Arc<PlaybackHandle>,
+        ) -> Result<Stream, Box<dyn Error>> {
+            Err("no output device".into())
+        }
+    }
+
+    fn slow_service() -> (PreloadService, Arc<SlowLoader>, Receiver<()>) {
+        let (started, receiver) = mpsc::channel();
+        let loader = Arc::new(SlowLoader {
+            started: Mutex::new(started),
+            decodes: AtomicUsize::new(0),
+        });
+        let service = PreloadService::new(loader.clone(), DEFAULT_CACHE_BUDGET);
+        (service, loader, receiver)
+    }
+
+    /// Writes a mono 16-bit WAV file with `frames` silent frames.
+    fn write_wav(path: &Path, frames: usize) {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let mut writer = WavWriter::create(path, spec).unwrap();
+        for _ in 0..frames {
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    #[test]
+    fn load_waits_for_the_preload_of_the_same_file() {
+        let (service, loader, started) = slow_service();
+        service.request(Path::new("next.wav"));
+        started.recv().unwrap();
+        service.load(Path::new("next.wav")).unwrap();
+        assert_eq!(loader.decodes.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn a_cancelled_preload_is_kept_when_it_is_loaded() {
+        let (service, loader, started) = slow_service();
+        service.request(Path::new("next.wav"));
+        started.recv().unwrap();
+        service.cancel();
+        service.load(Path::new("next.wav")).unwrap();
+        assert_eq!(loader.decodes.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn changed_files_are_decoded_again() {
+        let folder = TempFolder::new("preload-changed");
+        let path = folder.path().join("track.wav");
+        let service = PreloadService::new(Arc::new(DynamicAudioLoader), DEFAULT_CACHE_BUDGET);
+
+        write_wav(&path, 2);
+        assert_eq!(service.load(&path).unwrap().samples.len(), 2);
+        assert_eq!(service.load(&path).unwrap().samples.len(), 2);
+
+        // Rewritten (a tag edit, or a watcher `Changed` event): the old decode is stale.
+        write_wav(&path, 5);
+        assert_eq!(service.load(&path).unwrap().samples.len(), 5);
+        let cache = service.cache.lock().unwrap();
+        assert_eq!(cache.entries.len(), 1, "the stale decode is dropped");
+        assert_eq!(cache.bytes, cache.entries[0].1.size_in_bytes());
+    }
+}
@@ -0,0 +1,210 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use log::{info, warn};
+
+use crate::modules::models::audio_folder_model::RepeatMode;
+use crate::modules::models::crossfade_model::{CrossfadeModel, FadeCurve};
+use crate::modules::models::session_model::SessionModel;
+use crate::modules::services::state_file::{self, StateFolder};
+
+/// Environment variable with the path of the session file.
+pub const SESSION_FILE_VAR: &str = "SENSIT_SESSION";
+
+/// First line of a session file, the version changes when the keys do.
+const SESSION_HEADER: &str = "# sensit session v1";
+
+/// **SessionService Struct**
+///
+/// Keeps the playback session in a small file: one `key<TAB>value` line per setting and
+/// one `queue` line per queued track. Written when the player quits, offered for restoring
+/// on the next start.
+pub struct SessionService {
+    /// The session file.
+    path: PathBuf,
+}
+
+impl SessionService {
+    /// Creates a service that keeps the session in `path`.
+    pub fn new(path: PathBuf) -> Self {
+        SessionService { path }
+    }
+
+    /// Uses the file named by `SENSIT_SESSION`, or `sensit/session.tsv` in the user's state
+    /// folder (`$XDG_STATE_HOME`, `~/.local/state`), or `session.tsv` in the working folder.
+    pub fn from_env() -> Self {
+        let path = state_file::path(SESSION_FILE_VAR, StateFolder::State, "session.tsv");
+        Self::new(path)
+    }
+
+    /// **Load the Session**
+    ///
+    /// # Returns:
+    /// - `Ok(Some(SessionModel))`: The saved session. Unknown keys and broken values are
+    ///   logged and skipped.
+    /// - `Ok(None)`: If there is no session file yet.
+    /// - `Err(Box<dyn Error>)`: If the file cannot be read, has another version or names
+    ///   no source.
+    pub fn load(&self) -> Result<Option<SessionModel>, Box<dyn Error>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let mut lines = text.lines();
+        if lines.next() != Some(SESSION_HEADER) {
+            return Err(format!("{:?} is not a session file", self.path.display()).into());
+        }
+
+        let mut source = None;
+        let (mut crossfade, mut curve) = (Duration::ZERO, FadeCurve::EqualPower);
+        let mut session = SessionModel {
+            source: PathBuf::new(),
+            track: None,
+            index: 0,
+            position: Duration::ZERO,
+            playing: false,
+            volume: 1.0,
+            shuffle: false,
+            repeat: RepeatMode::Off,
+            crossfade: CrossfadeModel::disabled(),
+            queue: Vec::new(),
+        };
+        for (number, line) in lines.enumerate() {
+            let Some((key, value)) = line.split_once('\t') else {
+                warn!("Skipping broken session line {}.", number + 2);
+                continue;
+            };
+            let value = state_file::unescape(value);
+            let parsed = match key {
+                "source" => {
+                    source = Some(PathBuf::from(value));
+                    true
+                }
+                "track" => {
+                    session.track = Some(PathBuf::from(value));
+                    true
+                }
+                "queue" => {
+                    session.queue.push(PathBuf::from(value));
+                    true
+                }
+                "index" => value.parse().map(|index| session.index = index).is_ok(),
+                "position" => (value.parse())
+                    .map(|millis| session.position = Duration::from_millis(millis))
+                    .is_ok(),
+                "playing" => value
+                    .parse()
+                    .map(|playing| session.playing = playing)
+                    .is_ok(),
+                "volume" => value.parse().map(|volume| session.volume = volume).is_ok(),
+                "shuffle" => value
+                    .parse()
+                    .map(|shuffle| session.shuffle = shuffle)
+                    .is_ok(),
+                "repeat" => (RepeatMode::from_name(&value))
+                    .map(|repeat| session.repeat = repeat)
+                    .is_some(),
+                "crossfade" => (value.parse())
+                    .map(|millis| crossfade = Duration::from_millis(millis))
+                    .is_ok(),
+                "curve" => (FadeCurve::from_name(&value))
+                    .map(|name| curve = name)
+                    .is_some(),
+                _ => false,
+            };
+            if !parsed {
+                warn!("Skipping broken session line {}.", number + 2);
+            }
+        }
+        session.crossfade = CrossfadeModel::new(crossfade, curve);
+        session.source = source.ok_or("the session names no folder or playlist")?;
+        info!("Loaded the session of {:?}.", session.source.display());
+        Ok(Some(session))
+    }
+
+    /// **Save the Session**
+    ///
+    /// Writes a temporary file next to the session file and renames it over the old one,
+    /// so an interrupted save keeps the previous session.
+    pub fn save(&self, session: &SessionModel) -> Result<(), Box<dyn Error>> {
+        let path = |path: &Path| state_file::escape(&path.to_string_lossy());
+        let mut lines = vec![
+            SESSION_HEADER.to_string(),
+            format!("source\t{}", path(&session.source)),
+        ];
+        if let Some(ref track) = session.track {
+            lines.push(format!("track\t{}", path(track)));
+        }
+        lines.push(format!("index\t{}", session.index));
+        lines.push(format!("position\t{}", session.position.as_millis()));
+        lines.push(format!("playing\t{}", session.playing));
+        lines.push(format!("volume\t{}", session.volume));
+        lines.push(format!("shuffle\t{}", session.shuffle));
+        lines.push(format!("repeat\t{:?}", session.repeat));
+        lines.push(format!(
+            "crossfade\t{}",
+            session.crossfade.duration().as_millis()
+        ));
+        lines.push(format!("curve\t{}", session.crossfade.curve().name()));
+        for track in &session.queue {
+            lines.push(format!("queue\t{}", path(track)));
+        }
+        state_file::write(&self.path, &(lines.join("\n") + "\n"))?;
+        info!("Saved the session to {:?}.", self.path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::services::temp_folder::TempFolder;
+
+    #[test]
+    fn sessions_survive_a_round_trip() {
+        let folder = TempFolder::new("session-save");
+        let service = SessionService::new(folder.path().join("state/session.tsv"));
+        assert_eq!(service.load().unwrap(), None, "no file yet");
+        let session = SessionModel {
+            source: PathBuf::from("/music/Live\t1999"),
+            track: Some(PathBuf::from("/music/Live\t1999/02 b.flac")),
+            index: 1,
+            position: Duration::from_millis(83_250),
+            playing: true,
+            volume: 0.35,
+            shuffle: true,
+            repeat: RepeatMode::All,
+            crossfade: CrossfadeModel::new(Duration::from_millis(2500), FadeCurve::Logarithmic),
+            queue: vec![PathBuf::from("/other/x.mp3"), PathBuf::from("/other/y.mp3")],
+        };
+        service.save(&session).unwrap();
+        assert_eq!(service.load().unwrap(), Some(session));
+    }
+
+    #[test]
+    fn broken_values_keep_the_defaults() {
+        let folder = TempFolder::new("session-broken");
+        let path = folder.write(
+            "session.tsv",
+            format!(
+                "{}\nsource\t/music\nindex\tthird\nvolume\t0.5\nrepeat\tsometimes\n\
+                 crossfade\t3000\ncurve\tcubic\nno tab here\n",
+                SESSION_HEADER
+            ),
+        );
+        let session = SessionService::new(path).load().unwrap().unwrap();
+        assert_eq!(session.source, PathBuf::from("/music"));
+        assert_eq!((session.index, session.volume), (0, 0.5));
+        assert_eq!(session.repeat, RepeatMode::Off);
+        assert_eq!(
+            session.crossfade,
+            CrossfadeModel::new(Duration::from_secs(3), FadeCurve::EqualPower)
+        );
+
+        let nameless = folder.write("nameless.tsv", format!("{}\nindex\t2\n", SESSION_HEADER));
+        assert!(SessionService::new(nameless).load().is_err());
+    }
+}
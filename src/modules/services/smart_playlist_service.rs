@@ -0,0 +1,156 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use log::{info, warn};
+
+use crate::modules::models::audio_folder_model::natural_cmp;
+use crate::modules::models::smart_playlist_model::SmartPlaylist;
+use crate::modules::services::state_file::{self, StateFolder};
+
+/// Environment variable with the folder of the smart playlist definitions.
+pub const SMART_PLAYLISTS_VAR: &str = "SENSIT_SMART_PLAYLISTS";
+
+/// Extension of smart playlist definition files.
+const SMART_EXTENSION: &str = "smart";
+
+/// First line of a definition written by the player, other `#` lines are comments too.
+const SMART_HEADER: &str = "# sensit smart playlist v1";
+
+/// **SmartPlaylistService Struct**
+///
+/// Keeps smart playlist definitions in a folder, one `<name>.smart` file each holding its
+/// rules. The files are plain text and can be edited by hand: lines starting with `#` are
+/// comments, the other lines are joined into the rules. Only the rules are stored, the
+/// tracks are found again every time a playlist is played.
+pub struct SmartPlaylistService {
+    /// The folder of the definitions.
+    folder: PathBuf,
+}
+
+impl SmartPlaylistService {
+    /// Creates a service that keeps the definitions in `folder`.
+    pub fn new(folder: PathBuf) -> Self {
+        SmartPlaylistService { folder }
+    }
+
+    /// Uses the folder named by `SENSIT_SMART_PLAYLISTS`, or `sensit/smart` in the user's
+    /// data folder (`$XDG_DATA_HOME`, `~/.local/share`), or `smart` in the working folder.
+    pub fn from_env() -> Self {
+        let folder = state_file::path(SMART_PLAYLISTS_VAR, StateFolder::Data, "smart");
+        Self::new(folder)
+    }
+
+    /// **List the Smart Playlists**
+    ///
+    /// # Returns:
+    /// - `Ok(Vec<SmartPlaylist>)`: Every definition in natural name order, empty if the
+    ///   folder does not exist yet. Files that cannot be read or parsed are logged and skipped.
+    /// - `Err(Box<dyn Error>)`: If the folder cannot be read.
+    pub fn list(&self) -> Result<Vec<SmartPlaylist>, Box<dyn Error>> {
+        let entries = match fs::read_dir(&self.folder) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut playlists = Vec::new();
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some(SMART_EXTENSION) {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            match self.load(name) {
+                Ok(playlist) => playlists.push(playlist),
+                Err(err) => warn!("Skipping smart playlist {:?}: {}", path.display(), err),
+            }
+        }
+        playlists.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+        Ok(playlists)
+    }
+
+    /// **Load a Smart Playlist**
+    ///
+    /// # Parameters:
+    /// - `name`: The name of the playlist, its file name without `.smart`.
+    ///
+    /// # Returns:
+    /// - `Ok(SmartPlaylist)`: The parsed definition.
+    /// - `Err(Box<dyn Error>)`: If the name is invalid, there is no such playlist, or its
+    ///   rules cannot be parsed.
+    pub fn load(&self, name: &str) -> Result<SmartPlaylist, Box<dyn Error>> {
+        let path = self.path(name)?;
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Err(format!("No smart playlist {:?}", name).into())
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let rules: Vec<&str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        SmartPlaylist::parse(name, &rules.join(" "))
+            .ok_or_else(|| format!("Invalid rules in {:?}", path.display()).into())
+    }
+
+    /// **Save a Smart Playlist**
+    ///
+    /// Writes the rules to `<name>.smart` (creating the folder if needed) through a
+    /// temporary file, replacing the playlist of the same name.
+    pub fn save(&self, playlist: &SmartPlaylist) -> Result<(), Box<dyn Error>> {
+        let path = self.path(&playlist.name)?;
+        state_file::write(&path, &format!("{}\n{}\n", SMART_HEADER, playlist.rules))?;
+        info!("Saved smart playlist {:?}.", path.display());
+        Ok(())
+    }
+
+    /// **Delete a Smart Playlist**
+    ///
+    /// # Returns:
+    /// - `Ok(true)`: If the definition was deleted.
+    /// - `Ok(false)`: If there is no playlist of that name.
+    /// - `Err(Box<dyn Error>)`: If the name is invalid or the file cannot be deleted.
+    pub fn remove(&self, name: &str) -> Result<bool, Box<dyn Error>> {
+        match fs::remove_file(self.path(name)?) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Returns the file of the playlist `name`, an error if the name would lead out of
+    /// the folder (see `SmartPlaylist::is_valid_name`).
+    fn path(&self, name: &str) -> Result<PathBuf, Box<dyn Error>> {
+        if !SmartPlaylist::is_valid_name(name) {
+            return Err(format!("Invalid smart playlist name {:?}", name).into());
+        }
+        Ok(self.folder.join(format!("{}.{}", name, SMART_EXTENSION)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::services::temp_folder::TempFolder;
+
+    #[test]
+    fn names_cannot_leave_the_folder() {
+        let root = TempFolder::new("smart-names");
+        let service = SmartPlaylistService::new(root.path().join("smart"));
+        let outside = root.write("outside.smart", "genre:jazz\n");
+        for name in ["../outside", "", ".hidden", "a\\b"] {
+            assert!(service.remove(name).is_err(), "{:?}", name);
+            assert!(service.load(name).is_err(), "{:?}", name);
+        }
+        assert!(outside.exists());
+        let playlist = SmartPlaylist::parse("jazz", "genre:jazz").unwrap();
+        service.save(&playlist).unwrap();
+        assert_eq!(service.load("jazz").unwrap(), playlist);
+        assert!(service.remove("jazz").unwrap());
+        assert!(!service.remove("jazz").unwrap());
+    }
+}
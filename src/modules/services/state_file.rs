@@ -0,0 +1,120 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Numbers the temporary files of `write`, so two saves running at once never share one.
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// `StateFolder` is the XDG base folder a state file belongs in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateFolder {
+    /// User data worth keeping (`$XDG_DATA_HOME`, `~/.local/share`): the library, the
+    /// bookmarks, the history, smart playlists.
+    Data,
+    /// State that can be lost (`$XDG_STATE_HOME`, `~/.local/state`): the session.
+    State,
+}
+
+/// **Path of a State File**
+///
+/// # Parameters:
+/// - `var`: The environment variable that names the file (or folder) directly.
+/// - `folder`: The XDG base folder used without `var`.
+/// - `name`: The file (or folder) name, below `sensit` in the base folder.
+///
+/// # Returns:
+/// - The path named by `var`, or else `sensit/<name>` in the base folder, or else `name`
+///   in the working folder if neither the XDG variable nor `HOME` is set.
+pub fn path(var: &str, folder: StateFolder, name: &str) -> PathBuf {
+    let (xdg_var, fallback) = match folder {
+        StateFolder::Data => ("XDG_DATA_HOME", ".local/share"),
+        StateFolder::State => ("XDG_STATE_HOME", ".local/state"),
+    };
+    env::var_os(var)
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os(xdg_var)
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(fallback)))
+                .map(|base| base.join("sensit").join(name))
+        })
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+/// **Write a State File**
+///
+/// Writes `text` to a temporary file next to `path` and renames it over `path`, creating
+/// the folder first if needed, so an interrupted save keeps the previous file.
+pub fn write(path: &Path, text: &str) -> io::Result<()> {
+    let folder = path
+        .parent()
+        .filter(|folder| !folder.as_os_str().is_empty());
+    if let Some(folder) = folder {
+        fs::create_dir_all(folder)?;
+    }
+    let mut temp = path.as_os_str().to_owned();
+    let number = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    temp.push(format!(".{}-{}.tmp", process::id(), number));
+    fs::write(&temp, text)?;
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+/// Escapes the characters that separate columns and lines of the tab-separated state
+/// files.
+pub fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Reverts `escape`.
+pub fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::services::temp_folder::TempFolder;
+
+    #[test]
+    fn escaped_text_keeps_to_one_column() {
+        let text = "a\tb\\tc\nd\re\\";
+        let escaped = escape(text);
+        assert!(!escaped.contains(['\t', '\n', '\r']));
+        assert_eq!(unescape(&escaped), text);
+        assert_eq!(unescape("trailing\\"), "trailing\\");
+    }
+
+    #[test]
+    fn writes_replace_the_file() {
+        let folder = TempFolder::new("state-write");
+        let path = folder.path().join("sub").join("state.tsv");
+        write(&path, "first\n").unwrap();
+        write(&path, "second\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+        let files = fs::read_dir(path.parent().unwrap()).unwrap().count();
+        assert_eq!(files, 1, "no temporary file is left behind");
+    }
+}
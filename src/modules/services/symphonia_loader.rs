@@ -0,0 +1,93 @@
+use log::warn;
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::audio::{AudioBufferRef, Signal};
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use super::audio_loader::DecodedAudio;
+
+/// Decodes the first audio track of a file through symphonia.
+///
+/// # Parameters
+/// - path: The path to the audio file.
+/// - extension: The file extension given to symphonia as a hint for the format.
+///
+/// # Returns
+/// - Ok(DecodedAudio): The samples of all channels, interleaved, the channel count and sample rate.
+/// - Err(Box<dyn Error>): An error if the file cannot be opened or decoded.
+pub fn decode(path: &Path, extension: &str) -> Result<DecodedAudio, Box<dyn Error>> {
+    let src = File::open(path)?;
+    let mss = MediaSourceStream::new(Box::new(src), Default::default());
+
+    let mut hint = Hint::new();
+    hint.with_extension(extension);
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|err| format!("Unsupported format: {}", err))?;
+
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or("No supported audio tracks found.")?;
+
+    let channels = track
+        .codec_params
+        .channels
+        .ok_or("Channel count missing.")?
+        .count();
+
+    let sample_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or("Sample rate missing.")?;
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|err| format!("Unsupported codec: {}", err))?;
+
+    let mut samples = Vec::new();
+    let track_id = track.id;
+
+    while let Ok(packet) = format.next_packet() {
+        if packet.track_id() != track_id {
+            continue;
+        }
+        // The decoder keeps one plane per channel, the player wants them interleaved.
+        match decoder.decode(&packet) {
+            Ok(AudioBufferRef::F32(buffer)) => {
+                for frame in 0..buffer.frames() {
+                    samples.extend((0..channels).map(|channel| buffer.chan(channel)[frame]));
+                }
+            }
+            Ok(AudioBufferRef::S32(buffer)) => {
+                for frame in 0..buffer.frames() {
+                    samples.extend(
+                        (0..channels)
+                            .map(|channel| buffer.chan(channel)[frame] as f32 / i32::MAX as f32),
+                    );
+                }
+            }
+            Err(err) => warn!("Decode error: {:?}", err),
+        }
+    }
+
+    Ok(DecodedAudio {
+        samples,
+        channels: channels as u16,
+        sample_rate,
+    })
+}
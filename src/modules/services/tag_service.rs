@@ -0,0 +1,1136 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use log::{debug, info, warn};
+
+use crate::modules::models::track_metadata_model::TagField;
+use crate::modules::services::metadata_service::ID3V1_GENRES;
+
+/// One tag change: the field and its new value, `None` removes the field.
+pub type TagChange = (TagField, Option<String>);
+
+/// An ID3v2 frame: id, flags and content.
+pub type Id3Frame = ([u8; 4], [u8; 2], Vec<u8>);
+
+/// A sub-chunk of a RIFF `INFO` list: id and content.
+type InfoEntry = ([u8; 4], Vec<u8>);
+
+/// ID3v2.3 frames that have no ID3v2.4 equivalent and are dropped when a tag is upgraded.
+const ID3V23_ONLY_FRAMES: [&[u8; 4]; 6] = [b"TDAT", b"TIME", b"TRDA", b"TSIZ", b"RVAD", b"EQUA"];
+
+/// `POPM` rating bytes of 1 to 5 stars, the values most players write.
+const POPM_RATINGS: [u8; 5] = [1, 64, 128, 196, 255];
+
+/// User of the `POPM` frames written, the one Windows (and most taggers) read.
+const POPM_EMAIL: &str = "Windows Media Player 9 Series";
+
+/// **TagService Struct**
+///
+/// Writes tags back to audio files: ID3v2.4 for MP3, Vorbis comments for FLAC and
+/// Ogg (Vorbis, Opus) and RIFF INFO for WAV. Fields that are not edited, other frames
+/// and pictures are kept. The new file is written next to the old one and renamed over
+/// it, so a crash leaves either the old or the new file, never a broken one.
+pub struct TagService;
+
+impl TagService {
+    /// **Write Tags to a File**
+    ///
+    /// # Parameters:
+    /// - `path`: The audio file.
+    /// - `changes`: The fields to set (or remove with `None`).
+    ///
+    /// # Returns:
+    /// - `Ok(())`: If the file was rewritten with the new tags.
+    /// - `Err(Box<dyn Error>)`: If the format is not supported, a value is invalid or the
+    ///   file cannot be read or replaced. The file is unchanged in that case.
+    pub fn write(&self, path: &Path, changes: &[TagChange]) -> Result<(), Box<dyn Error>> {
+        for (field, value) in changes {
+            Self::check_value(*field, value.as_deref())?;
+        }
+        let bytes = fs::read(path)?;
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        let mut tagged = if bytes.starts_with(b"OggS") {
+            Self::write_ogg(&bytes, changes)?
+        } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WAVE") {
+            Self::write_riff_info(&bytes, changes)?
+        } else if Self::flac_start(&bytes).is_some() {
+            Self::write_flac(&bytes, changes)?
+        } else if extension == "mp3" {
+            Self::write_id3v2(&bytes, changes)?
+        } else {
+            return Err(format!("Writing tags to {:?} is not supported", path.display()).into());
+        };
+
+        Self::update_id3v1(&mut tagged, changes);
+        Self::replace_file(path, &tagged)?;
+        info!("Wrote {} tag(s) to {:?}.", changes.len(), path.display());
+        Ok(())
+    }
+
+    /// Rejects values that the number fields cannot hold (`3`, `3/12` and `1997-05-21` are fine)
+    /// and ratings other than 1 to 5 stars.
+    fn check_value(field: TagField, value: Option<&str>) -> Result<(), Box<dyn Error>> {
+        let is_number = matches!(
+            field,
+            TagField::TrackNumber | TagField::DiscNumber | TagField::Year
+        );
+        match value {
+            Some(value) if field == TagField::Rating && Self::stars(value).is_none() => {
+                Err(format!("A rating must be 1 to 5 stars, got {:?}", value).into())
+            }
+            Some(value) if is_number && !value.starts_with(|c: char| c.is_ascii_digit()) => {
+                Err(format!("{:?} must start with a number, got {:?}", field, value).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Parses a rating value, `1` to `5` stars.
+    fn stars(value: &str) -> Option<u8> {
+        value
+            .trim()
+            .parse()
+            .ok()
+            .filter(|stars| (1..=5).contains(stars))
+    }
+
+    /// Writes `bytes` to a temporary file in the folder of `path` and renames it over `path`.
+    fn replace_file(path: &Path, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        let name = path.file_name().ok_or("Not a file")?.to_string_lossy();
+        let temp = path.with_file_name(format!(".{}.tmp", name));
+        let written = (|| -> std::io::Result<()> {
+            let mut file = File::create(&temp)?;
+            file.write_all(bytes)?;
+            file.sync_all()?; // The data must be on disk before the rename is.
+            fs::set_permissions(&temp, fs::metadata(path)?.permissions())?;
+            fs::rename(&temp, path)
+        })();
+        if let Err(err) = written {
+            let _ = fs::remove_file(&temp);
+            return Err(err.into());
+        }
+        // Persist the rename itself, not supported on every platform.
+        if let Some(folder) = path
+            .parent()
+            .filter(|folder| !folder.as_os_str().is_empty())
+        {
+            if let Err(err) = File::open(folder).and_then(|folder| folder.sync_all()) {
+                debug!("Could not sync {:?}: {}", folder.display(), err);
+            }
+        }
+        Ok(())
+    }
+
+    // ---- ID3v2.4 (MP3) ----
+
+    /// Frame id of a field in ID3v2.4.
+    fn id3_frame(field: TagField) -> &'static [u8; 4] {
+        match field {
+            TagField::Title => b"TIT2",
+            TagField::Artist => b"TPE1",
+            TagField::Album => b"TALB",
+            TagField::AlbumArtist => b"TPE2",
+            TagField::TrackNumber => b"TRCK",
+            TagField::DiscNumber => b"TPOS",
+            TagField::Year => b"TDRC",
+            TagField::Genre => b"TCON",
+            TagField::Rating => b"POPM",
+        }
+    }
+
+    /// Replaces the ID3v2 tag at the start of an MP3 file by an ID3v2.4 tag with the changes
+    /// applied.
+    fn write_id3v2(bytes: &[u8], changes: &[TagChange]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let (mut frames, audio_start) = Self::read_id3v2_frames(bytes)?;
+        for (field, value) in changes {
+            let id = Self::id3_frame(*field);
+            // Every user's `POPM` frame goes, so the rating read back is the one written.
+            frames.retain(|(frame_id, _, _)| frame_id != id);
+            match (field, value) {
+                (TagField::Rating, Some(value)) => {
+                    // User, rating byte, no play counter.
+                    let stars = Self::stars(value).ok_or("Invalid rating")?;
+                    let mut data = POPM_EMAIL.as_bytes().to_vec();
+                    data.extend_from_slice(&[0, POPM_RATINGS[usize::from(stars) - 1]]);
+                    frames.push((*id, [0, 0], data));
+                }
+                (_, Some(value)) => {
+                    let mut data = vec![3]; // UTF-8.
+                    data.extend_from_slice(value.as_bytes());
+                    frames.push((*id, [0, 0], data));
+                }
+                (_, None) => {}
+            }
+        }
+
+        let mut body = Vec::new();
+        for (id, flags, data) in &frames {
+            body.extend_from_slice(id);
+            body.extend_from_slice(&Self::syncsafe(data.len())?);
+            body.extend_from_slice(flags);
+            body.extend_from_slice(data);
+        }
+        let mut tagged = b"ID3\x04\x00\x00".to_vec();
+        tagged.extend_from_slice(&Self::syncsafe(body.len())?);
+        tagged.extend_from_slice(&body);
+        tagged.extend_from_slice(&bytes[audio_start..]);
+        Ok(tagged)
+    }
+
+    /// Reads the frames of the ID3v2 tag at the start of `bytes` as `(id, flags, data)`,
+    /// converted to ID3v2.4, and returns them with the offset where the audio starts.
+    /// `bytes` may end right after the tag, which is enough to read the frames.
+    pub fn read_id3v2_frames(bytes: &[u8]) -> Result<(Vec<Id3Frame>, usize), Box<dyn Error>> {
+        if bytes.len() < 10 || &bytes[..3] != b"ID3" {
+            return Ok((Vec::new(), 0));
+        }
+        let (version, flags) = (bytes[3], bytes[5]);
+        let size = Self::read_syncsafe(&bytes[6..10]);
+        let footer = if version == 4 && flags & 0x10 != 0 {
+            10
+        } else {
+            0
+        };
+        let audio_start = (10 + size + footer).min(bytes.len());
+        if !(3..=4).contains(&version) {
+            warn!(
+                "Replacing an ID3v2.{} tag, its frames are not kept.",
+                version
+            );
+            return Ok((Vec::new(), audio_start));
+        }
+
+        let mut region = bytes[10..(10 + size).min(bytes.len())].to_vec();
+        if version == 3 && flags & 0x80 != 0 {
+            region = Self::remove_unsynchronisation(&region);
+        }
+        let mut pos = 0;
+        if flags & 0x40 != 0 && region.len() >= 4 {
+            // Extended header: v2.4 counts its own size field, v2.3 does not.
+            pos = match version {
+                4 => Self::read_syncsafe(&region[..4]),
+                _ => 4 + u32::from_be_bytes([region[0], region[1], region[2], region[3]]) as usize,
+            };
+        }
+
+        let mut frames = Vec::new();
+        while pos + 10 <= region.len() && region[pos] != 0 {
+            let header = &region[pos..pos + 10];
+            let id = [header[0], header[1], header[2], header[3]];
+            let frame_size = match version {
+                4 => Self::read_syncsafe(&header[4..8]),
+                _ => u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize,
+            };
+            let frame_flags = [header[8], header[9]];
+            let data = region
+                .get(pos + 10..pos + 10 + frame_size)
+                .ok_or("Truncated ID3v2 frame")?
+                .to_vec();
+            pos += 10 + frame_size;
+
+            if version == 4 {
+                frames.push((id, frame_flags, data));
+                continue;
+            }
+            // ID3v2.3: compressed, encrypted or grouped frames cannot be carried over as is.
+            if frame_flags[1] & 0xe0 != 0 {
+                debug!("Dropping ID3v2.3 frame {:?}.", String::from_utf8_lossy(&id));
+                continue;
+            }
+            let id = match &id {
+                b"TYER" => *b"TDRC",
+                b"TORY" => *b"TDOR",
+                id if ID3V23_ONLY_FRAMES.contains(&id) => continue,
+                _ => id,
+            };
+            frames.push((id, [0, 0], data));
+        }
+        Ok((frames, audio_start))
+    }
+
+    /// Updates the fields of an ID3v1 tag at the end of `bytes`, if there is one, so it
+    /// does not contradict the tags just written.
+    fn update_id3v1(bytes: &mut [u8], changes: &[TagChange]) {
+        let len = bytes.len();
+        if len < 128 || &bytes[len - 128..len - 125] != b"TAG" {
+            return;
+        }
+        let tag = &mut bytes[len - 128..];
+        let latin1 = |value: &str| -> Vec<u8> {
+            value
+                .chars()
+                .map(|c| if (c as u32) < 256 { c as u8 } else { b'?' })
+                .collect()
+        };
+        for (field, value) in changes {
+            let value = value.as_deref().unwrap_or("");
+            let range = match field {
+                TagField::Title => 3..33,
+                TagField::Artist => 33..63,
+                TagField::Album => 63..93,
+                TagField::Year => 93..97,
+                TagField::TrackNumber if tag[125] == 0 => {
+                    let number: String = value.chars().take_while(char::is_ascii_digit).collect();
+                    tag[126] = number.parse::<u8>().unwrap_or(0);
+                    continue;
+                }
+                TagField::Genre => {
+                    tag[127] = ID3V1_GENRES
+                        .iter()
+                        .position(|genre| genre.eq_ignore_ascii_case(value))
+                        .map_or(255, |index| index as u8);
+                    continue;
+                }
+                _ => continue, // ID3v1 has no such field.
+            };
+            let text = latin1(value);
+            for (i, byte) in tag[range.clone()].iter_mut().enumerate() {
+                *byte = text.get(i).copied().unwrap_or(0);
+            }
+        }
+    }
+
+    /// Encodes a size as four 7-bit bytes.
+    fn syncsafe(size: usize) -> Result<[u8; 4], Box<dyn Error>> {
+        if size >= 1 << 28 {
+            return Err("ID3v2 tag too large".into());
+        }
+        Ok([
+            (size >> 21) as u8 & 0x7f,
+            (size >> 14) as u8 & 0x7f,
+            (size >> 7) as u8 & 0x7f,
+            size as u8 & 0x7f,
+        ])
+    }
+
+    /// Decodes a size stored as four 7-bit bytes.
+    fn read_syncsafe(bytes: &[u8]) -> usize {
+        bytes
+            .iter()
+            .take(4)
+            .fold(0, |size, &b| (size << 7) | usize::from(b & 0x7f))
+    }
+
+    /// Reverts ID3v2.3 unsynchronisation (`FF 00` -> `FF`).
+    fn remove_unsynchronisation(bytes: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(bytes.len());
+        for (i, &b) in bytes.iter().enumerate() {
+            if !(b == 0 && i > 0 && bytes[i - 1] == 0xff) {
+                out.push(b);
+            }
+        }
+        out
+    }
+
+    // ---- Vorbis comments (FLAC, Ogg) ----
+
+    /// Vorbis comment names of a field, the first one is written, all are replaced.
+    fn vorbis_keys(field: TagField) -> &'static [&'static str] {
+        match field {
+            TagField::Title => &["TITLE"],
+            TagField::Artist => &["ARTIST"],
+            TagField::Album => &["ALBUM"],
+            TagField::AlbumArtist => &["ALBUMARTIST", "ALBUM ARTIST"],
+            TagField::TrackNumber => &["TRACKNUMBER"],
+            TagField::DiscNumber => &["DISCNUMBER"],
+            TagField::Year => &["DATE", "YEAR"],
+            TagField::Genre => &["GENRE"],
+            TagField::Rating => &["FMPS_RATING", "RATING"],
+        }
+    }
+
+    /// Applies the changes to a Vorbis comment block (vendor string, then `KEY=value`
+    /// comments, all with little-endian 32-bit lengths).
+    ///
+    /// # Returns:
+    /// - The new block and the bytes that followed the old one (the Vorbis framing bit,
+    ///   Opus padding), which the caller keeps.
+    fn edit_vorbis_comments(
+        block: &[u8],
+        changes: &[TagChange],
+    ) -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>> {
+        let read_u32 = |at: usize| -> Result<usize, Box<dyn Error>> {
+            let b = block.get(at..at + 4).ok_or("Truncated Vorbis comments")?;
+            Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        };
+        let vendor_len = read_u32(0)?;
+        let vendor = block
+            .get(4..4 + vendor_len)
+            .ok_or("Truncated Vorbis comments")?;
+        let count = read_u32(4 + vendor_len)?;
+        let mut pos = 8 + vendor_len;
+        let mut comments = Vec::new();
+        for _ in 0..count {
+            let len = read_u32(pos)?;
+            let comment = block
+                .get(pos + 4..pos + 4 + len)
+                .ok_or("Truncated Vorbis comments")?;
+            comments.push(comment.to_vec());
+            pos += 4 + len;
+        }
+        let rest = block[pos..].to_vec();
+
+        for (field, value) in changes {
+            let keys = Self::vorbis_keys(*field);
+            comments.retain(|comment| {
+                let key = comment.split(|&b| b == b'=').next().unwrap_or(&[]);
+                !keys.iter().any(|k| k.as_bytes().eq_ignore_ascii_case(key))
+            });
+            match (field, value) {
+                // FMPS ratings go from 0.0 to 1.0 in fifths for stars.
+                (TagField::Rating, Some(value)) => {
+                    let stars = Self::stars(value).ok_or("Invalid rating")?;
+                    let rating = f64::from(stars) / 5.0;
+                    comments.push(format!("{}={}", keys[0], rating).into_bytes());
+                }
+                (_, Some(value)) => comments.push(format!("{}={}", keys[0], value).into_bytes()),
+                (_, None) => {}
+            }
+        }
+
+        let mut out = (vendor.len() as u32).to_le_bytes().to_vec();
+        out.extend_from_slice(vendor);
+        out.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for comment in &comments {
+            out.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            out.extend_from_slice(comment);
+        }
+        Ok((out, rest))
+    }
+
+    /// Returns the offset of the `fLaC` marker, after an ID3v2 tag some taggers put first.
+    fn flac_start(bytes: &[u8]) -> Option<usize> {
+        let start = if bytes.starts_with(b"ID3") && bytes.len() >= 10 {
+            10 + Self::read_syncsafe(&bytes[6..10])
+        } else {
+            0
+        };
+        (bytes.get(start..start + 4) == Some(b"fLaC")).then_some(start)
+    }
+
+    /// Replaces the VORBIS_COMMENT block of a FLAC file (adding one after STREAMINFO if
+    /// there is none). The other metadata blocks and the audio frames are copied as is.
+    fn write_flac(bytes: &[u8], changes: &[TagChange]) -> Result<Vec<u8>, Box<dyn Error>> {
+        const VORBIS_COMMENT: u8 = 4;
+        let start = Self::flac_start(bytes).ok_or("Not a FLAC file")?;
+        let mut blocks: Vec<(u8, Vec<u8>)> = Vec::new();
+        let mut pos = start + 4;
+        loop {
+            let header = bytes.get(pos..pos + 4).ok_or("Truncated FLAC metadata")?;
+            let is_last = header[0] & 0x80 != 0;
+            let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+            let data = bytes
+                .get(pos + 4..pos + 4 + len)
+                .ok_or("Truncated FLAC metadata")?;
+            blocks.push((header[0] & 0x7f, data.to_vec()));
+            pos += 4 + len;
+            if is_last {
+                break;
+            }
+        }
+
+        let empty = 0u32.to_le_bytes().repeat(2); // No vendor, no comments.
+        let index = blocks.iter().position(|(kind, _)| *kind == VORBIS_COMMENT);
+        let old = index.map_or(&empty[..], |index| &blocks[index].1[..]);
+        let (comments, _) = Self::edit_vorbis_comments(old, changes)?;
+        if comments.len() >= 1 << 24 {
+            return Err("Vorbis comments too large for FLAC".into());
+        }
+        match index {
+            Some(index) => blocks[index].1 = comments,
+            None => blocks.insert(1.min(blocks.len()), (VORBIS_COMMENT, comments)),
+        }
+
+        let mut tagged = bytes[..start + 4].to_vec();
+        for (i, (kind, data)) in blocks.iter().enumerate() {
+            let last = if i + 1 == blocks.len() { 0x80 } else { 0 };
+            tagged.push(kind | last);
+            tagged.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+            tagged.extend_from_slice(data);
+        }
+        tagged.extend_from_slice(&bytes[pos..]);
+        Ok(tagged)
+    }
+
+    /// Replaces the comment header of an Ogg Vorbis or Opus stream. The header packets are
+    /// paged again, the pages that follow keep their content and only get new sequence
+    /// numbers (and checksums) if the header now takes a different number of pages.
+    fn write_ogg(bytes: &[u8], changes: &[TagChange]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut pages = OggPage::parse_all(bytes)?;
+        let serial = pages.first().ok_or("Empty Ogg file")?.serial;
+
+        // Collect the header packets: identification, comments (and setup for Vorbis).
+        let mut packets: Vec<Vec<u8>> = vec![Vec::new()];
+        let mut header_count = None;
+        let mut header_pages = 0;
+        for page in pages.iter().filter(|page| page.serial == serial) {
+            let mut offset = 0;
+            for &segment in &page.segments {
+                let last = packets.last_mut().ok_or("Ogg packet error")?;
+                last.extend_from_slice(&page.data[offset..offset + segment as usize]);
+                offset += segment as usize;
+                if segment < 255 {
+                    packets.push(Vec::new());
+                }
+            }
+            header_pages += 1;
+            if header_count.is_none() {
+                header_count = match &packets[0] {
+                    p if p.starts_with(b"\x01vorbis") => Some(3),
+                    p if p.starts_with(b"OpusHead") => Some(2),
+                    _ => return Err("Only Ogg Vorbis and Opus tags can be written".into()),
+                };
+            }
+            let complete = packets.len() - 1;
+            if Some(complete) >= header_count {
+                if Some(complete) > header_count || !packets[complete].is_empty() {
+                    return Err("Audio data shares a page with the Ogg headers".into());
+                }
+                break;
+            }
+        }
+        let header_count = header_count.ok_or("Missing Ogg headers")?;
+        packets.truncate(header_count);
+        if packets.len() < header_count || pages[0].segments.len() != 1 {
+            return Err("Unexpected Ogg header layout".into());
+        }
+
+        let (prefix, framing) = if packets[1].starts_with(b"\x03vorbis") {
+            (&b"\x03vorbis"[..], true)
+        } else if packets[1].starts_with(b"OpusTags") {
+            (&b"OpusTags"[..], false)
+        } else {
+            return Err("Missing Ogg comment header".into());
+        };
+        let (comments, rest) = Self::edit_vorbis_comments(&packets[1][prefix.len()..], changes)?;
+        let mut comment_packet = prefix.to_vec();
+        comment_packet.extend_from_slice(&comments);
+        if framing {
+            comment_packet.push(1);
+        } else {
+            comment_packet.extend_from_slice(&rest); // Opus padding or binary data.
+        }
+        packets[1] = comment_packet;
+
+        let new_headers = OggPage::paginate(&packets[1..], serial, 1);
+        let shift = new_headers.len() as i64 + 1 - header_pages as i64;
+        let header_indices: Vec<usize> = pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| page.serial == serial)
+            .map(|(index, _)| index)
+            .take(header_pages)
+            .collect();
+        for page in pages.iter_mut().filter(|page| page.serial == serial) {
+            page.sequence = (page.sequence as i64 + shift) as u32;
+        }
+        pages[0].sequence = 0;
+        // Replace the old comment/setup pages by the new ones, in place.
+        let insert_at = header_indices[1..].first().copied().unwrap_or(1);
+        for &index in header_indices[1..].iter().rev() {
+            pages.remove(index);
+        }
+        pages.splice(insert_at..insert_at, new_headers);
+
+        let mut tagged = Vec::with_capacity(bytes.len());
+        for page in &pages {
+            page.write(&mut tagged);
+        }
+        Ok(tagged)
+    }
+
+    // ---- RIFF INFO (WAV) ----
+
+    /// RIFF INFO chunk id of a field, the first one is written, all are replaced.
+    fn riff_info_ids(field: TagField) -> Result<&'static [&'static [u8; 4]], Box<dyn Error>> {
+        match field {
+            TagField::Title => Ok(&[b"INAM"]),
+            TagField::Artist => Ok(&[b"IART"]),
+            TagField::Album => Ok(&[b"IPRD"]),
+            TagField::TrackNumber => Ok(&[b"IPRT", b"ITRK", b"TRCK", b"PRT1"]),
+            TagField::Year => Ok(&[b"ICRD", b"YEAR"]),
+            TagField::Genre => Ok(&[b"IGNR", b"GENR"]),
+            TagField::AlbumArtist | TagField::DiscNumber | TagField::Rating => {
+                Err(format!("WAV files (RIFF INFO) have no {:?} field", field).into())
+            }
+        }
+    }
+
+    /// Replaces the `LIST`/`INFO` chunk of a WAV file (adding one before the `data` chunk
+    /// if there is none). The other chunks are copied as is.
+    fn write_riff_info(bytes: &[u8], changes: &[TagChange]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let riff_end = (8 + u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize)
+            .min(bytes.len());
+        let mut chunks: Vec<([u8; 4], &[u8])> = Vec::new();
+        let mut info: Option<(usize, Vec<InfoEntry>)> = None;
+        let mut pos = 12;
+        while pos + 8 <= riff_end {
+            let id = [bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]];
+            let len = u32::from_le_bytes([
+                bytes[pos + 4],
+                bytes[pos + 5],
+                bytes[pos + 6],
+                bytes[pos + 7],
+            ]) as usize;
+            let data = &bytes[pos + 8..(pos + 8 + len).min(riff_end)];
+            pos += 8 + len + len % 2;
+            if &id == b"LIST" && data.starts_with(b"INFO") && info.is_none() {
+                info = Some((chunks.len(), Self::read_info_entries(&data[4..])));
+            } else {
+                chunks.push((id, data));
+            }
+        }
+
+        let data_index = chunks.iter().position(|(id, _)| id == b"data");
+        let (index, mut entries) = info.unwrap_or((data_index.unwrap_or(chunks.len()), Vec::new()));
+        for (field, value) in changes {
+            let ids = Self::riff_info_ids(*field)?;
+            entries.retain(|(id, _)| !ids.contains(&id));
+            if let Some(value) = value {
+                let mut data = value.as_bytes().to_vec();
+                data.push(0);
+                entries.push((*ids[0], data));
+            }
+        }
+        let mut list = b"INFO".to_vec();
+        for (id, data) in &entries {
+            Self::push_riff_chunk(&mut list, id, data);
+        }
+
+        let mut tagged = bytes[..12].to_vec();
+        for (i, (id, data)) in chunks.iter().enumerate() {
+            if i == index && !entries.is_empty() {
+                Self::push_riff_chunk(&mut tagged, b"LIST", &list);
+            }
+            Self::push_riff_chunk(&mut tagged, id, data);
+        }
+        if index == chunks.len() && !entries.is_empty() {
+            Self::push_riff_chunk(&mut tagged, b"LIST", &list);
+        }
+        let riff_size = u32::try_from(tagged.len() - 8)?;
+        tagged[4..8].copy_from_slice(&riff_size.to_le_bytes());
+        tagged.extend_from_slice(&bytes[riff_end..]); // Data after the RIFF chunk, if any.
+        Ok(tagged)
+    }
+
+    /// Reads the sub-chunks of an `INFO` list.
+    fn read_info_entries(bytes: &[u8]) -> Vec<InfoEntry> {
+        let mut entries = Vec::new();
+        let mut pos = 0;
+        while pos + 8 <= bytes.len() {
+            let id = [bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]];
+            let len = u32::from_le_bytes([
+                bytes[pos + 4],
+                bytes[pos + 5],
+                bytes[pos + 6],
+                bytes[pos + 7],
+            ]) as usize;
+            let end = (pos + 8 + len).min(bytes.len());
+            entries.push((id, bytes[pos + 8..end].to_vec()));
+            pos += 8 + len + len % 2;
+        }
+        entries
+    }
+
+    /// Appends a RIFF chunk, padded to an even length.
+    fn push_riff_chunk(out: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+        out.extend_from_slice(id);
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            out.push(0);
+        }
+    }
+}
+
+/// One page of an Ogg stream.
+struct OggPage {
+    /// Continued packet (0x01), first page (0x02), last page (0x04).
+    header_type: u8,
+    granule: u64,
+    serial: u32,
+    sequence: u32,
+    /// Lacing values: packets are split into 255-byte segments, a shorter one ends a packet.
+    segments: Vec<u8>,
+    data: Vec<u8>,
+}
+
+impl OggPage {
+    /// Parses all pages of an Ogg file.
+    fn parse_all(bytes: &[u8]) -> Result<Vec<OggPage>, Box<dyn Error>> {
+        let mut pages = Vec::new();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let header = bytes.get(pos..pos + 27).ok_or("Truncated Ogg page")?;
+            if &header[..4] != b"OggS" {
+                return Err("Broken Ogg page".into());
+            }
+            let count = header[26] as usize;
+            let segments = bytes
+                .get(pos + 27..pos + 27 + count)
+                .ok_or("Truncated Ogg page")?
+                .to_vec();
+            let len: usize = segments.iter().map(|&s| s as usize).sum();
+            let start = pos + 27 + count;
+            let data = bytes.get(start..start + len).ok_or("Truncated Ogg page")?;
+            let u32_at = |at: usize| {
+                u32::from_le_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]])
+            };
+            pages.push(OggPage {
+                header_type: header[5],
+                granule: u64::from(u32_at(6)) | u64::from(u32_at(10)) << 32,
+                serial: u32_at(14),
+                sequence: u32_at(18),
+                segments,
+                data: data.to_vec(),
+            });
+            pos = start + len;
+        }
+        Ok(pages)
+    }
+
+    /// Splits header packets into pages, starting with sequence number `sequence`. The
+    /// last page ends with the last packet, so audio pages can follow.
+    fn paginate(packets: &[Vec<u8>], serial: u32, sequence: u32) -> Vec<OggPage> {
+        let new_page = |sequence: u32, continued: bool| OggPage {
+            header_type: if continued { 0x01 } else { 0 },
+            granule: u64::MAX, // No packet ends on the page (yet).
+            serial,
+            sequence,
+            segments: Vec::new(),
+            data: Vec::new(),
+        };
+        let mut pages = Vec::new();
+        let mut page = new_page(sequence, false);
+        for packet in packets {
+            let mut rest = &packet[..];
+            let mut started = false;
+            loop {
+                if page.segments.len() == 255 {
+                    let next = new_page(page.sequence + 1, started);
+                    pages.push(std::mem::replace(&mut page, next));
+                }
+                let take = rest.len().min(255);
+                page.segments.push(take as u8);
+                page.data.extend_from_slice(&rest[..take]);
+                rest = &rest[take..];
+                started = true;
+                if take < 255 {
+                    page.granule = 0; // Header packets have granule position 0.
+                    break;
+                }
+            }
+        }
+        pages.push(page);
+        pages
+    }
+
+    /// Appends the page with its checksum.
+    fn write(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        out.extend_from_slice(b"OggS\x00");
+        out.push(self.header_type);
+        out.extend_from_slice(&self.granule.to_le_bytes());
+        out.extend_from_slice(&self.serial.to_le_bytes());
+        out.extend_from_slice(&self.sequence.to_le_bytes());
+        out.extend_from_slice(&[0; 4]); // Checksum, computed over the page with this zeroed.
+        out.push(self.segments.len() as u8);
+        out.extend_from_slice(&self.segments);
+        out.extend_from_slice(&self.data);
+        let crc = Self::crc(&out[start..]);
+        out[start + 22..start + 26].copy_from_slice(&crc.to_le_bytes());
+    }
+
+    /// The Ogg CRC-32 (polynomial 0x04c11db7, not reflected, initial value 0).
+    fn crc(bytes: &[u8]) -> u32 {
+        let mut crc = 0u32;
+        for &byte in bytes {
+            crc ^= u32::from(byte) << 24;
+            for _ in 0..8 {
+                crc = if crc & 0x8000_0000 != 0 {
+                    (crc << 1) ^ 0x04c1_1db7
+                } else {
+                    crc << 1
+                };
+            }
+        }
+        crc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::services::metadata_service::MetadataService;
+    use crate::modules::services::temp_folder::TempFolder;
+    use std::path::PathBuf;
+
+    /// Writes `bytes` to a file named `name` in a temporary folder.
+    fn fixture(name: &str, bytes: &[u8]) -> (TempFolder, PathBuf) {
+        let folder = TempFolder::new("tags");
+        let path = folder.write(name, bytes);
+        (folder, path)
+    }
+
+    fn change(field: TagField, value: &str) -> TagChange {
+        (field, Some(value.to_string()))
+    }
+
+    /// MPEG-1 Layer III frames of silence (128 kbit/s, 44.1 kHz, 417 bytes each).
+    fn mp3_frames(count: usize) -> Vec<u8> {
+        let mut frame = vec![0u8; 417];
+        frame[..4].copy_from_slice(&[0xff, 0xfb, 0x90, 0x00]);
+        frame.repeat(count)
+    }
+
+    /// An ID3v2 frame with a big-endian size (ID3v2.3) and a Latin-1 text.
+    fn id3v23_text_frame(id: &[u8; 4], text: &str) -> Vec<u8> {
+        let mut frame = id.to_vec();
+        frame.extend_from_slice(&(text.len() as u32 + 1).to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0]); // Flags, Latin-1.
+        frame.extend_from_slice(text.as_bytes());
+        frame
+    }
+
+    /// A FLAC file with a STREAMINFO block (44.1 kHz stereo, 1 s) and the given Vorbis
+    /// comments, without audio frames.
+    fn flac(comments: &[&str]) -> Vec<u8> {
+        let mut bytes = b"fLaC".to_vec();
+        let mut info = vec![0x10, 0x00, 0x10, 0x00, 0, 0, 0, 0, 0, 0];
+        let packed: u64 = (44_100 << 44) | (1 << 41) | (15 << 36) | 44_100;
+        info.extend_from_slice(&packed.to_be_bytes());
+        info.extend_from_slice(&[0; 16]);
+        bytes.extend_from_slice(&[0, 0, 0, info.len() as u8]);
+        bytes.extend_from_slice(&info);
+        let mut block = 0u32.to_le_bytes().to_vec();
+        block.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            block.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            block.extend_from_slice(comment.as_bytes());
+        }
+        bytes.extend_from_slice(&[0x84, 0, 0, block.len() as u8]);
+        bytes.extend_from_slice(&block);
+        bytes
+    }
+
+    /// An Ogg Vorbis stream: the three header packets (the comment one padded to
+    /// `comment_size` bytes) and one audio page.
+    fn ogg_vorbis(comment_size: usize, setup_size: usize) -> Vec<u8> {
+        let mut identification = b"\x01vorbis".to_vec();
+        identification.resize(30, 0);
+        let mut comments = b"\x03vorbis".to_vec();
+        let padding = "x".repeat(comment_size - 26);
+        comments.extend_from_slice(&0u32.to_le_bytes());
+        comments.extend_from_slice(&1u32.to_le_bytes());
+        comments.extend_from_slice(&(6 + padding.len() as u32).to_le_bytes());
+        comments.extend_from_slice(format!("GENRE={}", padding).as_bytes());
+        comments.push(1);
+        assert_eq!(comments.len(), comment_size);
+        let mut setup = b"\x05vorbis".to_vec();
+        setup.resize(setup_size, 7);
+
+        let mut bytes = Vec::new();
+        let mut first = OggPage::paginate(&[identification], 42, 0).remove(0);
+        first.header_type = 0x02;
+        first.write(&mut bytes);
+        for page in OggPage::paginate(&[comments, setup], 42, 1) {
+            page.write(&mut bytes);
+        }
+        OggPage {
+            header_type: 0x04,
+            granule: 44_100,
+            serial: 42,
+            sequence: 99,
+            segments: vec![3],
+            data: vec![1, 2, 3],
+        }
+        .write(&mut bytes);
+        bytes
+    }
+
+    /// Reassembles the packets of an Ogg stream, checking every page checksum.
+    fn ogg_packets(bytes: &[u8]) -> Vec<Vec<u8>> {
+        let pages = OggPage::parse_all(bytes).unwrap();
+        let mut written = Vec::new();
+        for page in &pages {
+            page.write(&mut written);
+        }
+        assert_eq!(written, bytes, "checksums and layout are stable");
+        let mut packets = vec![Vec::new()];
+        for page in &pages {
+            let mut offset = 0;
+            for &segment in &page.segments {
+                let end = offset + segment as usize;
+                packets
+                    .last_mut()
+                    .unwrap()
+                    .extend_from_slice(&page.data[offset..end]);
+                offset = end;
+                if segment < 255 {
+                    packets.push(Vec::new());
+                }
+            }
+        }
+        packets.pop();
+        packets
+    }
+
+    /// A WAV file with a `fmt ` chunk, an odd-sized unknown chunk, an INFO list holding an
+    /// odd-sized title and a `data` chunk.
+    fn wav() -> Vec<u8> {
+        let mut chunks = Vec::new();
+        let fmt = [1, 0, 1, 0, 0x44, 0xac, 0, 0, 0x88, 0x58, 1, 0, 2, 0, 16, 0];
+        TagService::push_riff_chunk(&mut chunks, b"fmt ", &fmt);
+        TagService::push_riff_chunk(&mut chunks, b"junk", b"odd");
+        let mut list = b"INFO".to_vec();
+        TagService::push_riff_chunk(&mut list, b"INAM", b"Old\0");
+        TagService::push_riff_chunk(&mut list, b"ICMT", b"keep\0");
+        TagService::push_riff_chunk(&mut chunks, b"LIST", &list);
+        TagService::push_riff_chunk(&mut chunks, b"data", &[1, 0, 2, 0, 3, 0]);
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&(chunks.len() as u32 + 4).to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend_from_slice(&chunks);
+        bytes
+    }
+
+    /// Returns the chunks of a RIFF file as `(id, data)`.
+    fn riff_chunks(bytes: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+        assert_eq!(
+            u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize,
+            bytes.len() - 8
+        );
+        TagService::read_info_entries(&bytes[12..])
+    }
+
+    #[test]
+    fn mp3_tags_read_back() {
+        let mut bytes = id3v23_text_frame(b"TIT2", "Old title");
+        bytes.splice(0..0, b"ID3\x03\x00\x00\x00\x00\x00\x00".iter().copied());
+        let size = TagService::syncsafe(bytes.len() - 10).unwrap();
+        bytes[6..10].copy_from_slice(&size);
+        let audio = mp3_frames(8);
+        bytes.extend_from_slice(&audio);
+        let (_folder, path) = fixture("round-trip.mp3", &bytes);
+
+        let changes = [
+            change(TagField::Title, "Tïtle"),
+            change(TagField::Artist, "Artist"),
+            change(TagField::Album, "Album"),
+            change(TagField::TrackNumber, "3/12"),
+            change(TagField::Year, "1997-05-21"),
+            change(TagField::Genre, "Jazz"),
+            change(TagField::Rating, "4"),
+        ];
+        TagService.write(&path, &changes).unwrap();
+        let written = fs::read(&path).unwrap();
+        assert_eq!(&written[..4], b"ID3\x04");
+        assert!(written.ends_with(&audio), "the audio is kept");
+
+        let metadata = MetadataService.read(&path);
+        assert_eq!(metadata.title.as_deref(), Some("Tïtle"));
+        assert_eq!(metadata.artist.as_deref(), Some("Artist"));
+        assert_eq!(metadata.album.as_deref(), Some("Album"));
+        assert_eq!(metadata.track_number, Some(3));
+        assert_eq!(metadata.year, Some(1997));
+        assert_eq!(metadata.genre.as_deref(), Some("Jazz"));
+        assert_eq!(metadata.rating, Some(4));
+
+        TagService.write(&path, &[(TagField::Title, None)]).unwrap();
+        assert_eq!(MetadataService.read(&path).title, None);
+    }
+
+    #[test]
+    fn id3v23_tags_are_upgraded() {
+        let mut frames = id3v23_text_frame(b"TIT2", "Title");
+        frames.extend(id3v23_text_frame(b"TYER", "1984"));
+        frames.extend(id3v23_text_frame(b"TDAT", "0101"));
+        frames.extend(id3v23_text_frame(b"TPE1", "Artist"));
+        let mut compressed = id3v23_text_frame(b"TCOM", "Composer");
+        compressed[9] = 0x80;
+        frames.extend(compressed);
+        let mut bytes = b"ID3\x03\x00\x00".to_vec();
+        bytes.extend_from_slice(&TagService::syncsafe(frames.len() + 20).unwrap());
+        bytes.extend_from_slice(&frames);
+        bytes.extend_from_slice(&[0; 20]); // Padding.
+        bytes.extend_from_slice(&mp3_frames(8));
+
+        let (upgraded, audio_start) = TagService::read_id3v2_frames(&bytes).unwrap();
+        assert_eq!(audio_start, bytes.len() - 8 * 417);
+        let ids: Vec<&[u8; 4]> = upgraded.iter().map(|(id, _, _)| id).collect();
+        assert_eq!(ids, vec![b"TIT2", b"TDRC", b"TPE1"]);
+
+        let (_folder, path) = fixture("upgrade.mp3", &bytes);
+        TagService
+            .write(&path, &[change(TagField::Album, "Album")])
+            .unwrap();
+        let written = fs::read(&path).unwrap();
+        assert_eq!(&written[..4], b"ID3\x04");
+        let metadata = MetadataService.read(&path);
+        assert_eq!(metadata.title.as_deref(), Some("Title"));
+        assert_eq!(metadata.artist.as_deref(), Some("Artist"));
+        assert_eq!(metadata.album.as_deref(), Some("Album"));
+        assert_eq!(metadata.year, Some(1984));
+    }
+
+    #[test]
+    fn id3v1_follows_the_written_tags() {
+        let mut bytes = mp3_frames(8);
+        let mut tag = b"TAG".to_vec();
+        tag.resize(128, 0);
+        tag[127] = 255;
+        bytes.extend_from_slice(&tag);
+        let (_folder, path) = fixture("id3v1.mp3", &bytes);
+        TagService
+            .write(
+                &path,
+                &[
+                    change(TagField::Title, "Ünïcode ☃"),
+                    change(TagField::Genre, "rock"),
+                ],
+            )
+            .unwrap();
+        let written = fs::read(&path).unwrap();
+        let tag = &written[written.len() - 128..];
+        assert_eq!(&tag[3..14], b"\xdcn\xefcode ?\0\0");
+        assert_eq!(tag[127], 17);
+    }
+
+    #[test]
+    fn flac_tags_read_back() {
+        let (_folder, path) = fixture("round-trip.flac", &flac(&["TITLE=Old", "COMMENT=keep me"]));
+        TagService
+            .write(
+                &path,
+                &[
+                    change(TagField::Title, "New"),
+                    change(TagField::AlbumArtist, "Band"),
+                    change(TagField::Rating, "2"),
+                ],
+            )
+            .unwrap();
+
+        let metadata = MetadataService.read(&path);
+        assert_eq!(metadata.title.as_deref(), Some("New"));
+        assert_eq!(metadata.album_artist.as_deref(), Some("Band"));
+        assert_eq!(metadata.rating, Some(2));
+        assert_eq!(metadata.duration, Some(std::time::Duration::from_secs(1)));
+        let written = String::from_utf8_lossy(&fs::read(&path).unwrap()).into_owned();
+        assert!(written.contains("COMMENT=keep me"));
+        assert!(!written.contains("TITLE=Old"));
+    }
+
+    #[test]
+    fn flac_without_comments_gets_a_block() {
+        let mut bytes = flac(&[]);
+        bytes.truncate(4 + 4 + 34);
+        bytes[4] |= 0x80; // STREAMINFO is the last block now.
+        let (_folder, path) = fixture("no-comments.flac", &bytes);
+        TagService
+            .write(&path, &[change(TagField::Artist, "Artist")])
+            .unwrap();
+        assert_eq!(
+            MetadataService.read(&path).artist.as_deref(),
+            Some("Artist")
+        );
+    }
+
+    #[test]
+    fn ogg_packets_of_255_byte_multiples_survive() {
+        // Comment and setup packets ending exactly on a segment boundary need a final
+        // zero-length segment, both when read and when written.
+        for (comment_size, setup_size) in [(255, 510), (300, 255), (300, 65_025)] {
+            let bytes = ogg_vorbis(comment_size, setup_size);
+            let before = ogg_packets(&bytes);
+            assert_eq!(before[1].len(), comment_size);
+
+            // A title that makes the new comment packet exactly 510 bytes long: the old
+            // packet plus the length and `TITLE=` of the new comment.
+            let title = "t".repeat(510 - comment_size - 4 - 6);
+            let (_folder, path) = fixture("multiple.ogg", &bytes);
+            TagService
+                .write(&path, &[change(TagField::Title, &title)])
+                .unwrap();
+
+            let after = ogg_packets(&fs::read(&path).unwrap());
+            assert_eq!(after.len(), before.len());
+            assert_eq!(after[1].len(), 510);
+            assert!(after[1].ends_with(format!("TITLE={}\u{1}", title).as_bytes()));
+            assert_eq!(after[0], before[0]);
+            assert_eq!(after[2], before[2], "setup packet of {} bytes", setup_size);
+            assert_eq!(after[3], before[3]);
+        }
+    }
+
+    #[test]
+    fn ogg_sequence_numbers_follow_the_new_header_pages() {
+        let (_folder, path) = fixture("sequence.ogg", &ogg_vorbis(300, 400));
+        let long = "x".repeat(70_000); // The comments now span two pages.
+        TagService
+            .write(&path, &[change(TagField::Album, &long)])
+            .unwrap();
+        let pages = OggPage::parse_all(&fs::read(&path).unwrap()).unwrap();
+        let sequences: Vec<u32> = pages.iter().map(|page| page.sequence).collect();
+        // Identification, comments over two pages with the setup, then the audio page,
+        // which moves one page further.
+        assert_eq!(sequences, vec![0, 1, 2, 100]);
+        assert_eq!(pages[2].header_type, 0x01, "continued packet");
+    }
+
+    #[test]
+    fn wav_odd_chunks_stay_aligned() {
+        let (_folder, path) = fixture("odd.wav", &wav());
+        TagService
+            .write(
+                &path,
+                &[
+                    change(TagField::Title, "Even"),
+                    change(TagField::Artist, "Odd"),
+                ],
+            )
+            .unwrap();
+        let written = fs::read(&path).unwrap();
+        let chunks = riff_chunks(&written);
+        let ids: Vec<&[u8; 4]> = chunks.iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec![b"fmt ", b"junk", b"LIST", b"data"]);
+        assert_eq!(chunks[1].1, b"odd");
+        assert_eq!(chunks[3].1, [1, 0, 2, 0, 3, 0]);
+        let info = TagService::read_info_entries(&chunks[2].1[4..]);
+        assert_eq!(
+            info,
+            vec![
+                (*b"ICMT", b"keep\0".to_vec()),
+                (*b"INAM", b"Even\0".to_vec()),
+                (*b"IART", b"Odd\0".to_vec()),
+            ]
+        );
+
+        let metadata = MetadataService.read(&path);
+        assert_eq!(metadata.title.as_deref(), Some("Even"));
+        assert_eq!(metadata.artist.as_deref(), Some("Odd"));
+    }
+
+    #[test]
+    fn invalid_values_leave_the_file_unchanged() {
+        let bytes = wav();
+        let (_folder, path) = fixture("invalid.wav", &bytes);
+        assert!(TagService
+            .write(&path, &[change(TagField::Year, "soon")])
+            .is_err());
+        assert!(TagService
+            .write(&path, &[change(TagField::Rating, "6")])
+            .is_err());
+        assert!(TagService
+            .write(&path, &[change(TagField::DiscNumber, "1")])
+            .is_err());
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+        assert_eq!(MetadataService.read(&path).title.as_deref(), Some("Old"));
+    }
+}
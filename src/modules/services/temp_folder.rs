@@ -0,0 +1,49 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Numbers the folders, so tests running at once never share one.
+static FOLDER_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// **TempFolder Struct**
+///
+/// An empty folder in the temporary directory for the files a test reads and writes,
+/// removed with everything in it when the `TempFolder` is dropped.
+pub struct TempFolder {
+    path: PathBuf,
+}
+
+impl TempFolder {
+    /// Creates the folder `sensit-<name>-<process id>-<number>`.
+    pub fn new(name: &str) -> Self {
+        let number = FOLDER_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = env::temp_dir().join(format!("sensit-{}-{}-{}", name, process::id(), number));
+        let _ = fs::remove_dir_all(&path); // Left over by a crashed run.
+        fs::create_dir_all(&path).unwrap();
+        TempFolder { path }
+    }
+
+    /// Returns the path of the folder.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes `bytes` to the file `name` in the folder, creating its subfolders.
+    ///
+    /// # Returns
+    /// The path of the file.
+    pub fn write(&self, name: impl AsRef<Path>, bytes: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.path.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+}
+
+impl Drop for TempFolder {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
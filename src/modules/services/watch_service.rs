@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{debug, info, warn};
+use notify::event::{AccessKind, AccessMode, ModifyKind, RenameMode};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::modules::models::folder_change_model::FolderChange;
+
+/// How long a path has to be quiet before its change is reported.
+const QUIET_PERIOD: Duration = Duration::from_millis(1000);
+
+/// How often the debounce thread looks for quiet paths.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A change waiting for its path to become quiet.
+enum Pending {
+    Changed,
+    Removed,
+    RenamedFrom(PathBuf),
+}
+
+/// Turns raw events into `FolderChange`s: collects them per path and hands out the changes
+/// of the paths that have been quiet for `QUIET_PERIOD`. The times are passed in, so the
+/// watcher thread and the tests drive it the same way.
+#[derive(Default)]
+struct Debouncer {
+    pending: HashMap<PathBuf, (Pending, Instant)>,
+    /// The path of the last event if it was a `RenameMode::From`, with its tracker, so a
+    /// `To` right after it is taken as the other half of the rename.
+    moved_out: Option<(PathBuf, Option<usize>)>,
+}
+
+impl Debouncer {
+    /// Records one raw event that happened at `now`.
+    fn collect(&mut self, event: notify::Event, now: Instant) {
+        let moved_out = self.moved_out.take();
+        let tracker = event.attrs.tracker();
+        match (event.kind, event.paths.as_slice()) {
+            (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) => {
+                self.pending.remove(from); // Changes of the old path (a temporary file) are moot.
+                self.mark(to, Pending::RenamedFrom(from.clone()), now);
+            }
+            (EventKind::Modify(ModifyKind::Name(RenameMode::From)), [from]) => {
+                self.mark(from, Pending::Removed, now); // Moved out, unless the `To` follows.
+                self.moved_out = Some((from.clone(), tracker));
+            }
+            (EventKind::Modify(ModifyKind::Name(RenameMode::To)), [to]) => match moved_out {
+                // Both halves of a rename, reported without a `Both` event (Windows).
+                Some((from, from_tracker)) if from_tracker == tracker => {
+                    self.pending.remove(&from);
+                    self.mark(to, Pending::RenamedFrom(from), now);
+                }
+                _ => self.mark(to, Pending::Changed, now), // Moved in from elsewhere.
+            },
+            (EventKind::Remove(_), paths) => {
+                for path in paths {
+                    self.mark(path, Pending::Removed, now);
+                }
+            }
+            (EventKind::Create(_), paths)
+            | (EventKind::Modify(_), paths)
+            | (EventKind::Access(AccessKind::Close(AccessMode::Write)), paths) => {
+                for path in paths {
+                    self.mark(path, Pending::Changed, now);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Sets the pending change of `path` and restarts its quiet period. A later change
+    /// replaces an earlier one, except that a rename stays a rename when the new file is
+    /// written afterwards.
+    fn mark(&mut self, path: &Path, change: Pending, now: Instant) {
+        match self.pending.get_mut(path) {
+            Some((Pending::RenamedFrom(_), since)) if matches!(change, Pending::Changed) => {
+                *since = now;
+            }
+            _ => {
+                self.pending.insert(path.to_path_buf(), (change, now));
+            }
+        }
+    }
+
+    /// Removes and returns the changes of the paths that were quiet for `QUIET_PERIOD` at
+    /// `now`.
+    fn quiet(&mut self, now: Instant) -> Vec<FolderChange> {
+        let quiet: Vec<PathBuf> = (self.pending.iter())
+            .filter(|(_, (_, since))| now.duration_since(*since) >= QUIET_PERIOD)
+            .map(|(path, _)| path.clone())
+            .collect();
+        let mut changes = Vec::new();
+        for path in quiet {
+            let Some((change, _)) = self.pending.remove(&path) else {
+                continue;
+            };
+            changes.push(match change {
+                Pending::Changed => FolderChange::Changed(path),
+                Pending::Removed => FolderChange::Removed(path),
+                Pending::RenamedFrom(from) => FolderChange::Renamed { from, to: path },
+            });
+        }
+        changes
+    }
+}
+
+/// **WatchService Struct**
+///
+/// Watches folders recursively (inotify on Linux, the native API elsewhere) and reports
+/// created, written, deleted and renamed files and folders as `FolderChange`s.
+///
+/// Raw events are collected per path on a background thread and reported once the path
+/// has been quiet for `QUIET_PERIOD`: a file copied in produces one `Changed` after the
+/// copy, a rename (`mv`, or a tag editor replacing the file) one `Renamed`.
+pub struct WatchService {
+    /// The watched folders.
+    roots: Vec<PathBuf>,
+    /// Stops the events when dropped, so it lives as long as the service.
+    _watcher: RecommendedWatcher,
+    /// Changes reported by the debounce thread.
+    changes: Receiver<FolderChange>,
+}
+
+impl WatchService {
+    /// **Start Watching**
+    ///
+    /// # Parameters:
+    /// - `roots`: The folders to watch, with everything below them.
+    ///
+    /// # Returns:
+    /// - `Ok(WatchService)`: Watching until the service is dropped.
+    /// - `Err(Box<dyn Error>)`: If the platform cannot watch a folder (e.g. the inotify
+    ///   watch limit is reached).
+    pub fn start(roots: Vec<PathBuf>) -> Result<Self, Box<dyn Error>> {
+        let (event_sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(event_sender)?;
+        for root in &roots {
+            watcher.watch(root, RecursiveMode::Recursive)?;
+            info!("Watching {:?} for changes.", root.display());
+        }
+        let (change_sender, changes) = mpsc::channel();
+        thread::spawn(move || Self::debounce(events, change_sender));
+        Ok(WatchService {
+            roots,
+            _watcher: watcher,
+            changes,
+        })
+    }
+
+    /// Returns the changes reported since the last call, without waiting.
+    pub fn changes(&self) -> Vec<FolderChange> {
+        self.changes.try_iter().collect()
+    }
+
+    /// Returns the watched folder `path` lies in, if any.
+    pub fn root_of(&self, path: &Path) -> Option<&Path> {
+        self.roots
+            .iter()
+            .find(|root| path.starts_with(root))
+            .map(PathBuf::as_path)
+    }
+
+    /// Collects raw events per path and sends a `FolderChange` for every path that was
+    /// quiet for `QUIET_PERIOD`. Runs until the watcher or the receiver is dropped.
+    fn debounce(events: Receiver<notify::Result<notify::Event>>, changes: Sender<FolderChange>) {
+        let mut debouncer = Debouncer::default();
+        loop {
+            match events.recv_timeout(POLL_INTERVAL) {
+                Ok(Ok(event)) => debouncer.collect(event, Instant::now()),
+                Ok(Err(err)) => warn!("Folder watch error: {}", err),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            for change in debouncer.quiet(Instant::now()) {
+                debug!("Folder change: {:?}", change);
+                if changes.send(change).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, RemoveKind};
+
+    fn event(kind: EventKind, paths: &[&str]) -> notify::Event {
+        (paths.iter()).fold(notify::Event::new(kind), |event, path| {
+            event.add_path(PathBuf::from(path))
+        })
+    }
+
+    fn rename(mode: RenameMode, paths: &[&str]) -> notify::Event {
+        event(EventKind::Modify(ModifyKind::Name(mode)), paths)
+    }
+
+    fn renamed(from: &str, to: &str) -> FolderChange {
+        FolderChange::Renamed {
+            from: PathBuf::from(from),
+            to: PathBuf::from(to),
+        }
+    }
+
+    #[test]
+    fn changes_wait_for_the_quiet_period() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::default();
+        let write = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        debouncer.collect(
+            event(EventKind::Create(CreateKind::File), &["/m/a.mp3"]),
+            start,
+        );
+        for second in 1..=3 {
+            let now = start + Duration::from_millis(900 * second);
+            debouncer.collect(event(write, &["/m/a.mp3"]), now);
+            assert!(debouncer.quiet(now).is_empty(), "still being written");
+        }
+        let last = start + Duration::from_millis(2700);
+        assert!(debouncer.quiet(last + QUIET_PERIOD / 2).is_empty());
+        assert_eq!(
+            debouncer.quiet(last + QUIET_PERIOD),
+            [FolderChange::Changed(PathBuf::from("/m/a.mp3"))],
+            "one change for all the writes"
+        );
+        assert!(debouncer.quiet(last + QUIET_PERIOD * 2).is_empty());
+    }
+
+    #[test]
+    fn from_and_to_events_pair_into_a_rename() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::default();
+        debouncer.collect(rename(RenameMode::From, &["/m/old.mp3"]), start);
+        debouncer.collect(rename(RenameMode::To, &["/m/new.mp3"]), start);
+        assert_eq!(
+            debouncer.quiet(start + QUIET_PERIOD),
+            [renamed("/m/old.mp3", "/m/new.mp3")]
+        );
+
+        // inotify reports both halves and a `Both` event for the same rename.
+        let tracked = |mode, path| rename(mode, &[path]).set_tracker(7);
+        debouncer.collect(tracked(RenameMode::From, "/m/a"), start);
+        debouncer.collect(tracked(RenameMode::To, "/m/b"), start);
+        debouncer.collect(rename(RenameMode::Both, &["/m/a", "/m/b"]), start);
+        assert_eq!(
+            debouncer.quiet(start + QUIET_PERIOD),
+            [renamed("/m/a", "/m/b")]
+        );
+    }
+
+    #[test]
+    fn unpaired_halves_are_moves_in_or_out() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::default();
+        debouncer.collect(
+            rename(RenameMode::From, &["/m/out.mp3"]).set_tracker(1),
+            start,
+        );
+        debouncer.collect(rename(RenameMode::To, &["/m/in.mp3"]).set_tracker(2), start);
+        let mut changes = debouncer.quiet(start + QUIET_PERIOD);
+        changes.sort_by_key(|change| format!("{:?}", change));
+        assert_eq!(
+            changes,
+            [
+                FolderChange::Changed(PathBuf::from("/m/in.mp3")),
+                FolderChange::Removed(PathBuf::from("/m/out.mp3")),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_replaced_file_stays_a_rename() {
+        // A tag editor writes a temporary file, renames it over the track, then touches it.
+        let start = Instant::now();
+        let mut debouncer = Debouncer::default();
+        let temp = "/m/.a.mp3.tmp";
+        debouncer.collect(event(EventKind::Create(CreateKind::File), &[temp]), start);
+        debouncer.collect(rename(RenameMode::Both, &[temp, "/m/a.mp3"]), start);
+        let later = start + Duration::from_millis(500);
+        let write = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+        debouncer.collect(event(write, &["/m/a.mp3"]), later);
+        assert!(
+            debouncer.quiet(start + QUIET_PERIOD).is_empty(),
+            "written since"
+        );
+        assert_eq!(
+            debouncer.quiet(later + QUIET_PERIOD),
+            [renamed(temp, "/m/a.mp3")]
+        );
+
+        debouncer.collect(
+            event(EventKind::Remove(RemoveKind::File), &["/m/a.mp3"]),
+            later,
+        );
+        assert_eq!(
+            debouncer.quiet(later + QUIET_PERIOD),
+            [FolderChange::Removed(PathBuf::from("/m/a.mp3"))]
+        );
+    }
+}
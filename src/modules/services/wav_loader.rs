@@ -1,34 +1,121 @@
-use hound;
+use hound::SampleFormat;
 use std::error::Error;
 use std::path::Path;
 
-use super::audio_loader::AudioFileLoader;
+use super::audio_loader::{AudioFileLoader, DecodedAudio};
 
 /// **WavLoader Struct**
 ///
-/// This struct loads WAV files, extracting samples, sample rate, and channel count.
+/// This struct loads WAV files, extracting samples, channel count and sample rate. Integer samples of any
+/// bit depth (8, 16, 24, 32) and 32-bit float samples are supported.
 pub struct WavLoader;
 
 impl AudioFileLoader for WavLoader {
-    /// Loads WAV samples, sample rate, and channel count.
+    /// Loads WAV samples, channel count and sample rate.
     ///
     /// # Parameters:
     /// - `path`: Path to the WAV file.
     ///
     /// # Returns:
-    /// - `Ok((Vec<f32>, u32, u16))`: Samples, sample rate, and channels.
-    /// - `Err(Box<dyn Error>)`: On failure, returns an error.
-    fn load_samples(&self, path: &Path) -> Result<(Vec<f32>, u16), Box<dyn Error>> {
+    /// - `Ok(DecodedAudio)`: Samples, channels and sample rate.
+    /// - `Err(Box<dyn Error>)`: On failure (unsupported sample format, truncated or
+    ///   corrupt data), returns an error.
+    fn load_samples(&self, path: &Path) -> Result<DecodedAudio, Box<dyn Error>> {
         let reader = hound::WavReader::open(path)?;
         let spec = reader.spec();
 
         let channels = spec.channels;
 
-        let samples: Vec<f32> = reader
-            .into_samples::<i16>()
-            .map(|s| s.unwrap() as f32 / i16::MAX as f32)
-            .collect();
+        let samples: Vec<f32> = match (spec.sample_format, spec.bits_per_sample) {
+            (SampleFormat::Float, 32) => reader.into_samples::<f32>().collect::<Result<_, _>>()?,
+            (SampleFormat::Int, bits @ 1..=32) => {
+                // Divide by the full scale of the bit depth, so every depth maps to -1.0..1.0.
+                let scale = (1u64 << (bits - 1)) as f32;
+                reader
+                    .into_samples::<i32>()
+                    .map(|sample| sample.map(|sample| sample as f32 / scale))
+                    .collect::<Result<_, _>>()?
+            }
+            (format, bits) => {
+                return Err(
+                    format!("Unsupported WAV sample format: {:?} {} bit", format, bits).into(),
+                )
+            }
+        };
 
-        Ok((samples, channels))
+        Ok(DecodedAudio {
+            samples,
+            channels,
+            sample_rate: spec.sample_rate,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::services::temp_folder::TempFolder;
+    use hound::{WavSpec, WavWriter};
+    use std::fs;
+
+    /// Writes a one-channel WAV file with the given sample format.
+    fn write_wav<S: hound::Sample + Copy>(
+        folder: &TempFolder,
+        name: &str,
+        sample_format: SampleFormat,
+        bits_per_sample: u16,
+        samples: &[S],
+    ) -> std::path::PathBuf {
+        let path = folder.path().join(name);
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample,
+            sample_format,
+        };
+        let mut writer = WavWriter::create(&path, spec).unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    #[test]
+    fn decodes_24_bit_samples() {
+        let folder = TempFolder::new("wav-24-bit");
+        let path = write_wav(
+            &folder,
+            "24.wav",
+            SampleFormat::Int,
+            24,
+            &[0, 4_194_304, -8_388_608],
+        );
+        let audio = WavLoader.load_samples(&path).unwrap();
+        assert_eq!((audio.channels, audio.sample_rate), (1, 8000));
+        assert_eq!(audio.samples, vec![0.0, 0.5, -1.0]);
+    }
+
+    #[test]
+    fn decodes_float_samples() {
+        let folder = TempFolder::new("wav-float");
+        let path = write_wav(
+            &folder,
+            "float.wav",
+            SampleFormat::Float,
+            32,
+            &[0.25f32, -0.75],
+        );
+        let audio = WavLoader.load_samples(&path).unwrap();
+        assert_eq!(audio.samples, vec![0.25, -0.75]);
+    }
+
+    #[test]
+    fn truncated_file_is_an_error() {
+        let folder = TempFolder::new("wav-truncated");
+        let path = write_wav(&folder, "cut.wav", SampleFormat::Int, 16, &[1i16; 100]);
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 51]).unwrap(); // Ends in the middle of a sample.
+        assert!(WavLoader.load_samples(&path).is_err());
     }
 }
@@ -0,0 +1,316 @@
+use std::env;
+use std::error::Error;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, RgbImage};
+
+use crate::modules::models::artwork_model::Artwork;
+
+/// Environment variable with the artwork mode shown on track changes
+/// (`off`, `auto`, `blocks`, `sixel`, `kitty`), `off` if unset.
+pub const ARTWORK_VAR: &str = "SENSIT_ARTWORK";
+
+/// Width of the rendered artwork in terminal columns.
+const ARTWORK_COLUMNS: u32 = 32;
+
+/// Approximate size of a terminal cell in pixels, for the pixel protocols.
+const CELL_WIDTH: u32 = 8;
+
+/// `ArtworkProtocol` is the way an image is drawn in the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArtworkProtocol {
+    /// Unicode upper half blocks (`▀`) with a foreground and background colour, two
+    /// pixels per cell. Works in any colour terminal.
+    HalfBlocks,
+    /// DEC sixel graphics (xterm -ti vt340, mlterm, foot, WezTerm, ...).
+    Sixel,
+    /// The kitty graphics protocol (kitty, WezTerm, Ghostty).
+    Kitty,
+}
+
+impl ArtworkProtocol {
+    /// Parses a protocol name as typed in the CLI (`blocks`, `sixel`, `kitty`, `auto`).
+    ///
+    /// # Returns
+    /// * `Some(ArtworkProtocol)` if the name is known (`auto` detects it), otherwise `None`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "auto" | "on" => Some(Self::detect()),
+            "blocks" | "halfblocks" | "unicode" => Some(ArtworkProtocol::HalfBlocks),
+            "sixel" => Some(ArtworkProtocol::Sixel),
+            "kitty" => Some(ArtworkProtocol::Kitty),
+            _ => None,
+        }
+    }
+
+    /// Guesses the best protocol the terminal supports from `TERM`, `TERM_PROGRAM` and
+    /// `KITTY_WINDOW_ID`, falling back to half blocks.
+    pub fn detect() -> Self {
+        Self::for_terminal(
+            &env::var("TERM").unwrap_or_default(),
+            &env::var("TERM_PROGRAM").unwrap_or_default(),
+            env::var_os("KITTY_WINDOW_ID").is_some(),
+        )
+    }
+
+    /// Picks the protocol for a terminal by its `TERM` and `TERM_PROGRAM` values, and
+    /// whether it runs inside a kitty window.
+    fn for_terminal(term: &str, program: &str, kitty_window: bool) -> Self {
+        let (term, program) = (term.to_lowercase(), program.to_lowercase());
+        if kitty_window
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || program == "wezterm"
+            || program == "ghostty"
+        {
+            ArtworkProtocol::Kitty
+        } else if term.contains("sixel")
+            || term.starts_with("mlterm")
+            || term.starts_with("foot")
+            || term.starts_with("yaft")
+        {
+            ArtworkProtocol::Sixel
+        } else {
+            ArtworkProtocol::HalfBlocks
+        }
+    }
+}
+
+/// **ArtworkView Struct**
+///
+/// Draws cover art in the terminal.
+pub struct ArtworkView;
+
+impl ArtworkView {
+    /// **Artwork Mode from the Environment**
+    ///
+    /// # Returns:
+    /// - The protocol set by `SENSIT_ARTWORK`, `None` if artwork is off (the default).
+    pub fn from_env() -> Option<ArtworkProtocol> {
+        ArtworkProtocol::from_name(&env::var(ARTWORK_VAR).ok()?)
+    }
+
+    /// **Render Artwork**
+    ///
+    /// # Parameters:
+    /// - `artwork`: The encoded cover image (JPEG or PNG).
+    /// - `protocol`: How to draw it.
+    ///
+    /// # Returns:
+    /// - `Ok(String)`: The text to print, ending with a newline.
+    /// - `Err(Box<dyn Error>)`: If the image cannot be decoded.
+    pub fn render(
+        &self,
+        artwork: &Artwork,
+        protocol: ArtworkProtocol,
+    ) -> Result<String, Box<dyn Error>> {
+        let image = image::load_from_memory(&artwork.data)?;
+        Ok(match protocol {
+            ArtworkProtocol::HalfBlocks => {
+                let truecolor = env::var("COLORTERM")
+                    .is_ok_and(|value| value.contains("truecolor") || value.contains("24bit"));
+                Self::half_blocks(&image, truecolor)
+            }
+            ArtworkProtocol::Sixel => Self::sixel(&image),
+            ArtworkProtocol::Kitty => Self::kitty(&image),
+        })
+    }
+
+    /// Scales the image to `width` pixels, keeping the aspect ratio. `row_multiple` rounds
+    /// the height so whole character rows (or sixel bands) are filled.
+    fn scale(image: &DynamicImage, width: u32, row_multiple: u32) -> RgbImage {
+        let height = (image.height() * width / image.width().max(1)).max(1);
+        let height = height.div_ceil(row_multiple) * row_multiple;
+        image
+            .resize_exact(width, height, FilterType::Triangle)
+            .to_rgb8()
+    }
+
+    /// Draws two pixels per cell: the upper one as the foreground of `▀`, the lower one
+    /// as the background. Truecolor if `truecolor` is set (`COLORTERM` says so), the
+    /// 256-colour cube otherwise.
+    fn half_blocks(image: &DynamicImage, truecolor: bool) -> String {
+        let colour = |layer: u8, [r, g, b]: [u8; 3]| {
+            if truecolor {
+                format!("\x1b[{};2;{};{};{}m", layer, r, g, b)
+            } else {
+                let cube = |c: u8| (u16::from(c) * 5 / 255) as u8;
+                let index = 16 + 36 * cube(r) + 6 * cube(g) + cube(b);
+                format!("\x1b[{};5;{}m", layer, index)
+            }
+        };
+
+        let pixels = Self::scale(image, ARTWORK_COLUMNS, 2);
+        let mut out = String::new();
+        for y in (0..pixels.height()).step_by(2) {
+            for x in 0..pixels.width() {
+                out.push_str(&colour(38, pixels.get_pixel(x, y).0));
+                out.push_str(&colour(48, pixels.get_pixel(x, y + 1).0));
+                out.push('▀');
+            }
+            out.push_str("\x1b[0m\n");
+        }
+        out
+    }
+
+    /// Encodes the image as sixels with a 6x6x6 colour cube palette.
+    fn sixel(image: &DynamicImage) -> String {
+        let pixels = Self::scale(image, ARTWORK_COLUMNS * CELL_WIDTH, 6);
+        let cube = |c: u8| (u16::from(c) * 5 / 255) as usize;
+        let index = |[r, g, b]: [u8; 3]| 36 * cube(r) + 6 * cube(g) + cube(b);
+
+        let mut out = format!("\x1bPq\"1;1;{};{}", pixels.width(), pixels.height());
+        for i in 0..216 {
+            let level = |c: usize| c * 100 / 5;
+            out.push_str(&format!(
+                "#{};2;{};{};{}",
+                i,
+                level(i / 36),
+                level(i / 6 % 6),
+                level(i % 6)
+            ));
+        }
+        for band in (0..pixels.height()).step_by(6) {
+            // One pass per colour used in the band, each drawing the pixels of that colour.
+            let mut colours: Vec<usize> = (0..pixels.width())
+                .flat_map(|x| (band..band + 6).map(move |y| (x, y)))
+                .map(|(x, y)| index(pixels.get_pixel(x, y).0))
+                .collect();
+            colours.sort_unstable();
+            colours.dedup();
+            for colour in colours {
+                out.push_str(&format!("#{}", colour));
+                let mut run: Option<(char, usize)> = None;
+                for x in 0..pixels.width() {
+                    let bits = (0..6)
+                        .filter(|&row| index(pixels.get_pixel(x, band + row).0) == colour)
+                        .fold(0u8, |bits, row| bits | 1 << row);
+                    let sixel = char::from(63 + bits);
+                    run = match run {
+                        Some((c, count)) if c == sixel => Some((c, count + 1)),
+                        Some(previous) => {
+                            Self::push_sixel_run(&mut out, previous);
+                            Some((sixel, 1))
+                        }
+                        None => Some((sixel, 1)),
+                    };
+                }
+                if let Some(run) = run {
+                    Self::push_sixel_run(&mut out, run);
+                }
+                out.push('$'); // Back to the start of the band for the next colour.
+            }
+            out.push('-'); // Next band.
+        }
+        out.push_str("\x1b\\\n");
+        out
+    }
+
+    /// Appends a run of one sixel character, compressed with `!count` when it pays off.
+    fn push_sixel_run(out: &mut String, (sixel, count): (char, usize)) {
+        if count > 3 {
+            out.push_str(&format!("!{}{}", count, sixel));
+        } else {
+            out.extend(std::iter::repeat_n(sixel, count));
+        }
+    }
+
+    /// Sends the pixels as RGB with the kitty graphics protocol, in base64 chunks of at
+    /// most 4096 bytes, scaled by the terminal to `ARTWORK_COLUMNS` columns.
+    fn kitty(image: &DynamicImage) -> String {
+        let pixels = Self::scale(image, ARTWORK_COLUMNS * CELL_WIDTH, 1);
+        let payload = Self::base64(pixels.as_raw());
+        let chunks: Vec<&[u8]> = payload.as_bytes().chunks(4096).collect();
+        let mut out = String::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more = u8::from(i + 1 < chunks.len());
+            let chunk = String::from_utf8_lossy(chunk);
+            if i == 0 {
+                out.push_str(&format!(
+                    "\x1b_Ga=T,f=24,s={},v={},c={},m={};{}\x1b\\",
+                    pixels.width(),
+                    pixels.height(),
+                    ARTWORK_COLUMNS,
+                    more,
+                    chunk
+                ));
+            } else {
+                out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+            }
+        }
+        out.push('\n');
+        out
+    }
+
+    /// Standard base64 with padding.
+    fn base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8; 64] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let n = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    out.push(char::from(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize]));
+                } else {
+                    out.push('=');
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn protocols_follow_the_terminal() {
+        let detect = ArtworkProtocol::for_terminal;
+        assert_eq!(detect("xterm-kitty", "", false), ArtworkProtocol::Kitty);
+        assert_eq!(detect("xterm-ghostty", "", false), ArtworkProtocol::Kitty);
+        assert_eq!(
+            detect("xterm-256color", "WezTerm", false),
+            ArtworkProtocol::Kitty
+        );
+        assert_eq!(detect("xterm-256color", "", true), ArtworkProtocol::Kitty);
+        assert_eq!(detect("foot", "", false), ArtworkProtocol::Sixel);
+        assert_eq!(detect("mlterm", "", false), ArtworkProtocol::Sixel);
+        assert_eq!(detect("xterm-sixel", "", false), ArtworkProtocol::Sixel);
+        assert_eq!(
+            detect("xterm-256color", "Apple_Terminal", false),
+            ArtworkProtocol::HalfBlocks
+        );
+        assert_eq!(detect("", "", false), ArtworkProtocol::HalfBlocks);
+    }
+
+    #[test]
+    fn half_blocks_draw_two_pixel_rows_per_line() {
+        // Red on top, blue at the bottom, scaled up to the artwork width.
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(2, 2, |_, y| match y {
+            0 => Rgb([255, 0, 0]),
+            _ => Rgb([0, 0, 255]),
+        }));
+        let cells = |top: &str, bottom: &str| {
+            format!("\x1b[38;{}m\x1b[48;{}m▀", top, bottom).repeat(ARTWORK_COLUMNS as usize)
+                + "\x1b[0m"
+        };
+
+        let out = ArtworkView::half_blocks(&image, true);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), ARTWORK_COLUMNS as usize / 2, "a square image");
+        assert_eq!(lines[0], cells("2;255;0;0", "2;255;0;0"));
+        assert_eq!(lines[lines.len() - 1], cells("2;0;0;255", "2;0;0;255"));
+
+        let out = ArtworkView::half_blocks(&image, false);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], cells("5;196", "5;196"), "red in the colour cube");
+        assert_eq!(lines[lines.len() - 1], cells("5;21", "5;21"));
+        assert!(out.ends_with("\x1b[0m\n"));
+    }
+}
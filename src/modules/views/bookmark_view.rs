@@ -0,0 +1,138 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::modules::{
+    controllers::async_player_controller::Command,
+    models::bookmark_model::{Bookmark, BookmarkModel},
+    services::metadata_service::MetadataService,
+    views::cli_view::{CliContext, CliView},
+};
+
+/// The commands of the bookmark view, as listed by `help`.
+pub const BOOKMARK_HELP: &str = "\
+🔖 Bookmarks
+  bm [all]                List the bookmarks of the current track (or of every file), numbered
+  bm add [name]           Bookmark the current position
+  bm go <n>               Play from bookmark n of the last listing
+  bm del <n>              Remove bookmark n of the last listing
+  resume [minutes|off]    Show or set the length from which files resume where they were left";
+
+/// **BookmarkView Struct**
+///
+/// Lists, adds, plays and removes bookmarks and sets when long files resume, saving the
+/// bookmarks after every change. Keeps the numbered bookmarks of the last `bm` listing.
+#[derive(Default)]
+pub struct BookmarkView {
+    /// The bookmarks of the last `bm` listing with their files, in the numbered order.
+    marks: Vec<(PathBuf, Bookmark)>,
+}
+
+impl BookmarkView {
+    /// Runs a bookmark command (`bm`, `resume`).
+    ///
+    /// # Returns
+    /// `true` if `command` is one of them, `false` to let another view try it.
+    pub fn run(&mut self, context: &mut CliContext, command: &str, args: &[&str]) -> bool {
+        let player = context.player;
+        match (command, args) {
+            ("bm", args @ ([] | ["all"])) => {
+                let track = player.status().and_then(|status| status.current_track);
+                if let Some(bookmarks) = player.bookmarks() {
+                    self.marks = Self::show(&bookmarks, track.as_deref(), !args.is_empty());
+                }
+            }
+            ("bm", ["add", name @ ..]) => {
+                let name = Some(name.join(" ")).filter(|name| !name.is_empty());
+                player.send(Command::AddBookmark(name));
+                CliView::save_bookmarks(context);
+            }
+            ("bm", ["go", number]) => match self.mark(number) {
+                Some((path, bookmark)) => {
+                    let metadata = match context.library.get(path) {
+                        Some(entry) => entry.metadata.clone(),
+                        None => MetadataService.read(path),
+                    };
+                    player.send(Command::PlayBookmark(
+                        path.clone(),
+                        metadata,
+                        bookmark.position,
+                    ));
+                }
+                None => println!("⚠️  No bookmark {}, see `bm`.", number),
+            },
+            ("bm", ["del", number]) => match self.mark(number).cloned() {
+                Some(mark) => {
+                    self.marks.retain(|other| *other != mark); // Later numbers move up.
+                    let (path, bookmark) = mark;
+                    println!("🗑️  Removed {}.", bookmark.name);
+                    player.send(Command::RemoveBookmark(path, bookmark));
+                    CliView::save_bookmarks(context);
+                }
+                None => println!("⚠️  No bookmark {}, see `bm`.", number),
+            },
+            ("resume", []) => {
+                if let Some(bookmarks) = player.bookmarks() {
+                    match bookmarks.resume_after() {
+                        Some(length) => println!(
+                            "⏱️  Files of {} and longer resume where they were left.",
+                            CliView::format_time(length)
+                        ),
+                        None => println!("⏱️  Files always start at the beginning."),
+                    }
+                }
+            }
+            ("resume", ["off"]) => player.send(Command::SetResumeAfter(None)),
+            ("resume", [minutes]) => match minutes.parse::<f64>() {
+                Ok(minutes) if minutes > 0.0 && minutes.is_finite() => player.send(
+                    Command::SetResumeAfter(Some(Duration::from_secs_f64(minutes * 60.0))),
+                ),
+                _ => println!("Usage: resume <minutes|off>"),
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    /// Prints the bookmarks of the current `track`, or of every file if `all` is set,
+    /// numbered from 1.
+    ///
+    /// # Returns
+    /// The listed bookmarks with their files, in the numbered order.
+    fn show(
+        bookmarks: &BookmarkModel,
+        track: Option<&Path>,
+        all: bool,
+    ) -> Vec<(PathBuf, Bookmark)> {
+        let marks: Vec<(PathBuf, Bookmark)> = match (all, track) {
+            (true, _) => (bookmarks.all())
+                .map(|(path, bookmark)| (path.to_path_buf(), bookmark.clone()))
+                .collect(),
+            (false, Some(track)) => (bookmarks.bookmarks(track).iter())
+                .map(|bookmark| (track.to_path_buf(), bookmark.clone()))
+                .collect(),
+            (false, None) => Vec::new(),
+        };
+        if marks.is_empty() {
+            println!("No bookmarks yet, `bm add [name]` adds one.");
+        }
+        let mut file = None;
+        for (number, (path, bookmark)) in marks.iter().enumerate() {
+            if all && file != Some(path) {
+                println!("{}", path.display());
+                file = Some(path);
+            }
+            println!(
+                "  {:>3}. {} [{}]",
+                number + 1,
+                bookmark.name,
+                CliView::format_time(bookmark.position)
+            );
+        }
+        marks
+    }
+
+    /// Picks a bookmark of the last `bm` listing by its 1-based number.
+    fn mark(&self, number: &str) -> Option<&(PathBuf, Bookmark)> {
+        self.marks.get(CliView::parse_position(number)?)
+    }
+}
@@ -0,0 +1,144 @@
+use crate::modules::{
+    controllers::async_player_controller::Command,
+    models::browse_model::{BrowseModel, BrowseNode},
+    models::library_model::LibraryModel,
+    views::cli_view::{CliContext, CliView},
+};
+
+/// The commands of the browse view, as listed by `help`.
+pub const BROWSE_HELP: &str = "\
+🗂️  Browse
+  browse [artists|albums|genres|years]
+                          Browse the library from the top
+  open <n>                Open entry n of the browse list
+  up                      Go back to the list above
+  bplay [n]               Play the opened entry (or entry n) in disc and track order,
+                          replacing the folder tracks. In an album, n is the track to start with
+  benqueue [n]            Add the tracks of the opened entry (or entry n) to the queue";
+
+/// **BrowseView Struct**
+///
+/// Browses the library by artist, album, genre or year (see `BrowseModel`), keeping the
+/// opened entries.
+#[derive(Default)]
+pub struct BrowseView {
+    /// The browse path, the entries below the last one are shown.
+    opened: Vec<BrowseNode>,
+}
+
+impl BrowseView {
+    /// Runs a browse command (`browse`, `open`, `up`, `bplay`, `benqueue`).
+    ///
+    /// # Returns
+    /// `true` if `command` is one of them, `false` to let another view try it.
+    pub fn run(&mut self, context: &mut CliContext, command: &str, args: &[&str]) -> bool {
+        let library = &*context.library;
+        match (command, args) {
+            ("browse", args) => match BrowseNode::from_name(args.first().unwrap_or(&"artists")) {
+                Some(root) if args.len() <= 1 => {
+                    self.opened = vec![root];
+                    self.show(library);
+                }
+                _ => println!("Usage: browse [artists|albums|genres|years]"),
+            },
+            ("open", [number]) => {
+                if let Some(child) = self.child(library, number) {
+                    self.opened.push(child);
+                    self.show(library);
+                }
+            }
+            ("up", []) => {
+                if self.opened.len() > 1 {
+                    self.opened.pop();
+                }
+                self.show(library);
+            }
+            (command @ ("bplay" | "benqueue"), args) if args.len() <= 1 => {
+                self.play(context, command == "bplay", args.first().copied())
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Plays or enqueues the tracks of the opened entry, or of entry `number` of the list.
+    /// In an album `number` picks the track to start with instead.
+    fn play(&self, context: &CliContext, play: bool, number: Option<&str>) {
+        let Some(node) = self.opened.last() else {
+            println!("Nothing to play, start with `browse`.");
+            return;
+        };
+        let (node, start) = match number {
+            Some(number) if matches!(node, BrowseNode::Album { .. }) => {
+                match CliView::parse_position(number) {
+                    Some(start) => (node.clone(), start),
+                    None => {
+                        println!("⚠️  No entry {}, see `browse`.", number);
+                        return;
+                    }
+                }
+            }
+            Some(number) => match self.child(context.library, number) {
+                Some(child) => (child, 0),
+                None => return,
+            },
+            None => (node.clone(), 0),
+        };
+        let tracks = BrowseModel::new(context.library).tracks(&node);
+        if play {
+            CliView::play_tracks(context.player, &tracks, start);
+        } else {
+            for entry in &tracks {
+                context.player.send(Command::Enqueue(entry.path.clone()));
+            }
+            println!("📥 Queued {} tracks of {}.", tracks.len(), node.name());
+        }
+    }
+
+    /// Prints the browse path and the entries below the last node, numbered from 1.
+    fn show(&self, library: &LibraryModel) {
+        let Some(node) = self.opened.last() else {
+            println!("Start with `browse [artists|albums|genres|years]`.");
+            return;
+        };
+        let path: Vec<String> = self.opened.iter().map(BrowseNode::name).collect();
+        println!("🗂️  {}", path.join(" › "));
+        let children = BrowseModel::new(library).children(node);
+        if children.is_empty() {
+            println!("  Nothing here.");
+        }
+        for (index, (child, count)) in children.iter().enumerate() {
+            match child {
+                BrowseNode::Track(track) => {
+                    let metadata = library
+                        .get(track)
+                        .map(|entry| entry.metadata.clone())
+                        .unwrap_or_default();
+                    let number = match (metadata.disc_number, metadata.track_number) {
+                        (Some(disc), Some(number)) => format!("{}-{:02} ", disc, number),
+                        (None, Some(number)) => format!("{:02} ", number),
+                        _ => String::new(),
+                    };
+                    let name = CliView::display_name(&metadata, track);
+                    println!("  {:>3}. {}{}", index + 1, number, name);
+                }
+                _ => println!("  {:>3}. {} ({} tracks)", index + 1, child.name(), count),
+            }
+        }
+    }
+
+    /// Picks an entry of the shown browse list by its 1-based number.
+    fn child(&self, library: &LibraryModel, number: &str) -> Option<BrowseNode> {
+        let child = self.opened.last().and_then(|node| {
+            let index = CliView::parse_position(number)?;
+            BrowseModel::new(library)
+                .children(node)
+                .into_iter()
+                .nth(index)
+        });
+        if child.is_none() {
+            println!("⚠️  No entry {}, see `browse`.", number);
+        }
+        child.map(|(child, _)| child)
+    }
+}
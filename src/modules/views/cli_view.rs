@@ -1,62 +1,733 @@
+use std::fs;
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
 
 use crate::modules::{
-    controllers::player_controller::PlayerController,
+    controllers::async_player_controller::{AsyncPlayerController, Command},
+    models::artwork_model::Artwork,
+    models::audio_folder_model::{AudioFolderModel, SortKey},
+    models::crossfade_model::{CrossfadeModel, FadeCurve},
+    models::folder_change_model::FolderChange,
+    models::library_model::{FileUpdate, LibraryEntry, LibraryModel},
+    models::player_event_model::Event,
+    models::player_status_model::PlayerStatus,
+    models::playlist_model::PlaylistEntry,
+    models::session_model::SessionModel,
+    models::track_metadata_model::{TagField, TrackMetadata},
     services::audio_folder_service::AudioFolderService,
+    services::bookmark_service::BookmarkService,
+    services::cue_service::CueService,
+    services::history_service::HistoryService,
+    services::library_service::LibraryService,
+    services::metadata_service::MetadataService,
+    services::playlist_service::PlaylistService,
+    services::session_service::SessionService,
+    services::smart_playlist_service::SmartPlaylistService,
+    services::tag_service::TagService,
+    services::watch_service::WatchService,
+    views::artwork_view::{ArtworkProtocol, ArtworkView},
+    views::bookmark_view::{BookmarkView, BOOKMARK_HELP},
+    views::browse_view::{BrowseView, BROWSE_HELP},
+    views::history_view::{HistoryView, HISTORY_HELP},
+    views::rating_view::{RatingView, RATING_HELP},
+    views::search_view::{SearchView, SEARCH_HELP},
+    views::smart_view::{SmartView, SMART_HELP},
 };
 
+/// How often pending player events are shown while the CLI waits for input.
+const EVENT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The playback, track and queue commands, as listed by `help`.
+const PLAYER_HELP: &str = "\
+▶️  Playback
+  p                       Toggle play/pause (`play` and `pause` do just one of them)
+  j / k                   Play the previous / next track
+  s                       Stop (fade out and rewind to the beginning)
+  z                       Toggle shuffle
+  r                       Cycle the repeat mode (off, all, one)
+  seek <seconds>          Jump to a position in the current track (+10/-10 for relative)
+  vol [percent]           Show or set the volume (+10/-10 for relative)
+  c <seconds> [linear|equal|log]
+                          Set the crossfade (`c 0` turns it off)
+  fade <milliseconds>     Set the click-free ramp used on play/pause/stop/seek
+  art [on|off|auto|blocks|sixel|kitty]
+                          Show the cover art of the current track, or on every track change
+📋 Tracks and queue
+  list                    List the folder tracks with their numbers
+  sort <name|mtime|track|duration>
+                          Reorder the folder tracks
+  tag [<n|path>] [set <field> <value>|clear <field>]
+                          Show or edit the tags of the current (or given) track
+  queue                   Show the play queue
+  enqueue <n|path>        Add a folder track (by number) or any file to the queue
+  playnext <n|path>       Queue a track right after the current one
+  dequeue <position>      Remove an entry from the queue
+  qmove <from> <to>       Move a queue entry
+  qclear                  Empty the queue
+  export <file.m3u8|file.xspf>
+                          Save the folder tracks as a playlist (`qexport` saves the queue)";
+
+/// The commands of the CLI itself, listed last by `help`.
+const CLI_HELP: &str = "\
+❓ CLI
+  help                    List the commands
+  q                       Quit";
+
+/// **CliContext Struct**
+///
+/// The player, the library and the services the CLI and its views work with.
+pub struct CliContext<'a> {
+    /// The player the commands are sent to (play/pause, previous, next, ...).
+    pub player: &'a AsyncPlayerController,
+    /// The music library searched and browsed, kept up to date with `watcher`.
+    pub library: &'a mut LibraryModel,
+    /// Saves the library after the watched folder changed or a track was rated or starred.
+    pub library_service: &'a LibraryService,
+    /// Decides which new files are tracks (the `ScanOptions`).
+    pub audio_folder_service: &'a AudioFolderService,
+    /// Saves the bookmarks after they were added, removed or moved.
+    pub bookmark_service: &'a BookmarkService,
+    /// The play history shown by `history` and `top` and used by smart playlists.
+    pub history_service: &'a HistoryService,
+    /// The smart playlist definitions.
+    pub smart_playlist_service: &'a SmartPlaylistService,
+    /// Reports changes to the opened folder, `None` if it is not watched.
+    pub watcher: Option<&'a WatchService>,
+}
+
 pub struct CliView;
 
 impl CliView {
     /// Starts the command-line interface (CLI) to control audio playback.
     ///
     /// # Parameters
-    /// - `player_controller`: A mutable reference to the `PlayerController`
-    ///   used to control playback operations (play/pause, previous, next).
+    /// - `context`: The player, the library and the services the commands work with. New
+    ///   files in the watched folder are added to the tracks, deleted ones removed and
+    ///   renamed ones followed.
     ///
     /// # Behavior
-    /// - Continuously prompts the user to enter a command.
-    /// - Commands:
-    ///     - `p`: Toggle play/pause.
-    ///     - `j`: Play the previous track.
-    ///     - `k`: Play the next track.
-    ///     - `q`: Quit the CLI.
+    /// - Continuously prompts the user to enter a command, `help` lists them.
+    /// - The playback, queue and tag commands are handled here, the others by the search,
+    ///   history, browse, bookmark, smart playlist and rating views in that order.
     /// - If an invalid command is entered, it displays a message and waits for new input.
+    /// - Stdin is read on a separate thread so player events (track changes, errors) are
+    ///   shown while the prompt waits.
     ///
     /// # Panics
     /// - If `stdout` flush or `stdin` read fails, it will panic with `unwrap()`.
-    pub fn start_cli(player_controller: &mut PlayerController) {
+    pub fn start_cli(mut context: CliContext) {
+        let player = context.player;
+        let events = player.subscribe();
+        let mut artwork = ArtworkView::from_env(); // Shown on track changes if set.
+        let mut search = SearchView::default();
+        let mut browse = BrowseView::default();
+        let mut bookmarks = BookmarkView::default();
+        let (line_sender, lines) = mpsc::channel::<String>();
+        thread::spawn(move || loop {
+            let mut input = String::new();
+            io::stdin().read_line(&mut input).unwrap();
+            if line_sender.send(input).is_err() {
+                break; // The CLI has quit.
+            }
+        });
+
         loop {
             // Get user input
-            print!(
-                "🎵 Enter command (▶️  p = play/pause, ⏮️  j = prev, ⏭️  k = next, 🛑 q = quit): "
-            );
+            print!("🎵 Enter command (help = list commands, q = quit): ");
             io::stdout().flush().unwrap();
 
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
-            let input = input.trim();
+            let input = loop {
+                match lines.recv_timeout(EVENT_INTERVAL) {
+                    Ok(input) => break input,
+                    Err(RecvTimeoutError::Timeout) => {
+                        Self::show_events(&events, artwork);
+                        Self::apply_folder_changes(&mut context);
+                    }
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            };
+            let mut words = input.split_whitespace();
+
+            match (
+                words.next().unwrap_or(""),
+                words.collect::<Vec<_>>().as_slice(),
+            ) {
+                ("", []) => {}
+                ("help", []) => {
+                    for help in [
+                        PLAYER_HELP,
+                        SEARCH_HELP,
+                        BROWSE_HELP,
+                        BOOKMARK_HELP,
+                        SMART_HELP,
+                        RATING_HELP,
+                        HISTORY_HELP,
+                        CLI_HELP,
+                    ] {
+                        println!("{}", help);
+                    }
+                }
+                ("p", []) => player.send(Command::TogglePlay),
+                ("play", []) => player.send(Command::Play),
+                ("pause", []) => player.send(Command::Pause),
+                ("j", []) => player.send(Command::Prev),
+                ("k", []) => player.send(Command::Next),
+                ("s", []) => player.send(Command::Stop),
+                ("z", []) => player.send(Command::ToggleShuffle),
+                ("r", []) => player.send(Command::CycleRepeat),
+                ("seek", [seconds]) => match Self::parse_seek(seconds) {
+                    Some(command) => player.send(command),
+                    None => println!("Usage: seek <seconds> | seek +<seconds> | seek -<seconds>"),
+                },
+                ("fade", [millis]) => match millis.parse::<u64>() {
+                    Ok(millis) => player.send(Command::SetFade(Duration::from_millis(millis))),
+                    Err(_) => println!("Usage: fade <milliseconds>"),
+                },
+                ("vol", []) => match player.status() {
+                    Some(status) => println!("🔊 Volume {:.0}%", status.volume * 100.0),
+                    None => return,
+                },
+                ("vol", [percent]) => match Self::parse_volume(player, percent) {
+                    Some(volume) => player.send(Command::SetVolume(volume)),
+                    None => println!("Usage: vol <0-100> (or +10 / -10)"),
+                },
+                ("c", args) => match Self::parse_crossfade(args) {
+                    Some(crossfade) => player.send(Command::SetCrossfade(crossfade)),
+                    None => println!("Usage: c <0-12 seconds> [linear|equal|log]"),
+                },
+                ("list", []) => match (player.status(), player.metadata()) {
+                    (Some(status), Some(tracks)) => {
+                        Self::show_tracks(&tracks, status.current_index)
+                    }
+                    _ => return,
+                },
+                ("sort", [key]) => match SortKey::from_name(key) {
+                    Some(key) => player.send(Command::Sort(key)),
+                    None => println!("Usage: sort <name|mtime|track|duration>"),
+                },
+                ("queue", []) => match player.status() {
+                    Some(status) => Self::show_queue(&status),
+                    None => return,
+                },
+                ("enqueue", args) if !args.is_empty() => {
+                    if let Some(track) = Self::resolve_track(player, args) {
+                        player.send(Command::Enqueue(track));
+                    }
+                }
+                ("playnext", args) if !args.is_empty() => {
+                    if let Some(track) = Self::resolve_track(player, args) {
+                        player.send(Command::PlayNext(track));
+                    }
+                }
+                ("dequeue", [position]) => match Self::parse_position(position) {
+                    Some(position) => player.send(Command::RemoveFromQueue(position)),
+                    None => println!("Usage: dequeue <position>"),
+                },
+                ("qmove", [from, to]) => {
+                    match (Self::parse_position(from), Self::parse_position(to)) {
+                        (Some(from), Some(to)) => player.send(Command::MoveInQueue(from, to)),
+                        _ => println!("Usage: qmove <from> <to>"),
+                    }
+                }
+                ("qclear", []) => player.send(Command::ClearQueue),
+                ("export", args) if !args.is_empty() => {
+                    Self::export_playlist(player, Path::new(&args.join(" ")), false)
+                }
+                ("qexport", args) if !args.is_empty() => {
+                    Self::export_playlist(player, Path::new(&args.join(" ")), true)
+                }
+                ("tag", args) => Self::edit_tags(&mut context, args),
+                ("art", []) => {
+                    if let Some(track) = player.status().and_then(|status| status.current_track) {
+                        let protocol = artwork.unwrap_or_else(ArtworkProtocol::detect);
+                        let image = MetadataService.read_artwork(&track);
+                        Self::show_artwork(&track, image.as_ref(), protocol);
+                    }
+                }
+                ("art", ["off"]) => artwork = None,
+                ("art", [mode]) => match ArtworkProtocol::from_name(mode) {
+                    Some(protocol) => artwork = Some(protocol),
+                    None => println!("Usage: art [on|off|auto|blocks|sixel|kitty]"),
+                },
+                ("q", []) => break, // break is enought nothing will happen after here in the programm
+                (command, args) => {
+                    let handled = search.run(&mut context, command, args)
+                        || HistoryView.run(&mut context, command, args)
+                        || browse.run(&mut context, command, args)
+                        || bookmarks.run(&mut context, command, args)
+                        || SmartView.run(&mut context, &mut search, command, args)
+                        || RatingView.run(&mut context, &mut search, command, args);
+                    if !handled {
+                        println!("Invalid command, `help` lists the commands.");
+                    }
+                }
+            }
+        }
+    }
 
-            match input {
-                "p" => player_controller.toggle_play(),
-                "j" => player_controller.prev(),
-                "k" => player_controller.next(),
-                "q" => break, // break is enought nothing will happen after here in the programm
-                _ => println!("Invalid command"),
+    /// Prints the events the player reported since the last call.
+    /// Only track changes, errors and running out of tracks are shown, position ticks
+    /// would flood the prompt.
+    /// Track changes show the cover art too if `artwork` is set.
+    fn show_events(events: &Receiver<Event>, artwork: Option<ArtworkProtocol>) {
+        while let Ok(event) = events.try_recv() {
+            match event {
+                Event::TrackChanged { path, metadata, .. } => {
+                    println!("\n🎶 Now playing: {}", Self::describe(&metadata, &path));
+                    if let Some(protocol) = artwork {
+                        Self::show_artwork(&path, metadata.artwork.as_deref(), protocol);
+                    }
+                }
+                Event::Error(message) => println!("\n⚠️  {}", message),
+                Event::BookmarkAdded { path, bookmark } => println!(
+                    "\n🔖 {} at {} in {}",
+                    bookmark.name,
+                    Self::format_time(bookmark.position),
+                    Self::track_name(&path)
+                ),
+                Event::NothingToPlay => println!(
+                    "\n📭 Nothing to play. Add files to the folder, or use `search` or `browse`."
+                ),
+                _ => {}
             }
         }
     }
 
-    /// Prompts the user to enter a valid folder path containing audio files.
+    /// Applies the changes of the watched folder since the last call, then saves the
+    /// library, and the bookmarks if files were renamed (they moved with the files).
+    fn apply_folder_changes(context: &mut CliContext) {
+        let Some(watcher) = context.watcher else {
+            return;
+        };
+        let changes = watcher.changes();
+        for change in &changes {
+            Self::apply_folder_change(context, watcher, change);
+        }
+        if !changes.is_empty() {
+            if let Err(err) = context.library_service.save(context.library) {
+                println!("\n⚠️  Could not save the library: {}", err);
+            }
+        }
+        let renamed = |change: &FolderChange| matches!(change, FolderChange::Renamed { .. });
+        if changes.iter().any(renamed) {
+            Self::save_bookmarks(context);
+        }
+    }
+
+    /// Applies a change of the watched folder to the library and the player: new and
+    /// rewritten files are read (when they are tracks by the `ScanOptions`), deleted ones
+    /// removed, renamed ones moved.
+    fn apply_folder_change(
+        context: &mut CliContext,
+        watcher: &WatchService,
+        change: &FolderChange,
+    ) {
+        let player = context.player;
+        let library_service = context.library_service;
+        let audio_folder_service = context.audio_folder_service;
+        let library = &mut *context.library;
+        let changed = match change {
+            FolderChange::Changed(path) => path,
+            FolderChange::Removed(path) => {
+                let gone: Vec<PathBuf> = library
+                    .query(|entry| entry.path.starts_with(path))
+                    .into_iter()
+                    .map(|entry| entry.path.clone())
+                    .collect();
+                for track in &gone {
+                    library.remove(track);
+                }
+                if !gone.is_empty() {
+                    println!("\n➖ Removed {} tracks: {}", gone.len(), path.display());
+                }
+                player.send(Command::RemoveTracks(path.clone()));
+                return;
+            }
+            FolderChange::Renamed { from, to } => {
+                library.rename(from, to);
+                player.send(Command::RenameTracks(from.clone(), to.clone()));
+                to // Files written under the new name (a replaced file) are read below.
+            }
+        };
+
+        let Some(root) = watcher.root_of(changed) else {
+            return;
+        };
+        let files = if changed.is_dir() {
+            audio_folder_service.load_audio_files(&changed.to_string_lossy())
+        } else {
+            vec![changed.clone()]
+        };
+        for file in files
+            .iter()
+            .filter(|file| audio_folder_service.accepts(root, file))
+        {
+            let update = library_service.update_file(library, file);
+            let Some(entry) = library.get(file) else {
+                continue;
+            };
+            let metadata = entry.metadata.clone();
+            match update {
+                FileUpdate::Added => {
+                    println!("\n➕ New track: {}", Self::describe(&metadata, file));
+                    player.send(Command::AddTrack(file.clone(), metadata));
+                }
+                FileUpdate::Updated => player.send(Command::UpdateMetadata(file.clone(), metadata)),
+                FileUpdate::Unchanged | FileUpdate::Missing => {}
+            }
+        }
+    }
+
+    /// Prints the folder tracks, numbered from 1, marking the one at `current_index`.
+    /// Tracks with tags show artist and title next to the file name.
+    fn show_tracks(tracks: &[(PathBuf, TrackMetadata)], current_index: usize) {
+        for (index, (track, metadata)) in tracks.iter().enumerate() {
+            let marker = if index == current_index { "▶" } else { " " };
+            let name = Self::track_name(track);
+            match Self::display_name(metadata, track) {
+                title if title != name => {
+                    println!("{} {:>3}. {} ({})", marker, index + 1, title, name)
+                }
+                _ => println!("{} {:>3}. {}", marker, index + 1, name),
+            }
+        }
+    }
+
+    /// Prints the current track and the player state, then the queued tracks, numbered
+    /// from 1 in play order.
+    fn show_queue(status: &PlayerStatus) {
+        let position = status.position.unwrap_or_default().as_secs();
+        println!(
+            "{} {} [{}:{:02}] (shuffle {}, repeat {:?})",
+            if status.is_playing { "▶" } else { "⏸" },
+            status
+                .current_track
+                .as_deref()
+                .map_or_else(|| "(no tracks)".to_string(), Self::track_name),
+            position / 60,
+            position % 60,
+            if status.shuffle { "on" } else { "off" },
+            status.repeat
+        );
+        if status.queue.is_empty() {
+            println!("The queue is empty.");
+        }
+        for (position, track) in status.queue.iter().enumerate() {
+            println!("  {:>3}. {}", position + 1, Self::track_name(track));
+        }
+    }
+
+    /// Writes the folder tracks (or the play queue) to a playlist file, with the titles and
+    /// durations of the tracks (from their tags, or from the playlist they were opened from).
+    fn export_playlist(player: &AsyncPlayerController, path: &Path, queue_only: bool) {
+        let Some(folder_tracks) = player.metadata() else {
+            return;
+        };
+        let tracks: Vec<(PathBuf, TrackMetadata)> = if queue_only {
+            let Some(status) = player.status() else {
+                return;
+            };
+            // A folder track has its metadata already, unless it is split into CUE tracks.
+            let read = |track: PathBuf| {
+                let mut matches = folder_tracks.iter().filter(|(other, _)| *other == track);
+                let metadata = match (matches.next(), matches.next()) {
+                    (Some((_, metadata)), None) => metadata.clone(),
+                    _ => MetadataService.read(&track),
+                };
+                (track, metadata)
+            };
+            status.queue.into_iter().map(read).collect()
+        } else {
+            folder_tracks
+        };
+        let entries: Vec<PlaylistEntry> = tracks
+            .into_iter()
+            .map(|(track, metadata)| PlaylistEntry {
+                title: metadata
+                    .title
+                    .is_some()
+                    .then(|| Self::display_name(&metadata, &track)),
+                duration: metadata.duration,
+                ..PlaylistEntry::new(track)
+            })
+            .collect();
+        match PlaylistService.save(path, &entries) {
+            Ok(()) => println!("💾 Saved {} tracks to {}", entries.len(), path.display()),
+            Err(err) => println!("⚠️  Could not save the playlist: {}", err),
+        }
+    }
+
+    /// Returns the file name of a track for display.
+    pub fn track_name(track: &Path) -> String {
+        track
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Returns `Artist - Title` from the tags, the title alone without an artist, or the
+    /// file name for untagged tracks.
+    pub fn display_name(metadata: &TrackMetadata, track: &Path) -> String {
+        match (&metadata.artist, &metadata.title) {
+            (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+            (None, Some(title)) => title.clone(),
+            _ => Self::track_name(track),
+        }
+    }
+
+    /// Returns `display_name` followed by the album and year and the rating, if known:
+    /// `Artist - Title (Album, 1997) ★★★★☆`.
+    pub fn describe(metadata: &TrackMetadata, track: &Path) -> String {
+        let name = Self::display_name(metadata, track);
+        let name = match (&metadata.album, metadata.year) {
+            (Some(album), Some(year)) => format!("{} ({}, {})", name, album, year),
+            (Some(album), None) => format!("{} ({})", name, album),
+            (None, Some(year)) => format!("{} ({})", name, year),
+            (None, None) => name,
+        };
+        match metadata.rating {
+            Some(_) => format!("{} {}", name, Self::format_stars(metadata.rating)),
+            None => name,
+        }
+    }
+
+    /// Replaces the folder tracks with library tracks (CUE sheets split) and plays the
+    /// one at `start`.
+    pub fn play_tracks(player: &AsyncPlayerController, tracks: &[&LibraryEntry], start: usize) {
+        if start >= tracks.len() {
+            println!("⚠️  No track {}, see `browse`.", start + 1);
+            return;
+        }
+        let mut audio_model = AudioFolderModel::from_library(tracks);
+        audio_model.current_index = start;
+        audio_model.split_cue_sheets(&CueService.sheets_for(&audio_model.files));
+        player.send(Command::PlayTracks(audio_model));
+    }
+
+    /// Saves the bookmarks as the player holds them (after the commands sent so far).
+    pub fn save_bookmarks(context: &CliContext) {
+        if let Some(bookmarks) = context.player.bookmarks() {
+            if let Err(err) = context.bookmark_service.save(&bookmarks) {
+                println!("⚠️  Could not save the bookmarks: {}", err);
+            }
+        }
+    }
+
+    /// Formats a rating as five stars, `★★★★☆` for 4.
+    pub fn format_stars(rating: Option<u8>) -> String {
+        let stars = usize::from(rating.unwrap_or(0).min(5));
+        format!("{}{}", "★".repeat(stars), "☆".repeat(5 - stars))
+    }
+
+    /// Formats a position as `m:ss`, or `h:mm:ss` from an hour on.
+    pub fn format_time(time: Duration) -> String {
+        let seconds = time.as_secs();
+        match seconds / 3600 {
+            0 => format!("{}:{:02}", seconds / 60, seconds % 60),
+            hours => format!("{}:{:02}:{:02}", hours, seconds / 60 % 60, seconds % 60),
+        }
+    }
+
+    /// Turns the arguments of `enqueue`/`playnext` into a track: a number picks a folder
+    /// track (as shown by `list`), anything else is taken as a file path.
+    ///
+    /// # Returns
+    /// `Some(PathBuf)` of an existing track, otherwise `None` (after printing why).
+    fn resolve_track(player: &AsyncPlayerController, args: &[&str]) -> Option<PathBuf> {
+        if let [number] = args {
+            if let Ok(number) = number.parse::<usize>() {
+                let track = player.tracks()?.get(number.wrapping_sub(1)).cloned();
+                if track.is_none() {
+                    println!("⚠️  No track number {}, see `list`.", number);
+                }
+                return track;
+            }
+        }
+        let path = PathBuf::from(args.join(" "));
+        if path.is_file() {
+            Some(path)
+        } else {
+            println!("⚠️  No such file: {}", path.display());
+            None
+        }
+    }
+
+    /// Shows or edits the tags of a track: `tag [<number|path>]`,
+    /// `tag [<number|path>] set <field> <value>` or `tag [<number|path>] clear <field>`.
+    /// Without a number or path the current track is used. Edited tags are read into the
+    /// library, which is saved, and handed to the player.
+    fn edit_tags(context: &mut CliContext, args: &[&str]) {
+        let player = context.player;
+        let action = args
+            .iter()
+            .position(|&arg| arg == "set" || arg == "clear")
+            .unwrap_or(args.len());
+        let track = match &args[..action] {
+            [] => match player.status().and_then(|status| status.current_track) {
+                Some(track) => track,
+                None => {
+                    println!("⚠️  No track is loaded, give a track number or a file.");
+                    return;
+                }
+            },
+            target => match Self::resolve_track(player, target) {
+                Some(track) => track,
+                None => return,
+            },
+        };
+
+        let change = match &args[action..] {
+            [] => {
+                Self::show_tags(&track);
+                return;
+            }
+            ["set", field, value @ ..] if !value.is_empty() => {
+                let value = value.join(" ");
+                let value = value.trim_matches('"').to_string();
+                TagField::from_name(field).map(|field| (field, Some(value)))
+            }
+            ["clear", field] => TagField::from_name(field).map(|field| (field, None)),
+            _ => None,
+        };
+        let Some(change) = change else {
+            println!("Usage: tag [<number|path>] set <title|artist|album|albumartist|track|disc|year|genre|rating> <value> | clear <field>");
+            return;
+        };
+        let rating_changed = change.0 == TagField::Rating;
+        if let Err(err) = TagService.write(&track, &[change]) {
+            println!("⚠️  Could not write the tags: {}", err);
+            return;
+        }
+        let mut metadata = MetadataService.read(&track);
+        println!("🏷️  Saved: {}", Self::display_name(&metadata, &track));
+
+        // The library keeps its own rating when the tags have none, a cleared rating tag
+        // has to clear it too.
+        context.library_service.update_file(context.library, &track);
+        if rating_changed {
+            context.library.set_rating(&track, metadata.rating);
+        }
+        if let Some(entry) = context.library.get(&track) {
+            metadata = entry.metadata.clone();
+        }
+        player.send(Command::UpdateMetadata(track, metadata));
+        if let Err(err) = context.library_service.save(context.library) {
+            println!("⚠️  Could not save the library: {}", err);
+        }
+    }
+
+    /// Draws the cover art of a track, or says it has none.
+    fn show_artwork(track: &Path, artwork: Option<&Artwork>, protocol: ArtworkProtocol) {
+        let Some(artwork) = artwork else {
+            println!("No cover art for {}", Self::track_name(track));
+            return;
+        };
+        match ArtworkView.render(artwork, protocol) {
+            Ok(image) => print!("{}", image),
+            Err(err) => println!("⚠️  Could not show the cover art: {}", err),
+        }
+    }
+
+    /// Prints the tags of a track.
+    fn show_tags(track: &Path) {
+        let metadata = MetadataService.read(track);
+        let number = |n: Option<u32>| n.map(|n| n.to_string());
+        println!("{}", track.display());
+        for (name, value) in [
+            ("title", metadata.title),
+            ("artist", metadata.artist),
+            ("album", metadata.album),
+            ("albumartist", metadata.album_artist),
+            ("track", number(metadata.track_number)),
+            ("disc", number(metadata.disc_number)),
+            ("year", number(metadata.year)),
+            ("genre", metadata.genre),
+            ("rating", number(metadata.rating.map(u32::from))),
+        ] {
+            println!("  {:<12} {}", name, value.unwrap_or_default());
+        }
+    }
+
+    /// Parses a 1-based number as typed in the CLI (a queue position, a search result, ...)
+    /// into a 0-based index.
+    pub fn parse_position(arg: &str) -> Option<usize> {
+        arg.parse::<usize>().ok()?.checked_sub(1)
+    }
+
+    /// Parses the argument of the `seek` command. A leading `+` or `-` seeks relative to
+    /// the current position.
+    ///
+    /// # Returns
+    /// `Some(Command)` with the matching seek command, otherwise `None`.
+    fn parse_seek(arg: &str) -> Option<Command> {
+        let seconds: f64 = arg.parse().ok()?;
+        if !seconds.is_finite() {
+            return None;
+        }
+        if arg.starts_with('+') || arg.starts_with('-') {
+            Some(Command::SeekBy(seconds))
+        } else {
+            Duration::try_from_secs_f64(seconds).ok().map(Command::Seek)
+        }
+    }
+
+    /// Parses the argument of the `vol` command, a percentage or a change of the current
+    /// volume (`+10`, `-10`).
+    ///
+    /// # Returns
+    /// `Some(f32)` with the new volume from `0.0` to `1.0`, otherwise `None`.
+    fn parse_volume(player: &AsyncPlayerController, arg: &str) -> Option<f32> {
+        let percent: f32 = arg.parse().ok()?;
+        if !percent.is_finite() {
+            return None;
+        }
+        if arg.starts_with('+') || arg.starts_with('-') {
+            let volume = player.status()?.volume + percent / 100.0;
+            Some(volume.clamp(0.0, 1.0))
+        } else {
+            (0.0..=100.0).contains(&percent).then_some(percent / 100.0)
+        }
+    }
+
+    /// Parses the arguments of the `c` command into crossfade settings.
+    ///
+    /// # Returns
+    /// `Some(CrossfadeModel)` for a valid duration (and optional curve name), otherwise `None`.
+    fn parse_crossfade(args: &[&str]) -> Option<CrossfadeModel> {
+        let seconds: f64 = args.first()?.parse().ok()?;
+        if !(0.0..=12.0).contains(&seconds) {
+            return None;
+        }
+        let curve = match args.get(1) {
+            Some(name) => FadeCurve::from_name(name)?,
+            None => FadeCurve::EqualPower,
+        };
+        Some(CrossfadeModel::new(Duration::from_secs_f64(seconds), curve))
+    }
+
+    /// Prompts the user to enter a valid folder path containing audio files, or the path
+    /// of a playlist file (`.m3u` / `.m3u8`, `.pls`, `.xspf`), until one opens (see
+    /// `open_source`).
     ///
     /// # Parameters
     /// - `audio_folder_service`: A reference to the `AudioFolderService` to load audio files.
+    /// - `library_service`: Saves the library after a folder was scanned into it.
+    /// - `library`: The music library. A folder is rescanned into it (only new and changed
+    ///   files are read) and played from it.
     ///
     /// # Returns
-    /// A `Vec<PathBuf>` containing the paths of valid audio files.
-    pub fn read_folder_input(audio_folder_service: &AudioFolderService) -> Vec<std::path::PathBuf> {
+    /// The tracks and the absolute path of the folder or playlist, as `open_source` returns them.
+    pub fn read_folder_input(
+        audio_folder_service: &AudioFolderService,
+        library_service: &LibraryService,
+        library: &mut LibraryModel,
+    ) -> (AudioFolderModel, PathBuf) {
         loop {
-            print!("📁 Enter the folder path containing audio files(q to quit): ");
+            print!("📁 Enter the folder path containing audio files or a playlist (q to quit): ");
             io::stdout().flush().unwrap(); // Ensure prompt is displayed immediately.
 
             let mut folder = String::new();
@@ -69,14 +740,119 @@ impl CliView {
                 std::process::exit(0); // Exits the program
             }
 
-            // Use the service to load audio files from the provided path.
-            let audio_files = audio_folder_service.load_audio_files(&folder);
+            if let Some(opened) = Self::open_source(
+                audio_folder_service,
+                library_service,
+                library,
+                Path::new(&folder),
+            ) {
+                return opened;
+            }
+        }
+    }
+
+    /// Opens a folder or playlist file as the tracks to play.
+    ///
+    /// # Parameters
+    /// - `audio_folder_service`: A reference to the `AudioFolderService` to load audio files.
+    /// - `library_service`: Saves the library after a folder was scanned into it.
+    /// - `library`: The music library. A folder is rescanned into it (only new and changed
+    ///   files are read) and played from it.
+    /// - `path`: The folder or playlist file.
+    ///
+    /// # Returns
+    /// `Some` with an `AudioFolderModel` holding the valid audio files, files with a CUE sheet
+    /// split into its tracks, and the absolute path of the folder or playlist. Playlist
+    /// entries whose file is missing are listed before it returns. A folder without tracks
+    /// is accepted, the model is empty until files are added to it. `None` after printing
+    /// why if the path cannot be played.
+    pub fn open_source(
+        audio_folder_service: &AudioFolderService,
+        library_service: &LibraryService,
+        library: &mut LibraryModel,
+        path: &Path,
+    ) -> Option<(AudioFolderModel, PathBuf)> {
+        let Ok(source) = fs::canonicalize(path) else {
+            println!("⚠️  No audio files found or invalid path. Please try again.");
+            return None;
+        };
+        let mut audio_model = if PlaylistService::is_playlist(&source) && source.is_file() {
+            match PlaylistService.load(&source) {
+                Ok(playlist) => {
+                    for missing in &playlist.missing {
+                        println!("⚠️  Playlist entry not found: {}", missing);
+                    }
+                    let mut audio_model = AudioFolderModel::from_playlist(&playlist);
+                    audio_model.attach_metadata(|track| MetadataService.read(track));
+                    if audio_model.is_empty() {
+                        println!("⚠️  No audio files found in the playlist. Please try again.");
+                        return None;
+                    }
+                    audio_model
+                }
+                Err(err) => {
+                    println!("⚠️  Could not read the playlist: {}", err);
+                    return None;
+                }
+            }
+        } else if source.is_dir() {
+            let summary = library_service.scan(library, &source, audio_folder_service);
+            println!(
+                "📚 Library: {} new, {} changed, {} removed, {} unchanged",
+                summary.added, summary.updated, summary.removed, summary.unchanged
+            );
+            if let Err(err) = library_service.save(library) {
+                println!("⚠️  Could not save the library: {}", err);
+            }
+            let audio_model = AudioFolderModel::from_library(
+                &library.query(|entry| entry.path.starts_with(&source)),
+            );
+            if audio_model.is_empty() {
+                // An existing folder without tracks yet, the watcher picks up new files.
+                println!("📭 No audio files in {} yet.", source.display());
+            }
+            audio_model
+        } else {
+            println!("⚠️  No audio files found or invalid path. Please try again.");
+            return None;
+        };
+
+        // Split single-file album rips into the tracks of their CUE sheets.
+        audio_model.split_cue_sheets(&CueService.sheets_for(&audio_model.files));
+        Some((audio_model, source))
+    }
 
-            if audio_files.is_empty() {
-                println!("⚠️  No audio files found or invalid path. Please try again.");
-            } else {
-                return audio_files; // Return valid audio files.
+    /// Offers to pick up the session saved when the player last quit.
+    ///
+    /// # Returns
+    /// `Some(SessionModel)` if there is a session whose folder or playlist still exists and
+    /// the user wants to resume it, otherwise `None`.
+    pub fn offer_session(session_service: &SessionService) -> Option<SessionModel> {
+        let session = match session_service.load() {
+            Ok(session) => session?,
+            Err(err) => {
+                println!("⚠️  Could not read the last session: {}", err);
+                return None;
             }
+        };
+        if !session.source.exists() {
+            return None;
         }
+        let position = session.position.as_secs();
+        print!(
+            "⏯️  Resume {} at {} [{}:{:02}]? [Y/n]: ",
+            session.source.display(),
+            session
+                .track
+                .as_deref()
+                .map_or_else(|| "the start".to_string(), Self::track_name),
+            position / 60,
+            position % 60
+        );
+        io::stdout().flush().unwrap();
+
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer).unwrap();
+        matches!(answer.trim().to_lowercase().as_str(), "" | "y" | "yes").then_some(session)
     }
 }
@@ -0,0 +1,102 @@
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::modules::{
+    models::history_model::HistoryModel,
+    models::library_model::LibraryModel,
+    views::cli_view::{CliContext, CliView},
+};
+
+/// Number of playbacks listed by `history` and tracks by `top` without a count.
+const HISTORY_SHOWN: usize = 20;
+
+/// The commands of the history view, as listed by `help`.
+pub const HISTORY_HELP: &str = "\
+🕘 History
+  history [n]             List the last n (20) playbacks, newest first
+  top [n]                 List the n (20) most played tracks with play and skip counts";
+
+/// **HistoryView Struct**
+///
+/// Shows the play history and the most played tracks.
+pub struct HistoryView;
+
+impl HistoryView {
+    /// Runs a history command (`history`, `top`).
+    ///
+    /// # Returns
+    /// `true` if `command` is one of them, `false` to let another view try it.
+    pub fn run(&self, context: &mut CliContext, command: &str, args: &[&str]) -> bool {
+        let (command @ ("history" | "top"), [] | [_]) = (command, args) else {
+            return false;
+        };
+        let count = match args.first().map(|count| count.parse::<usize>()) {
+            None => HISTORY_SHOWN,
+            Some(Ok(count)) => count,
+            Some(Err(_)) => {
+                println!("Usage: {} [count]", command);
+                return true;
+            }
+        };
+        match context.history_service.load() {
+            Ok(history) if command == "history" => {
+                Self::show_history(&history, context.library, count)
+            }
+            Ok(history) => Self::show_top(&history, context.library, count),
+            Err(err) => println!("⚠️  Could not read the history: {}", err),
+        }
+        true
+    }
+
+    /// Prints the last `count` playbacks, newest first.
+    fn show_history(history: &HistoryModel, library: &LibraryModel, count: usize) {
+        if history.is_empty() {
+            println!("Nothing played yet.");
+        }
+        for record in history.recent().take(count) {
+            println!(
+                "{:>10}  {} {:>7}  {}",
+                Self::format_ago(record.started),
+                if record.skipped { "⏭️ " } else { "✅" },
+                CliView::format_time(record.listened),
+                Self::library_name(library, &record.path)
+            );
+        }
+    }
+
+    /// Prints the `count` most played tracks.
+    fn show_top(history: &HistoryModel, library: &LibraryModel, count: usize) {
+        if history.is_empty() {
+            println!("Nothing played yet.");
+        }
+        for (number, (path, stats)) in history.top().into_iter().take(count).enumerate() {
+            println!(
+                "{:>3}. {} ({} plays, {} skips, last {})",
+                number + 1,
+                Self::library_name(library, path),
+                stats.plays,
+                stats.skips,
+                stats.last_played.map_or_else(String::new, Self::format_ago)
+            );
+        }
+    }
+
+    /// Returns `Artist - Title` from the library tags of `track`, or its file name.
+    fn library_name(library: &LibraryModel, track: &Path) -> String {
+        match library.get(track) {
+            Some(entry) => CliView::display_name(&entry.metadata, track),
+            None => CliView::track_name(track),
+        }
+    }
+
+    /// Formats how long ago `time` was (`just now`, `5 min ago`, `3 h ago`, `2 days ago`).
+    fn format_ago(time: SystemTime) -> String {
+        let seconds = time.elapsed().unwrap_or_default().as_secs();
+        match seconds {
+            0..=59 => "just now".to_string(),
+            60..=3599 => format!("{} min ago", seconds / 60),
+            3600..=86_399 => format!("{} h ago", seconds / 3600),
+            _ => format!("{} days ago", seconds / 86_400),
+        }
+    }
+}
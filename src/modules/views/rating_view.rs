@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+
+use crate::modules::{
+    controllers::async_player_controller::Command,
+    views::cli_view::{CliContext, CliView},
+    views::search_view::SearchView,
+};
+
+/// The commands of the rating view, as listed by `help`.
+pub const RATING_HELP: &str = "\
+⭐ Ratings
+  rate [1-5|off]          Show or set the rating of the current track
+  fav                     Star the current track as a favourite, or unstar it
+  favs [play]             List the favourites, numbered like search results, or play them
+                          all, replacing the folder tracks";
+
+/// **RatingView Struct**
+///
+/// Rates and stars the current track, saving the library (and the tags if ratings are
+/// written to files), and lists or plays the favourites.
+pub struct RatingView;
+
+impl RatingView {
+    /// Runs a rating command (`rate`, `fav`, `favs`). The favourites listed by `favs`
+    /// become the results of `search`.
+    ///
+    /// # Returns
+    /// `true` if `command` is one of them, `false` to let another view try it.
+    pub fn run(
+        &self,
+        context: &mut CliContext,
+        search: &mut SearchView,
+        command: &str,
+        args: &[&str],
+    ) -> bool {
+        match (command, args) {
+            ("rate", []) => match Self::current_track(context) {
+                Some(track) => match context.library.get(&track) {
+                    Some(entry) if entry.metadata.rating.is_some() => {
+                        println!("{}", CliView::format_stars(entry.metadata.rating))
+                    }
+                    _ => println!("Not rated yet, `rate <1-5>` rates it."),
+                },
+                None => println!("⚠️  No track is loaded."),
+            },
+            ("rate", [stars]) => match *stars {
+                "off" | "0" => Self::rate(context, None),
+                stars => match stars.parse::<u8>() {
+                    Ok(stars @ 1..=5) => Self::rate(context, Some(stars)),
+                    _ => println!("Usage: rate <1-5|off>"),
+                },
+            },
+            ("fav", []) => Self::star(context),
+            ("favs", args @ ([] | ["play"])) => {
+                let favourites = context.library.favourites();
+                if favourites.is_empty() {
+                    println!("No favourites yet, `fav` stars the current track.");
+                } else if args.is_empty() {
+                    search.show(favourites);
+                } else {
+                    CliView::play_tracks(context.player, &favourites, 0);
+                    println!("⭐ Playing {} favourites.", favourites.len());
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Rates the current track in the library (and its tags if ratings are written to
+    /// files), saves the library and hands the new rating to the player.
+    fn rate(context: &mut CliContext, rating: Option<u8>) {
+        let Some(track) = Self::current_track(context) else {
+            println!("⚠️  No track is loaded.");
+            return;
+        };
+        match context
+            .library_service
+            .rate(context.library, &track, rating)
+        {
+            Ok(()) => match rating {
+                Some(_) => println!("⭐ Rated {}", CliView::format_stars(rating)),
+                None => println!("⭐ Rating removed."),
+            },
+            Err(err) => println!("⚠️  {}", err),
+        }
+        Self::save_rated(context, &track);
+    }
+
+    /// Stars the current track as a favourite, or unstars it if it is one.
+    fn star(context: &mut CliContext) {
+        let Some(track) = Self::current_track(context) else {
+            println!("⚠️  No track is loaded.");
+            return;
+        };
+        let favourite = !context
+            .library
+            .get(&track)
+            .is_some_and(|entry| entry.favourite);
+        match context
+            .library_service
+            .star(context.library, &track, favourite)
+        {
+            Ok(()) if favourite => println!("⭐ Added to the favourites."),
+            Ok(()) => println!("☆ Removed from the favourites."),
+            Err(err) => println!("⚠️  Could not star the track: {}", err),
+        }
+        Self::save_rated(context, &track);
+    }
+
+    /// Saves the library after `track` was rated or starred and hands its metadata to the
+    /// player.
+    fn save_rated(context: &CliContext, track: &Path) {
+        let Some(entry) = context.library.get(track) else {
+            return;
+        };
+        context.player.send(Command::UpdateMetadata(
+            track.to_path_buf(),
+            entry.metadata.clone(),
+        ));
+        if let Err(err) = context.library_service.save(context.library) {
+            println!("⚠️  Could not save the library: {}", err);
+        }
+    }
+
+    /// Returns the track the player is on, `None` if there is none.
+    fn current_track(context: &CliContext) -> Option<PathBuf> {
+        context.player.status()?.current_track
+    }
+}
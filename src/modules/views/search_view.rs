@@ -0,0 +1,124 @@
+use crate::modules::{
+    controllers::async_player_controller::Command,
+    models::library_model::{LibraryEntry, LibraryModel},
+    models::search_model::SearchQuery,
+    views::cli_view::{CliContext, CliView},
+};
+
+/// Number of search results printed, the others can still be picked by number.
+const SEARCH_RESULTS_SHOWN: usize = 50;
+
+/// The commands of the search view, as listed by `help`.
+pub const SEARCH_HELP: &str = "\
+🔎 Search
+  search <query>          Search the library: words, artist:, album:, title:, genre:, path:,
+                          format:, year:2010..2015, rating:4.., fav:yes, field=value for a
+                          whole field
+  splay <n>               Play result n (it is added to the folder tracks)
+  sadd <n|all>            Add results to the folder tracks
+  senqueue <n>            Add result n to the play queue";
+
+/// **SearchView Struct**
+///
+/// Searches the library and keeps the numbered results of the last listing, the results
+/// of `search` as well as the tracks listed by `favs` and `smart show`.
+#[derive(Default)]
+pub struct SearchView {
+    /// The tracks of the last listing, in the numbered order.
+    results: Vec<LibraryEntry>,
+}
+
+impl SearchView {
+    /// Runs a search command (`search`, `splay`, `sadd`, `senqueue`).
+    ///
+    /// # Returns
+    /// `true` if `command` is one of them, `false` to let another view try it.
+    pub fn run(&mut self, context: &mut CliContext, command: &str, args: &[&str]) -> bool {
+        match (command, args) {
+            ("search", args) if !args.is_empty() => self.search(context.library, &args.join(" ")),
+            ("splay", [number]) => {
+                if let Some(entry) = self.result(number) {
+                    context.player.send(Command::PlayTrack(
+                        entry.path.clone(),
+                        entry.metadata.clone(),
+                    ));
+                }
+            }
+            ("sadd", ["all"]) => {
+                for entry in &self.results {
+                    context.player.send(Command::AddTrack(
+                        entry.path.clone(),
+                        entry.metadata.clone(),
+                    ));
+                }
+                println!("➕ Added {} tracks.", self.results.len());
+            }
+            ("sadd", [number]) => {
+                if let Some(entry) = self.result(number) {
+                    context.player.send(Command::AddTrack(
+                        entry.path.clone(),
+                        entry.metadata.clone(),
+                    ));
+                }
+            }
+            ("senqueue", [number]) => {
+                if let Some(entry) = self.result(number) {
+                    context.player.send(Command::Enqueue(entry.path.clone()));
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Prints `tracks` numbered from 1 and keeps them as the results `splay`, `sadd` and
+    /// `senqueue` pick from.
+    pub fn show(&mut self, tracks: Vec<&LibraryEntry>) {
+        Self::print(&tracks);
+        self.results = tracks.into_iter().cloned().collect();
+    }
+
+    /// Searches the library and prints the results, numbered from 1. A query that cannot
+    /// be parsed prints the usage and keeps the last results.
+    fn search(&mut self, library: &LibraryModel, text: &str) {
+        let Some(query) = SearchQuery::parse(text) else {
+            println!("Usage: search <words> [artist:<name>] [album:<name>] [title:<name>] [genre:<name>] [path:<text>] [format:<ext>] [<field>=<value>] [year:<year|from..to>] [rating:<stars|from..to>] [fav:<yes|no>]");
+            return;
+        };
+        if library.is_empty() {
+            println!("The library is empty, open a folder first.");
+        }
+        let results = library.search(&query);
+        Self::print(&results[..results.len().min(SEARCH_RESULTS_SHOWN)]);
+        match results.len() {
+            0 => println!("No matches."),
+            count if count > SEARCH_RESULTS_SHOWN => println!(
+                "  ... {} more ({} matches), narrow the search to see them.",
+                count - SEARCH_RESULTS_SHOWN,
+                count
+            ),
+            count => println!("{} matches.", count),
+        }
+        self.results = results.into_iter().cloned().collect();
+    }
+
+    /// Prints tracks numbered from 1.
+    fn print(tracks: &[&LibraryEntry]) {
+        for (index, entry) in tracks.iter().enumerate() {
+            println!(
+                "  {:>3}. {}",
+                index + 1,
+                CliView::describe(&entry.metadata, &entry.path)
+            );
+        }
+    }
+
+    /// Picks a result of the last listing by its 1-based number.
+    fn result(&self, number: &str) -> Option<&LibraryEntry> {
+        let entry = CliView::parse_position(number).and_then(|index| self.results.get(index));
+        if entry.is_none() {
+            println!("⚠️  No search result {}, see `search`.", number);
+        }
+        entry
+    }
+}
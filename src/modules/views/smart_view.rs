@@ -0,0 +1,102 @@
+use crate::modules::{
+    models::history_model::HistoryModel,
+    models::smart_playlist_model::SmartPlaylist,
+    views::cli_view::{CliContext, CliView},
+    views::search_view::SearchView,
+};
+
+/// The commands of the smart playlist view, as listed by `help`.
+pub const SMART_HELP: &str = "\
+✨ Smart playlists
+  smart                   List the smart playlists with their rules and track counts
+  smart set <name> <rules>
+                          Create or change one, e.g. `smart set best rating:4.. lastplayed:30d`
+                          (search filters, played:, lastplayed:, sort:[-]<key>, limit:)
+  smart show <name>       List its tracks, numbered like search results
+  smart play <name>       Play its tracks, replacing the folder tracks
+  smart del <name>        Delete a smart playlist";
+
+/// **SmartView Struct**
+///
+/// Defines, lists and plays smart playlists (see `SmartPlaylist`), evaluated against the
+/// library and the play history.
+pub struct SmartView;
+
+impl SmartView {
+    /// Runs a `smart` command. The tracks of `smart show` become the results of `search`.
+    ///
+    /// # Returns
+    /// `true` if `command` is `smart`, `false` to let another view try it.
+    pub fn run(
+        &self,
+        context: &mut CliContext,
+        search: &mut SearchView,
+        command: &str,
+        args: &[&str],
+    ) -> bool {
+        let service = context.smart_playlist_service;
+        match (command, args) {
+            ("smart", []) => match service.list() {
+                Ok(playlists) if playlists.is_empty() => {
+                    println!("No smart playlists yet, `smart set <name> <rules>` adds one.")
+                }
+                Ok(playlists) => {
+                    let history = Self::load_history(context);
+                    for playlist in playlists {
+                        let count = playlist.evaluate(context.library, &history).len();
+                        println!("  {}: {} ({} tracks)", playlist.name, playlist.rules, count);
+                    }
+                }
+                Err(err) => println!("⚠️  Could not read the smart playlists: {}", err),
+            },
+            ("smart", ["set", name, rules @ ..]) if !rules.is_empty() => {
+                let Some(playlist) = SmartPlaylist::parse(name, &rules.join(" ")) else {
+                    println!("Usage: smart set <name> <search filters> [played:<count|from..to>] [lastplayed:<age|from..to>] [sort:[-]<name|duration|rating|plays|lastplayed|year|mtime>] [limit:<count>]");
+                    return true;
+                };
+                match service.save(&playlist) {
+                    Ok(()) => {
+                        let history = Self::load_history(context);
+                        let count = playlist.evaluate(context.library, &history).len();
+                        println!("✨ Saved {}, {} tracks match now.", playlist.name, count);
+                    }
+                    Err(err) => println!("⚠️  Could not save the smart playlist: {}", err),
+                }
+            }
+            ("smart", ["del", name]) => match service.remove(name) {
+                Ok(true) => println!("🗑️  Deleted {}.", name),
+                Ok(false) => println!("⚠️  No smart playlist {}, see `smart`.", name),
+                Err(err) => println!("⚠️  Could not delete the smart playlist: {}", err),
+            },
+            ("smart", [action @ ("show" | "play"), name]) => {
+                let playlist = match service.load(name) {
+                    Ok(playlist) => playlist,
+                    Err(err) => {
+                        println!("⚠️  {}", err);
+                        return true;
+                    }
+                };
+                let history = Self::load_history(context);
+                let tracks = playlist.evaluate(context.library, &history);
+                if tracks.is_empty() {
+                    println!("Nothing matches {} right now.", playlist.name);
+                } else if *action == "play" {
+                    CliView::play_tracks(context.player, &tracks, 0);
+                    println!("✨ Playing {} ({} tracks).", playlist.name, tracks.len());
+                } else {
+                    search.show(tracks);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Loads the play history for evaluating smart playlists, an empty one if it cannot be read.
+    fn load_history(context: &CliContext) -> HistoryModel {
+        context.history_service.load().unwrap_or_else(|err| {
+            println!("⚠️  Could not read the history, ignoring it: {}", err);
+            HistoryModel::default()
+        })
+    }
+}